@@ -8,55 +8,301 @@
 //! literals are just symbols:
 //! <literal>    ::= [A-Za-z]
 //!
+//! Whitespace may go between any two symbols.
+//!
 //! # Design
 //! Appearance of variants is similar to the order of BNF above.
-//! Variants in boxes form a tree.
+//! Variants in boxes form a tree. Brackets only group, so they have no variant:
+//! `Display` puts them back where the tree needs them.
 use std::{
+	collections::BTreeSet,
 	fmt::{Display, Formatter},
 	str::FromStr
 };
 
-use itertools::Itertools;
-use winnow::combinator::todo;
+use winnow::{
+	ascii::multispace0,
+	combinator::{cut_err, delimited, eof, opt, preceded, terminated},
+	error::{ContextError, StrContext, StrContextValue},
+	token::one_of,
+	ModalResult,
+	Parser
+};
 
-enum Expr {
-	And(Box<Expr>, Box<Expr>),
-	Or(Box<Expr>, Box<Expr>),
-	Primed(Box<Expr>),
-	Parenthesised(Box<Expr>),
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprNode {
+	Or(Box<ExprNode>, Box<ExprNode>),
+	And(Box<ExprNode>, Box<ExprNode>),
+	Primed(Box<ExprNode>),
 	Literal(char)
 }
 
-impl Display for Expr {
+impl ExprNode {
+	/// Every letter in the expression, once, in alphabetical order (capitals first).
+	pub fn variables(&self) -> Vec<char> {
+		fn collect(expr: &ExprNode, into: &mut BTreeSet<char>) {
+			match expr {
+				ExprNode::Or(a, b) | ExprNode::And(a, b) => {
+					collect(a, into);
+					collect(b, into);
+				}
+				ExprNode::Primed(t) => collect(t, into),
+				ExprNode::Literal(c) => {
+					into.insert(*c);
+				}
+			}
+		}
+		let mut variables = BTreeSet::new();
+		collect(self, &mut variables);
+		variables.into_iter().collect()
+	}
+
+	/// The value of the expression, given the value of every letter.
+	pub fn evaluate(&self, value: &impl Fn(char) -> bool) -> bool {
+		match self {
+			ExprNode::Or(a, b) => a.evaluate(value) || b.evaluate(value),
+			ExprNode::And(a, b) => a.evaluate(value) && b.evaluate(value),
+			ExprNode::Primed(t) => !t.evaluate(value),
+			ExprNode::Literal(c) => value(*c)
+		}
+	}
+
+	/// How tightly the variant binds, OR being the loosest.
+	fn precedence(&self) -> u8 {
+		match self {
+			ExprNode::Or(..) => 0,
+			ExprNode::And(..) => 1,
+			ExprNode::Primed(_) | ExprNode::Literal(_) => 2
+		}
+	}
+
+	/// Writes the expression, bracketed if it binds looser than `precedence`.
+	fn write(&self, f: &mut Formatter<'_>, precedence: u8) -> std::fmt::Result {
+		if self.precedence() < precedence {
+			write!(f, "(")?;
+			self.write(f, 0)?;
+			return write!(f, ")");
+		}
+		// both operators group to the left, so a right operand of the same kind needs brackets
+		match self {
+			ExprNode::Or(a, b) => {
+				a.write(f, 0)?;
+				write!(f, "+")?;
+				b.write(f, 1)
+			}
+			ExprNode::And(a, b) => {
+				a.write(f, 1)?;
+				b.write(f, 2)
+			}
+			ExprNode::Primed(t) => {
+				t.write(f, 2)?;
+				write!(f, "'")
+			}
+			ExprNode::Literal(c) => write!(f, "{}", c)
+		}
+	}
+}
+
+impl Display for ExprNode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		self.write(f, 0)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	/// Counting characters from 1.
+	pub column:  usize,
+	pub message: String
+}
+
+impl Display for ParseError {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", match self {
-			Expr::Or(a, b) => format!("{}+{}", a, b),
-			Expr::And(a, b) => format!("{}{}", a, b),
-			Expr::Parenthesised(t) => format!("({})", t),
-			Expr::Primed(t) => format!("{}'", t),
-			Expr::Literal(c) => format!("{}", c)
+		write!(f, "column {}: {}", self.column, self.message)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+fn expected(what: &'static str) -> StrContext {
+	StrContext::Expected(StrContextValue::Description(what))
+}
+
+fn expression(input: &mut &str) -> ModalResult<ExprNode> {
+	let mut expr = term(input)?;
+	while opt(preceded(multispace0, '+')).parse_next(input)?.is_some() {
+		let right = cut_err(term).parse_next(input)?;
+		expr = ExprNode::Or(Box::new(expr), Box::new(right));
+	}
+	Ok(expr)
+}
+
+fn term(input: &mut &str) -> ModalResult<ExprNode> {
+	let mut expr = factor(input)?;
+	while let Some(right) = opt(factor).parse_next(input)? {
+		expr = ExprNode::And(Box::new(expr), Box::new(right));
+	}
+	Ok(expr)
+}
+
+fn factor(input: &mut &str) -> ModalResult<ExprNode> {
+	let start = preceded(multispace0, one_of(|c: char| c.is_ascii_alphabetic() || c == '('))
+		.context(expected("a letter or `(`"))
+		.parse_next(input)?;
+	let mut expr = match start {
+		'(' => cut_err(terminated(
+			expression,
+			preceded(multispace0, ')').context(expected("`)`"))
+		))
+		.parse_next(input)?,
+		c => ExprNode::Literal(c)
+	};
+	while opt(preceded(multispace0, '\'')).parse_next(input)?.is_some() {
+		expr = ExprNode::Primed(Box::new(expr));
+	}
+	Ok(expr)
+}
+
+/// What went wrong at `offset` bytes into `s`.
+fn describe(s: &str, offset: usize, error: &ContextError) -> ParseError {
+	let expected = error
+		.context()
+		.filter_map(|context| match context {
+			StrContext::Expected(value) => Some(value.to_string()),
+			_ => None
 		})
+		.collect::<Vec<_>>();
+	let found = match s[offset..].chars().next() {
+		Some(c) => format!("`{}`", c),
+		None => "the end".to_string()
+	};
+	let message = match expected.is_empty() {
+		true => format!("unexpected {}", found),
+		false => format!("expected {}, found {}", expected.join(" or "), found)
+	};
+	ParseError { column: s[..offset].chars().count() + 1, message }
+}
+
+impl FromStr for ExprNode {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		terminated(expression, delimited(multispace0, eof, multispace0))
+			.parse(s)
+			.map_err(|e| describe(s, e.offset(), e.inner()))
 	}
 }
 
-/// Accepted syntax is similar to a stack based language.
-impl FromStr for Expr {
-	type Err = String;
-
-	fn from_str(s: &str) -> Result<Self, Self::Err> { 
-        s.chars().try_fold(None, |prev: Option<>, c| {
-            Some(match prev {
-                None => {
-                    match c {
-                        '+' => Err("Early `+`".to_string()),
-                        '\'' => Err("Early `'`".to_string()),
-                        _ => Err("Unsupported symbol".to_string())
-                    }
-                }
-                Some(prev) => {
-                    todo!()
-                }
-            })
-        }).ok_or("No expression to parse.".to_string()).flatten()
+#[cfg(test)]
+use proptest::prelude::*;
+
+#[cfg(test)]
+fn parse(s: &str) -> ExprNode {
+	s.parse().unwrap_or_else(|e| panic!("{}: {}", s, e))
+}
+
+#[test]
+fn parses_the_grammar() {
+	use ExprNode::*;
+	let l = |c| Box::new(Literal(c));
+
+	assert_eq!(parse("a"), Literal('a'));
+	assert_eq!(parse("a+b"), Or(l('a'), l('b')));
+	assert_eq!(parse("aB"), And(l('a'), l('B')));
+	assert_eq!(parse("a'"), Primed(l('a')));
+	assert_eq!(parse("a''"), Primed(Box::new(Primed(l('a')))));
+	assert_eq!(parse("(a)"), Literal('a'));
+	// AND binds tighter than OR, and both group to the left
+	assert_eq!(parse("a+bc"), Or(l('a'), Box::new(And(l('b'), l('c')))));
+	assert_eq!(parse("a+b+c"), Or(Box::new(Or(l('a'), l('b'))), l('c')));
+	assert_eq!(parse("abc"), And(Box::new(And(l('a'), l('b'))), l('c')));
+	assert_eq!(parse("(a+b)'c"), And(Box::new(Primed(Box::new(Or(l('a'), l('b'))))), l('c')));
+	assert_eq!(parse(" ( a + b ) ' c "), parse("(a+b)'c"));
+}
+
+#[test]
+fn displays_without_redundant_brackets() {
+	for (input, output) in [
+		("((a))", "a"),
+		("(ab)+(c)", "ab+c"),
+		("(a+b)+c", "a+b+c"),
+		("a+(b+c)", "a+(b+c)"),
+		("a(bc)", "a(bc)"),
+		("(a+b)c", "(a+b)c"),
+		("(ab)'", "(ab)'"),
+		("(a')'", "a''"),
+		("a b' + c (d + e)'", "ab'+c(d+e)'")
+	] {
+		assert_eq!(parse(input).to_string(), output, "{}", input);
+	}
+}
+
+#[test]
+fn errors_point_at_the_column() {
+	for (input, column, message) in [
+		("", 1, "expected a letter or `(`, found the end"),
+		("a+", 3, "expected a letter or `(`, found the end"),
+		("+a", 1, "expected a letter or `(`, found `+`"),
+		("a + 1", 5, "expected a letter or `(`, found `1`"),
+		("(a+b", 5, "expected `)`, found the end"),
+		("ab)", 3, "unexpected `)`"),
+		("'a", 1, "expected a letter or `(`, found `'`"),
+		("é+x", 1, "expected a letter or `(`, found `é`"),
+		("aé", 2, "unexpected `é`")
+	] {
+		assert_eq!(
+			input.parse::<ExprNode>(),
+			Err(ParseError { column, message: message.to_string() }),
+			"{}",
+			input
+		);
+	}
+}
+
+#[test]
+fn evaluates() {
+	let expr = parse("a'b + (c+a)'");
+	assert_eq!(expr.variables(), ['a', 'b', 'c']);
+	let value = |a, b, c| expr.evaluate(&|v| match v {
+		'a' => a,
+		'b' => b,
+		_ => c
+	});
+	assert!(value(false, true, true));
+	assert!(value(false, false, false));
+	assert!(!value(false, false, true));
+	assert!(!value(true, true, false));
+	assert_eq!(parse("Ba+b").variables(), ['B', 'a', 'b']);
+}
+
+#[cfg(test)]
+fn arbitrary_expr() -> impl Strategy<Value = ExprNode> {
+	arbitrary_expr_of(('a'..='z').chain('A'..='Z').collect())
+}
+
+#[cfg(test)]
+pub(crate) fn arbitrary_expr_of(letters: Vec<char>) -> impl Strategy<Value = ExprNode> {
+	let literal = proptest::sample::select(letters).prop_map(ExprNode::Literal);
+	literal.prop_recursive(8, 64, 2, |inner| {
+		let pair = (inner.clone(), inner.clone()).prop_map(|(a, b)| (Box::new(a), Box::new(b)));
+		prop_oneof![
+			pair.clone().prop_map(|(a, b)| ExprNode::Or(a, b)),
+			pair.prop_map(|(a, b)| ExprNode::And(a, b)),
+			inner.prop_map(|t| ExprNode::Primed(Box::new(t)))
+		]
+	})
+}
+
+#[cfg(test)]
+proptest! {
+	#[test]
+	fn display_round_trips(expr in arbitrary_expr()) {
+		prop_assert_eq!(expr.to_string().parse::<ExprNode>(), Ok(expr));
+	}
+
+	#[test]
+	fn whitespace_is_tolerated(expr in arbitrary_expr()) {
+		let spaced = expr.to_string().chars().map(|c| format!(" {}", c)).collect::<String>();
+		prop_assert_eq!(spaced.parse::<ExprNode>(), Ok(expr));
 	}
 }
@@ -1,16 +1,52 @@
 mod expressions;
 mod maps;
+mod truth;
 
+use clap::Parser;
 use expressions::*;
 use maps::*;
+use truth::*;
+
+/// Parses a Boolean expression, like `a'b + (c+d)'`, and prints its truth table, canonical forms
+/// and Karnaugh map.
+#[derive(Debug, Parser)]
+struct Args {
+	/// `+` is OR, writing factors next to each other is AND, and a `'` after a factor is NOT.
+	expression: String,
+	/// Rows whose output doesn't matter, like `3,7`.
+	#[arg(long, value_name = "ROWS", value_delimiter = ',')]
+	dc: Vec<usize>
+}
 
 fn main() {
-	let arg = std::env::args().nth(1).unwrap();
+	let args = Args::parse();
+	let arg = args.expression;
 	let expression = arg.parse::<ExprNode>();
 	println!("Exp (debug):\n{:?}\n", expression);
-	if let Ok(expr) = expression {
-		println!("Exp (display):\n{}\n", expr);
-		let map = Map::from(expr);
-		println!("Map:\n{}\n", map);
-	}
+	let expr = match expression {
+		Ok(expr) => expr,
+		Err(e) => {
+			eprintln!("{}\n{}^", arg, " ".repeat(e.column - 1));
+			eprintln!("Error at {}", e);
+			std::process::exit(1);
+		}
+	};
+	println!("Exp (display):\n{}\n", expr);
+
+	let table = match TruthTable::new(&expr, &args.dc) {
+		Ok(table) => table,
+		Err(e) => {
+			eprintln!("{}", e);
+			std::process::exit(1);
+		}
+	};
+	println!("Truth table:\n{}", table);
+	// without terms, the canonical forms are constants
+	let sop = table.sum_of_products().map_or("0".to_string(), |sop| sop.to_string());
+	let pos = table.product_of_sums().map_or("1".to_string(), |pos| pos.to_string());
+	println!("Sum of products:\n{}\n{}\n", table.minterm_list(), sop);
+	println!("Product of sums:\n{}\n{}\n", table.maxterm_list(), pos);
+
+	let map = Map::from(&table);
+	println!("Map:\n{}\n", map);
 }
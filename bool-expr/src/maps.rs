@@ -5,9 +5,9 @@ use std::{
 
 use itertools::Itertools;
 
-use crate::expressions::ExprNode;
+use crate::{expressions::ExprNode, truth::TruthTable};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Field {
 	One,
 	Zero,
@@ -22,8 +22,9 @@ impl Display for Field {
 		})
 	}
 }
-pub struct Map<'a> {
-	grid:       &'a [Field; 2usize.pow(4)],
+pub struct Map {
+	/// Row by row, both in gray code order.
+	grid:       Vec<Field>,
 	wrap_point: NonZero<u8>,
 	symbols:    Vec<char>
 }
@@ -48,6 +49,12 @@ impl Display for GrayCode {
 		write!(f, "{}", self.0.iter().join(""))
 	}
 }
+impl GrayCode {
+	/// The bits read as a binary number.
+	fn value(&self) -> usize {
+		self.0.iter().fold(0, |value, bit| value << 1 | (*bit == Bit::One) as usize)
+	}
+}
 
 /// Given a length `n`, returns a vec of unique graycodes ordered such that
 /// every next value is only one bit different from the previous, wrap-around
@@ -63,8 +70,10 @@ fn graycode_recursive(n: NonZero<u8>) -> Vec<GrayCode> {
 				.cloned()
 				.map(|code| GrayCode([vec![Bit::Zero], code.0].concat()))
 				.collect::<Vec<_>>();
+			// reflected, so that the halves meet at codes one bit apart
 			let one_prefixed = lower
 				.into_iter()
+				.rev()
 				.map(|code| GrayCode([vec![Bit::One], code.0].concat()))
 				.collect::<Vec<_>>();
 
@@ -72,6 +81,14 @@ fn graycode_recursive(n: NonZero<u8>) -> Vec<GrayCode> {
 		}
 	}
 }
+/// Like [`graycode_recursive`], but a length of zero gives the one empty code.
+fn graycodes(n: usize) -> Vec<GrayCode> {
+	match NonZero::new(n as u8) {
+		Some(n) => graycode_recursive(n),
+		None => vec![GrayCode(vec![])]
+	}
+}
+
 #[test]
 fn graycode_works() {
 	for bit_count in 1..=2u8.pow(3) {
@@ -85,6 +102,10 @@ fn graycode_works() {
 			graycodes.iter().all_unique(),
 			"every generated graycode is unique"
 		);
+		for (code, next) in graycodes.iter().circular_tuple_windows() {
+			let differences = code.0.iter().zip(&next.0).filter(|(a, b)| a != b).count();
+			assert_eq!(differences, 1, "{} and {} are one bit apart", code, next);
+		}
 		for graycode in graycodes {
 			assert_eq!(
 				graycode.0.len() as u8,
@@ -97,7 +118,7 @@ fn graycode_works() {
 	}
 }
 
-impl<'a> Display for Map<'a> {
+impl Display for Map {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		let midpoint = self.symbols.len() / 2;
 		let down_symbols = &self.symbols[..midpoint]; // does *not* include middle
@@ -110,7 +131,7 @@ impl<'a> Display for Map<'a> {
 			down_symbols.iter().join(""),
 			right_symbols.iter().join("")
 		)?;
-		let right = graycode_recursive((right_symbols.len() as u8).try_into().unwrap());
+		let right = graycodes(right_symbols.len());
 		for code in right {
 			write!(f, " {} |", code)?;
 		}
@@ -119,7 +140,7 @@ impl<'a> Display for Map<'a> {
 		let space = right_symbols.len() + 1; // equivalent to `width - down_symbols.len()`
 
 		// write rest: `|    00 |  0 |  1 |  0 |  X |`
-		let down = graycode_recursive((down_symbols.len() as u8).try_into().unwrap());
+		let down = graycodes(down_symbols.len());
 		for (i, code) in down.into_iter().enumerate() {
 			write!(f, "| {}{} |", " ".repeat(space), code)?;
 			let wrap_point = self.wrap_point.get() as usize;
@@ -133,8 +154,54 @@ impl<'a> Display for Map<'a> {
 	}
 }
 
-impl<'a> From<ExprNode> for Map<'a> {
+/// The first half of the variables goes down, the rest right.
+impl From<&TruthTable> for Map {
+	fn from(table: &TruthTable) -> Self {
+		let midpoint = table.variables.len() / 2;
+		let right_len = table.variables.len() - midpoint;
+		let down = graycodes(midpoint);
+		let right = graycodes(right_len);
+		let grid = down
+			.iter()
+			.cartesian_product(&right)
+			.map(|(row, column)| table.outputs[row.value() << right_len | column.value()])
+			.collect();
+		Map {
+			grid,
+			wrap_point: NonZero::new(right.len() as u8).expect("there is a column"),
+			symbols: table.variables.clone()
+		}
+	}
+}
+
+impl From<ExprNode> for Map {
 	fn from(value: ExprNode) -> Self {
-		todo!()
+		let table = TruthTable::new(&value, &[]).expect("the expression fits a table");
+		Map::from(&table)
 	}
 }
+
+#[test]
+fn map_works() {
+	let expr: ExprNode = "a'b + cd".parse().unwrap();
+	assert_eq!(
+		Map::from(expr).to_string(),
+		"| ab\\cd | 00 | 01 | 11 | 10 |\n\
+		 |    00 |  0 |  0 |  1 |  0 |\n\
+		 |    01 |  1 |  1 |  1 |  1 |\n\
+		 |    11 |  0 |  0 |  1 |  0 |\n\
+		 |    10 |  0 |  0 |  1 |  0 |\n"
+	);
+
+	let expr: ExprNode = "ab'+c".parse().unwrap();
+	let table = TruthTable::new(&expr, &[0]).unwrap();
+	assert_eq!(
+		Map::from(&table).to_string(),
+		"| a\\bc | 00 | 01 | 11 | 10 |\n\
+		 |    0 |  X |  1 |  1 |  0 |\n\
+		 |    1 |  1 |  1 |  1 |  0 |\n"
+	);
+
+	let expr: ExprNode = "a'".parse().unwrap();
+	assert_eq!(Map::from(expr).to_string(), "| \\a | 0 | 1 |\n|    | 1 | 0 |\n");
+}
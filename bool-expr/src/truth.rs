@@ -0,0 +1,231 @@
+//! # Truth tables
+//! Rows are numbered by reading the inputs as a binary number, the first variable being the most
+//! significant bit. A row is a minterm when the output is one, a maxterm when it is zero, and a
+//! don't-care when it was declared one.
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+
+use crate::{expressions::ExprNode, maps::Field};
+
+/// More variables make tables too long to read, and maps too wide to draw.
+pub const MAX_VARIABLES: usize = 12;
+
+pub struct TruthTable {
+	pub variables: Vec<char>,
+	/// By row number.
+	pub outputs:   Vec<Field>
+}
+
+impl TruthTable {
+	/// Evaluates `expr` for every assignment, marking the `dont_cares` rows as such.
+	pub fn new(expr: &ExprNode, dont_cares: &[usize]) -> Result<Self, String> {
+		let variables = expr.variables();
+		if variables.len() > MAX_VARIABLES {
+			return Err(format!(
+				"{} variables are too many for a table, at most {} fit",
+				variables.len(),
+				MAX_VARIABLES
+			));
+		}
+		let rows = 1 << variables.len();
+		if let Some(row) = dont_cares.iter().find(|&&row| row >= rows) {
+			return Err(format!(
+				"Don't-care {} is not a row: the table has rows 0 to {}",
+				row,
+				rows - 1
+			));
+		}
+
+		let n = variables.len();
+		let index = |c| variables.iter().position(|&v| v == c).expect("every letter is a variable");
+		let outputs = (0..rows)
+			.map(|row| {
+				if dont_cares.contains(&row) {
+					return Field::X;
+				}
+				let value = |c| bit(row, n, index(c));
+				match expr.evaluate(&value) {
+					true => Field::One,
+					false => Field::Zero
+				}
+			})
+			.collect();
+		Ok(TruthTable { variables, outputs })
+	}
+
+	fn rows(&self, field: Field) -> Vec<usize> {
+		self.outputs.iter().positions(|&output| output == field).collect()
+	}
+
+	pub fn minterms(&self) -> Vec<usize> {
+		self.rows(Field::One)
+	}
+
+	pub fn maxterms(&self) -> Vec<usize> {
+		self.rows(Field::Zero)
+	}
+
+	pub fn dont_cares(&self) -> Vec<usize> {
+		self.rows(Field::X)
+	}
+
+	/// The variable, primed when `bit` is not `value`.
+	fn literal(&self, row: usize, variable: usize, value: bool) -> ExprNode {
+		let literal = ExprNode::Literal(self.variables[variable]);
+		match bit(row, self.variables.len(), variable) == value {
+			true => literal,
+			false => ExprNode::Primed(Box::new(literal))
+		}
+	}
+
+	/// The canonical sum of products: every minterm AND'd out of its literals.
+	/// `None` when the output is never one.
+	pub fn sum_of_products(&self) -> Option<ExprNode> {
+		let n = self.variables.len();
+		self.minterms()
+			.into_iter()
+			.map(|row| {
+				(0..n)
+					.map(|variable| self.literal(row, variable, true))
+					.reduce(|a, b| ExprNode::And(Box::new(a), Box::new(b)))
+					.expect("expressions have a variable")
+			})
+			.reduce(|a, b| ExprNode::Or(Box::new(a), Box::new(b)))
+	}
+
+	/// The canonical product of sums: every maxterm OR'd out of its literals.
+	/// `None` when the output is never zero.
+	pub fn product_of_sums(&self) -> Option<ExprNode> {
+		let n = self.variables.len();
+		self.maxterms()
+			.into_iter()
+			.map(|row| {
+				(0..n)
+					.map(|variable| self.literal(row, variable, false))
+					.reduce(|a, b| ExprNode::Or(Box::new(a), Box::new(b)))
+					.expect("expressions have a variable")
+			})
+			.reduce(|a, b| ExprNode::And(Box::new(a), Box::new(b)))
+	}
+
+	/// Like `Σm(1, 3) + d(7)`.
+	pub fn minterm_list(&self) -> String {
+		format!("Σm({}){}", self.minterms().iter().join(", "), self.dont_care_list(" + "))
+	}
+
+	/// Like `ΠM(0, 2) · d(7)`.
+	pub fn maxterm_list(&self) -> String {
+		format!("ΠM({}){}", self.maxterms().iter().join(", "), self.dont_care_list(" · "))
+	}
+
+	fn dont_care_list(&self, operator: &str) -> String {
+		match self.dont_cares().as_slice() {
+			[] => String::new(),
+			rows => format!("{}d({})", operator, rows.iter().join(", "))
+		}
+	}
+}
+
+/// Whether `variable` is one in `row`, out of `n` variables.
+pub fn bit(row: usize, n: usize, variable: usize) -> bool {
+	row >> (n - 1 - variable) & 1 == 1
+}
+
+impl Display for TruthTable {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		let n = self.variables.len();
+		let width = (self.outputs.len() - 1).to_string().len();
+
+		// write top bar: `| # | A | B | F |`
+		write!(f, "| {:>width$} |", "#")?;
+		for variable in &self.variables {
+			write!(f, " {} |", variable)?;
+		}
+		writeln!(f, " F |")?;
+
+		// write rest: `| 2 | 1 | 0 | X |`
+		for (row, output) in self.outputs.iter().enumerate() {
+			write!(f, "| {:>width$} |", row)?;
+			for variable in 0..n {
+				write!(f, " {} |", bit(row, n, variable) as u8)?;
+			}
+			writeln!(f, " {} |", output)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+use proptest::prelude::*;
+
+#[cfg(test)]
+fn table(expr: &str, dont_cares: &[usize]) -> TruthTable {
+	TruthTable::new(&expr.parse().unwrap(), dont_cares).unwrap()
+}
+
+#[test]
+fn lists_the_terms() {
+	let xor = table("a'b + ab'", &[]);
+	assert_eq!(xor.minterms(), [1, 2]);
+	assert_eq!(xor.maxterms(), [0, 3]);
+	assert_eq!(xor.minterm_list(), "Σm(1, 2)");
+	assert_eq!(xor.maxterm_list(), "ΠM(0, 3)");
+	assert_eq!(xor.sum_of_products().unwrap().to_string(), "a'b+ab'");
+	assert_eq!(xor.product_of_sums().unwrap().to_string(), "(a+b)(a'+b')");
+
+	let table = table("ab + c", &[3, 7]);
+	assert_eq!(table.minterms(), [1, 5, 6]);
+	assert_eq!(table.maxterms(), [0, 2, 4]);
+	assert_eq!(table.dont_cares(), [3, 7]);
+	assert_eq!(table.minterm_list(), "Σm(1, 5, 6) + d(3, 7)");
+	assert_eq!(table.maxterm_list(), "ΠM(0, 2, 4) · d(3, 7)");
+}
+
+#[test]
+fn constants() {
+	let always = table("a + a'", &[]);
+	assert_eq!(always.minterms(), [0, 1]);
+	assert_eq!(always.product_of_sums(), None);
+	assert_eq!(always.maxterm_list(), "ΠM()");
+	assert_eq!(table("aa'", &[]).sum_of_products(), None);
+}
+
+#[test]
+fn prints_the_table() {
+	assert_eq!(
+		table("a + b'", &[2]).to_string(),
+		"| # | a | b | F |\n\
+		 | 0 | 0 | 0 | 1 |\n\
+		 | 1 | 0 | 1 | 0 |\n\
+		 | 2 | 1 | 0 | X |\n\
+		 | 3 | 1 | 1 | 1 |\n"
+	);
+}
+
+#[test]
+fn rejects_what_does_not_fit() {
+	let expr: ExprNode = "ab".parse().unwrap();
+	assert!(TruthTable::new(&expr, &[4]).is_err());
+	let expr: ExprNode = "abcdefghijklm".parse().unwrap();
+	assert!(TruthTable::new(&expr, &[]).is_err());
+}
+
+#[cfg(test)]
+proptest! {
+	#[test]
+	fn canonical_forms_are_equivalent(
+		expr in crate::expressions::arbitrary_expr_of(vec!['a', 'b', 'c', 'd'])
+	) {
+		let table = TruthTable::new(&expr, &[]).unwrap();
+		let n = table.variables.len();
+		for row in 0..table.outputs.len() {
+			let value = |c| bit(row, n, table.variables.iter().position(|&v| v == c).unwrap());
+			let expected = expr.evaluate(&value);
+			let sop = table.sum_of_products().is_some_and(|sop| sop.evaluate(&value));
+			let pos = table.product_of_sums().is_none_or(|pos| pos.evaluate(&value));
+			prop_assert_eq!(sop, expected);
+			prop_assert_eq!(pos, expected);
+		}
+	}
+}
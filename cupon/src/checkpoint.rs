@@ -0,0 +1,44 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The prefix confirmed so far, `None` when there is no checkpoint yet.
+pub fn load(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text.trim_end_matches(['\r', '\n']).to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes a new file and moves it over the old one, so a crash never loses the prefix.
+pub fn save(path: &Path, prefix: &str) -> io::Result<()> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, format!("{}\n", prefix))?;
+    fs::rename(partial, path)
+}
+
+pub fn clear(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clear, load, save};
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hkn.checkpoint");
+        assert_eq!(load(&path).unwrap(), None);
+        save(&path, "4w").unwrap();
+        save(&path, "4wx").unwrap();
+        assert_eq!(load(&path).unwrap().as_deref(), Some("4wx"));
+        clear(&path).unwrap();
+        clear(&path).unwrap();
+        assert_eq!(load(&path).unwrap(), None);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use reqwest::{Method, Url};
+use serde::Deserialize;
+
+/// What is guessed and how the oracle is asked, read from a TOML file:
+///
+/// ```toml
+/// url = "https://aubergine.hkn/cupon"
+/// alphabet = "4wxvbTYHJKN-"
+/// template = "HKN{%s}"
+/// send = { header = "cupon" }
+/// correct = { status = [302] }
+/// partial = { status = [303] }
+/// wrong = { status = [301] }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// The symbols the secret is made of, tried in this order.
+    pub alphabet: String,
+    /// What is sent, `%s` being the guess.
+    #[serde(default = "default_template")]
+    pub template: String,
+    pub send: Send,
+    /// Sent with every request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub correct: Matcher,
+    pub partial: Matcher,
+    pub wrong: Matcher,
+    /// Gives up on secrets longer than this.
+    pub max_length: Option<usize>,
+    /// How often a request is tried again after a network error or an unexpected response.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Before the first retry, doubling with each one after.
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+fn default_template() -> String {
+    "%s".to_string()
+}
+fn default_retries() -> u32 {
+    3
+}
+fn default_retry_delay_ms() -> u64 {
+    500
+}
+
+/// Where the filled in template goes.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Send {
+    Header(String),
+    Form(String),
+    Query(String),
+}
+
+/// Which responses mean a verdict. Every field given must match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Matcher {
+    /// Any of these status codes.
+    #[serde(default)]
+    pub status: Vec<u16>,
+    /// Text the body contains.
+    pub body: Option<String>,
+}
+
+impl Matcher {
+    pub fn matches(&self, status: u16, body: &str) -> bool {
+        (self.status.is_empty() || self.status.contains(&status))
+            && self.body.as_ref().is_none_or(|text| body.contains(text))
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let config: Config =
+            toml::from_str(&text).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        Url::parse(&self.url).map_err(|e| format!("Invalid url: {}", e))?;
+        self.method()?;
+        let symbols = self.symbols();
+        if symbols.is_empty() {
+            return Err("The alphabet is empty".to_string());
+        }
+        if let Some(symbol) = symbols
+            .iter()
+            .enumerate()
+            .find_map(|(i, c)| symbols[..i].contains(c).then_some(c))
+        {
+            return Err(format!("'{}' is in the alphabet twice", symbol));
+        }
+        if self.template.matches("%s").count() != 1 {
+            return Err("The template needs exactly one %s".to_string());
+        }
+        for (name, matcher) in [
+            ("correct", &self.correct),
+            ("partial", &self.partial),
+            ("wrong", &self.wrong),
+        ] {
+            if matcher.status.is_empty() && matcher.body.is_none() {
+                return Err(format!("`{}` matches every response", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn method(&self) -> Result<Method, String> {
+        Method::from_bytes(self.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("Invalid method: {}", self.method))
+    }
+
+    pub fn symbols(&self) -> Vec<char> {
+        self.alphabet.chars().collect()
+    }
+
+    /// The template with the guess filled in.
+    pub fn fill(&self, guess: &str) -> String {
+        self.template.replacen("%s", guess, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Config, Send};
+
+    #[test]
+    fn example() {
+        let config = Config::load(Path::new("hkn.toml")).unwrap();
+        assert_eq!(config.send, Send::Header("cupon".to_string()));
+        assert_eq!(config.symbols().len(), 12);
+        assert_eq!(config.fill("4w"), "HKN{4w}");
+        assert!(config.partial.matches(303, ""));
+        assert!(!config.partial.matches(301, ""));
+        assert_eq!(config.retries, 3);
+    }
+
+    #[test]
+    fn invalid() {
+        let valid = "url = \"http://localhost/\"\nalphabet = \"ab\"\nsend = { form = \"code\" }\n\
+                     correct = { body = \"Welcome\" }\npartial = { status = [303] }\n\
+                     wrong = { status = [301] }\n";
+        let config: Config = toml::from_str(valid).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.fill("a"), "a");
+        for (from, to) in [
+            ("alphabet = \"ab\"", "alphabet = \"aba\""),
+            ("alphabet = \"ab\"", "alphabet = \"\""),
+            ("url = \"http://localhost/\"", "url = \"localhost\""),
+            (
+                "alphabet = \"ab\"",
+                "alphabet = \"ab\"\ntemplate = \"flag\"",
+            ),
+            (
+                "alphabet = \"ab\"",
+                "alphabet = \"ab\"\nmethod = \"NOT A METHOD\"",
+            ),
+            ("{ body = \"Welcome\" }", "{}"),
+        ] {
+            let invalid = valid.replace(from, to);
+            let config: Config = toml::from_str(&invalid).unwrap();
+            assert!(config.validate().is_err(), "{}", invalid);
+        }
+        for unreadable in [
+            valid.replace("{ form = \"code\" }", "{ cookie = \"code\" }"),
+            valid.replace("[303]", "[303], contains = \"x\""),
+        ] {
+            assert!(
+                toml::from_str::<Config>(&unreadable).is_err(),
+                "{}",
+                unreadable
+            );
+        }
+    }
+}
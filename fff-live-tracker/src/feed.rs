@@ -0,0 +1,245 @@
+use std::fmt::{Display, Formatter};
+
+use atom_syndication as atom;
+use chrono::{DateTime, FixedOffset};
+// only to tell the two apart, the feed crates read the rest
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// A post, whichever kind of feed it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    /// Atom `id`, RSS `guid`, or the link or title when an RSS item has no guid.
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub published: Option<DateTime<FixedOffset>>,
+    /// The full content when the feed has it, otherwise the summary, usually HTML.
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Atom,
+    Rss,
+}
+
+/// A fetched feed, items in the order the feed lists them (newest first, usually).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub format: Format,
+    pub title: String,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug)]
+pub enum FeedError {
+    Atom(atom::Error),
+    Rss(rss::Error),
+    /// The document is neither `<feed>` nor `<rss>`.
+    Unknown(String),
+}
+impl Display for FeedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedError::Atom(e) => write!(f, "Invalid Atom feed: {}", e),
+            FeedError::Rss(e) => write!(f, "Invalid RSS feed: {}", e),
+            FeedError::Unknown(root) if root.is_empty() => write!(f, "Not a feed"),
+            FeedError::Unknown(root) => write!(f, "Not an Atom or RSS feed: <{}>", root),
+        }
+    }
+}
+
+impl Feed {
+    /// Reads Atom or RSS 2.0, told apart by the root element.
+    pub fn parse(body: &str) -> Result<Self, FeedError> {
+        match root_element(body).as_deref() {
+            Some("feed") => Self::parse_atom(body),
+            Some("rss") => Self::parse_rss(body),
+            root => Err(FeedError::Unknown(root.unwrap_or_default().to_string())),
+        }
+    }
+
+    fn parse_atom(body: &str) -> Result<Self, FeedError> {
+        let feed = atom::Feed::read_from(body.as_bytes()).map_err(FeedError::Atom)?;
+        Ok(Feed {
+            format: Format::Atom,
+            title: feed.title.value.clone(),
+            items: feed
+                .entries
+                .iter()
+                .map(|entry| Item {
+                    id: entry.id.clone(),
+                    title: entry.title.value.clone(),
+                    link: entry
+                        .links
+                        .iter()
+                        .find(|link| link.rel == "alternate")
+                        .or(entry.links.first())
+                        .map(|link| link.href.clone()),
+                    published: entry.published.or(Some(entry.updated)),
+                    summary: entry
+                        .content
+                        .as_ref()
+                        .and_then(|content| content.value.clone())
+                        .or_else(|| entry.summary.as_ref().map(|summary| summary.value.clone())),
+                })
+                .collect(),
+        })
+    }
+
+    fn parse_rss(body: &str) -> Result<Self, FeedError> {
+        let channel = rss::Channel::read_from(body.as_bytes()).map_err(FeedError::Rss)?;
+        Ok(Feed {
+            format: Format::Rss,
+            title: channel.title().to_string(),
+            items: channel.items().iter().filter_map(rss_item).collect(),
+        })
+    }
+}
+
+fn rss_item(item: &rss::Item) -> Option<Item> {
+    let title = item.title().map(str::to_string);
+    let link = item.link().map(str::to_string);
+    let id = item
+        .guid()
+        .map(|guid| guid.value().to_string())
+        .or(link.clone())
+        .or(title.clone())?;
+    Some(Item {
+        id,
+        title: title.unwrap_or_default(),
+        link,
+        published: item
+            .pub_date()
+            .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok()),
+        // `content:encoded`
+        summary: item.content().or(item.description()).map(str::to_string),
+    })
+}
+
+/// The local name of the first element.
+fn root_element(body: &str) -> Option<String> {
+    let mut reader = Reader::from_str(body);
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(start) | Event::Empty(start) => {
+                return Some(String::from_utf8_lossy(start.local_name().as_ref()).into_owned())
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Feed, FeedError, Format};
+
+    /// The Factorio blog feed, as saved next to the manifest.
+    const FACTORIO: &str = include_str!("../rss");
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+  <channel>
+    <title>Example &amp; Co</title>
+    <link>https://example.com/</link>
+    <item>
+      <title><![CDATA[Release 1.1 <beta>]]></title>
+      <link>https://example.com/1.1</link>
+      <guid isPermaLink="false">release-1.1</guid>
+      <description>Short</description>
+      <content:encoded><![CDATA[<p>Long</p>]]></content:encoded>
+      <pubDate>Fri, 29 Dec 2023 10:00:00 +0100</pubDate>
+      <media:content url="https://example.com/1.1.png"><media:title>Screenshot</media:title></media:content>
+    </item>
+    <item>
+      <title>Release 1.0</title>
+      <link>https://example.com/1.0</link>
+      <description>&lt;p&gt;First&lt;/p&gt;</description>
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn atom() {
+        let feed = Feed::parse(FACTORIO).unwrap();
+        assert_eq!(feed.format, Format::Atom);
+        assert_eq!(feed.title, "Factorio Blog");
+        let first = &feed.items[0];
+        assert_eq!(first.title, "Friday Facts #391 - 2023 recap");
+        assert_eq!(first.id, "https://www.factorio.com/blog/post/fff-391");
+        assert_eq!(
+            first.link.as_deref(),
+            Some("https://www.factorio.com/blog/post/fff-391")
+        );
+        assert_eq!(
+            first.published.unwrap().to_rfc3339(),
+            "2023-12-29T00:00:00+00:00"
+        );
+        assert!(first
+            .summary
+            .as_deref()
+            .unwrap()
+            .starts_with("<p>\n  Hello,<br />"));
+        assert!(feed.items.len() > 1);
+    }
+
+    #[test]
+    fn rss() {
+        let feed = Feed::parse(RSS).unwrap();
+        assert_eq!(feed.format, Format::Rss);
+        assert_eq!(feed.title, "Example & Co");
+        let titles: Vec<_> = feed.items.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, ["Release 1.1 <beta>", "Release 1.0"]);
+        assert_eq!(feed.items[0].id, "release-1.1");
+        assert_eq!(
+            feed.items[0].published.unwrap().to_rfc3339(),
+            "2023-12-29T10:00:00+01:00"
+        );
+        // without a guid the link identifies the item
+        assert_eq!(feed.items[1].id, "https://example.com/1.0");
+        assert_eq!(feed.items[1].published, None);
+        assert_eq!(feed.items[0].summary.as_deref(), Some("<p>Long</p>"));
+        assert_eq!(feed.items[1].summary.as_deref(), Some("<p>First</p>"));
+    }
+
+    #[test]
+    fn rss_entities_and_cdata() {
+        let feed = Feed::parse(
+            r#"<rss version="2.0"><channel><title>Caf&#233; &lt;3</title>
+              <item>
+                <title>Fish &amp; chips <![CDATA[& <more>]]></title>
+                <guid>a&amp;b</guid>
+                <description><![CDATA[<p>Tom &amp; Jerry</p>]]></description>
+              </item>
+              <item><title>&quot;Quoted&quot; &#x2014; &apos;too&apos;</title></item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        assert_eq!(feed.title, "Café <3");
+        assert_eq!(feed.items[0].title, "Fish & chips & <more>");
+        assert_eq!(feed.items[0].id, "a&b");
+        // CDATA is taken as it is, entities and all
+        assert_eq!(
+            feed.items[0].summary.as_deref(),
+            Some("<p>Tom &amp; Jerry</p>")
+        );
+        assert_eq!(feed.items[1].title, "\"Quoted\" — 'too'");
+        // without a guid or a link the title identifies the item
+        assert_eq!(feed.items[1].id, feed.items[1].title);
+    }
+
+    #[test]
+    fn not_a_feed() {
+        assert!(matches!(
+            Feed::parse("<html><body>Not found</body></html>"),
+            Err(FeedError::Unknown(root)) if root == "html"
+        ));
+        assert!(matches!(Feed::parse(""), Err(FeedError::Unknown(_))));
+        assert!(matches!(
+            Feed::parse("<rss><channel><item><title>a</item></channel></rss>"),
+            Err(FeedError::Rss(_))
+        ));
+    }
+}
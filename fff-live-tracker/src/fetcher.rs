@@ -0,0 +1,58 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::feed::Feed;
+
+/// The outcome of fetching one feed.
+#[derive(Debug)]
+pub struct Update {
+    pub url: String,
+    pub result: Result<Feed, String>,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    /// Fetch every feed now instead of at the next interval.
+    Refresh,
+}
+
+/// A thread that fetches every feed in turn, then waits for the interval or a refresh.
+/// It stops once either end of the channels is dropped.
+pub struct Fetcher {
+    pub commands: Sender<Command>,
+    pub updates: Receiver<Update>,
+}
+
+pub fn spawn(urls: Vec<String>, interval: Duration) -> Fetcher {
+    let (commands, command_receiver) = mpsc::channel();
+    let (update_sender, updates) = mpsc::channel();
+    thread::spawn(move || loop {
+        for url in &urls {
+            let update = Update {
+                url: url.clone(),
+                result: fetch(url),
+            };
+            if update_sender.send(update).is_err() {
+                return;
+            }
+        }
+        match command_receiver.recv_timeout(interval) {
+            Ok(Command::Refresh) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        // several refreshes queued up during a fetch are answered by one
+        while command_receiver.try_recv().is_ok() {}
+    });
+    Fetcher { commands, updates }
+}
+
+fn fetch(url: &str) -> Result<Feed, String> {
+    let response = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("No response? {}", e.without_url()))?;
+    let body = response
+        .text()
+        .map_err(|e| format!("Unreadable response: {}", e))?;
+    Feed::parse(&body).map_err(|e| e.to_string())
+}
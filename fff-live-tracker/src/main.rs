@@ -1,41 +1,98 @@
-use atom_syndication as atom;
-use std::collections::VecDeque;
-use std::iter;
+use std::path::PathBuf;
+use std::sync::mpsc::TryRecvError;
 use std::time::Duration;
 
+use clap::Parser;
+
+mod feed;
+mod fetcher;
+mod seen;
+mod text;
+mod tui;
+use seen::{New, Seen};
+
+/// Prints the titles of new posts in Atom and RSS 2.0 feeds, or shows them in a reader.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Feeds to track.
+    #[arg(default_value = "https://www.factorio.com/blog/rss")]
+    feeds: Vec<String>,
+    /// Where the IDs of posts already seen are kept between runs.
+    #[arg(long, default_value = "seen.json")]
+    state: PathBuf,
+    /// Seconds between checks.
+    #[arg(long, default_value_t = 10)]
+    interval: u64,
+    /// Browse the feeds in a terminal reader instead of printing new posts.
+    #[arg(long)]
+    tui: bool,
+}
+
 fn main() {
-    let mut latest = None;
+    let args = Args::parse();
+    let mut seen = match Seen::load(&args.state) {
+        Ok(seen) => seen,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", args.state.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let fetcher = fetcher::spawn(args.feeds.clone(), Duration::from_secs(args.interval));
+    if args.tui {
+        if let Err(e) = tui::run(args.feeds, seen, fetcher) {
+            eprintln!("Terminal error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let mut just_saw_new_post = false;
     loop {
-        if let Ok(feed_response) = reqwest::blocking::get("https://www.factorio.com/blog/rss") {
-            let feed_body = feed_response.text().expect("parsable as text");
-            let channel = atom::Feed::read_from(feed_body.as_bytes()).expect("parsable as channel");
-            let mut new_posts = channel
-                .entries
-                .into_iter()
-                .take_while(|item| {
-                    !latest
-                        .as_ref()
-                        .is_some_and(|latest_item| latest_item == item)
-                })
-                .collect::<VecDeque<_>>();
-            if let Some(newest) = new_posts.pop_front() {
-                println!(
-                    "NEW POSTS:\n{}",
-                    iter::once(&newest)
-                        .chain(new_posts.iter())
-                        .map(|post| post.title.value.as_str())
-                        .collect::<String>()
-                );
-                latest = Some(newest);
+        let update = match fetcher.updates.try_recv() {
+            Ok(update) => update,
+            Err(TryRecvError::Empty) => {
+                if just_saw_new_post {
+                    eprintln!("Waiting for new posts...");
+                    just_saw_new_post = false;
+                }
+                match fetcher.updates.recv() {
+                    Ok(update) => update,
+                    Err(_) => return,
+                }
+            }
+            Err(TryRecvError::Disconnected) => return,
+        };
+        let url = &update.url;
+        let feed = match update.result {
+            Ok(feed) => feed,
+            Err(e) => {
+                eprintln!("{} @ {}", e, url);
+                continue;
+            }
+        };
+        match seen.update(url, &feed.items) {
+            New::FirstFetch => match feed.items.first() {
+                Some(latest) if feed.items.len() == 1 => {
+                    println!("Tracking {}: 1 post, {}", feed.title, latest.title)
+                }
+                Some(latest) => println!(
+                    "Tracking {}: {} posts, latest is {}",
+                    feed.title,
+                    feed.items.len(),
+                    latest.title
+                ),
+                None => println!("Tracking {}: no posts yet", feed.title),
+            },
+            New::Items(new_posts) if new_posts.is_empty() => continue,
+            New::Items(new_posts) => {
+                println!("NEW POSTS in {}:", feed.title);
+                for post in new_posts {
+                    println!("{}", post.title);
+                }
                 just_saw_new_post = true;
-            } else if just_saw_new_post {
-                eprintln!("Waiting for new posts...");
-                just_saw_new_post = false;
             }
-        } else {
-            eprintln!("No response?");
         }
-        std::thread::sleep(Duration::from_secs(10));
+        if let Err(e) = seen.save() {
+            eprintln!("Could not save {}: {}", seen.path().display(), e);
+        }
     }
-}
\ No newline at end of file
+}
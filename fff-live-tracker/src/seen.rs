@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::feed::Item;
+
+/// IDs kept per feed at least, beyond what the feed currently lists.
+const REMEMBERED: usize = 1000;
+
+/// The IDs of posts already reported and of those read in the reader, per feed URL.
+/// Kept as JSON so restarts only report what was posted meanwhile.
+#[derive(Debug)]
+pub struct Seen {
+    path: PathBuf,
+    feeds: BTreeMap<String, FeedState>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(from = "StoredFeed")]
+struct FeedState {
+    /// Newest first.
+    seen: Vec<String>,
+    read: Vec<String>,
+}
+
+/// Files from before read state was kept hold only the seen IDs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredFeed {
+    Seen(Vec<String>),
+    State {
+        seen: Vec<String>,
+        #[serde(default)]
+        read: Vec<String>,
+    },
+}
+impl From<StoredFeed> for FeedState {
+    fn from(stored: StoredFeed) -> Self {
+        match stored {
+            StoredFeed::Seen(seen) => FeedState { seen, read: vec![] },
+            StoredFeed::State { seen, read } => FeedState { seen, read },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum New<'a> {
+    /// The feed was not tracked before, so all its posts are taken as seen.
+    FirstFetch,
+    Items(Vec<&'a Item>),
+}
+
+impl Seen {
+    /// Starts empty when there is no file yet.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let feeds = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(io::Error::other)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, feeds })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Marks the items of the feed at `url` as seen, returning those that were not, in feed order.
+    pub fn update<'a>(&mut self, url: &str, items: &'a [Item]) -> New<'a> {
+        let Some(FeedState { seen, .. }) = self.feeds.get_mut(url) else {
+            self.feeds.insert(
+                url.to_string(),
+                FeedState {
+                    seen: items.iter().map(|item| item.id.clone()).collect(),
+                    read: vec![],
+                },
+            );
+            return New::FirstFetch;
+        };
+        let new: Vec<&Item> = items
+            .iter()
+            .filter(|item| !seen.contains(&item.id))
+            .collect();
+        if !new.is_empty() {
+            let mut ids: Vec<String> = new.iter().map(|item| item.id.clone()).collect();
+            ids.append(seen);
+            ids.truncate(REMEMBERED.max(items.len()));
+            *seen = ids;
+        }
+        New::Items(new)
+    }
+
+    pub fn is_read(&self, url: &str, id: &str) -> bool {
+        self.feeds
+            .get(url)
+            .is_some_and(|feed| feed.read.iter().any(|read| read == id))
+    }
+
+    pub fn set_read(&mut self, url: &str, id: &str, read: bool) {
+        let feed = self.feeds.entry(url.to_string()).or_default();
+        feed.read.retain(|other| other != id);
+        if read {
+            feed.read.insert(0, id.to_string());
+            feed.read.truncate(REMEMBERED);
+        }
+    }
+
+    /// Writes a new file and moves it over the old one, so a crash never leaves half a file.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.feeds).map_err(io::Error::other)?;
+        let partial = self.path.with_extension("partial");
+        fs::write(&partial, json)?;
+        fs::rename(partial, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{New, Seen};
+    use crate::feed::Item;
+
+    fn items(ids: &[&str]) -> Vec<Item> {
+        ids.iter()
+            .map(|id| Item {
+                id: id.to_string(),
+                title: format!("Post {}", id),
+                link: None,
+                published: None,
+                summary: None,
+            })
+            .collect()
+    }
+
+    fn ids(new: New) -> Vec<String> {
+        match new {
+            New::FirstFetch => panic!("tracked already"),
+            New::Items(items) => items.iter().map(|item| item.id.clone()).collect(),
+        }
+    }
+
+    #[test]
+    fn reports_only_unseen_items_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/seen.json");
+        let mut seen = Seen::load(&path).unwrap();
+        assert_eq!(seen.update("a", &items(&["2", "1"])), New::FirstFetch);
+        assert!(ids(seen.update("a", &items(&["2", "1"]))).is_empty());
+        assert_eq!(ids(seen.update("a", &items(&["4", "3", "2"]))), ["4", "3"]);
+        seen.save().unwrap();
+
+        let mut restarted = Seen::load(&path).unwrap();
+        // an item that dropped out of the feed is still known
+        assert_eq!(ids(restarted.update("a", &items(&["5", "4", "1"]))), ["5"]);
+        assert_eq!(restarted.update("b", &items(&["1"])), New::FirstFetch);
+    }
+
+    #[test]
+    fn read_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.json");
+        let mut seen = Seen::load(&path).unwrap();
+        seen.update("a", &items(&["1"]));
+        assert!(!seen.is_read("a", "1"));
+        seen.set_read("a", "1", true);
+        seen.set_read("a", "1", true);
+        seen.set_read("b", "2", true);
+        seen.save().unwrap();
+
+        let mut restarted = Seen::load(&path).unwrap();
+        assert!(restarted.is_read("a", "1") && restarted.is_read("b", "2"));
+        assert!(!restarted.is_read("b", "1"));
+        restarted.set_read("a", "1", false);
+        assert!(!restarted.is_read("a", "1"));
+        // reading a feed before it was ever fetched does not count as tracking it
+        assert!(matches!(restarted.update("a", &items(&["1"])), New::Items(new) if new.is_empty()));
+    }
+
+    #[test]
+    fn reads_files_without_read_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.json");
+        std::fs::write(&path, r#"{"a": ["2", "1"]}"#).unwrap();
+        let mut seen = Seen::load(&path).unwrap();
+        assert_eq!(ids(seen.update("a", &items(&["3", "2"]))), ["3"]);
+        assert!(!seen.is_read("a", "2"));
+    }
+
+    #[test]
+    fn invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(Seen::load(&path).is_err());
+    }
+}
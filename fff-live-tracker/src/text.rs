@@ -0,0 +1,139 @@
+use scraper::{ElementRef, Html, Node};
+
+/// Plain text of an HTML fragment, for the preview pane.
+/// Blocks go on lines of their own with a blank line between paragraphs, list items get a
+/// bullet, and whitespace is collapsed outside `<pre>`.
+pub fn html_to_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut writer = Writer::default();
+    writer.element(fragment.root_element());
+    writer.out
+}
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+    /// Line breaks owed before the next text, at most 2 (a blank line).
+    breaks: usize,
+    space: bool,
+    pre: bool,
+}
+
+impl Writer {
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        let breaks = match name {
+            "script" | "style" | "head" => return,
+            "br" => {
+                self.newline();
+                return;
+            }
+            "img" => {
+                if let Some(alt) = element.value().attr("alt").filter(|alt| !alt.is_empty()) {
+                    self.text(&format!("[{}]", alt));
+                }
+                return;
+            }
+            "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "pre" | "blockquote"
+            | "table" | "figure" | "hr" => 2,
+            "div" | "li" | "tr" | "section" | "article" | "header" | "footer" | "dt" | "dd" => 1,
+            _ => 0,
+        };
+        self.block(breaks);
+        if name == "li" {
+            self.flush_breaks();
+            self.out.push_str("• ");
+            self.space = false;
+        }
+        let pre = self.pre;
+        self.pre |= name == "pre";
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.pre = pre;
+        if name == "td" || name == "th" {
+            self.space = true;
+        }
+        self.block(breaks);
+    }
+
+    fn block(&mut self, breaks: usize) {
+        self.breaks = self.breaks.max(breaks);
+        if breaks > 0 {
+            self.space = false;
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.breaks = 0;
+        self.space = false;
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre {
+            self.flush_breaks();
+            self.out.push_str(text);
+            return;
+        }
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        let ends_with_space = text.ends_with(char::is_whitespace);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() {
+            self.space |= !text.is_empty();
+            return;
+        }
+        self.flush_breaks();
+        if (self.space || starts_with_space) && !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push(' ');
+        }
+        self.out.push_str(&words.join(" "));
+        self.space = ends_with_space;
+    }
+
+    fn flush_breaks(&mut self) {
+        if !self.out.is_empty() {
+            let have = self.out.len() - self.out.trim_end_matches('\n').len();
+            for _ in have..self.breaks {
+                self.out.push('\n');
+            }
+        }
+        self.breaks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::html_to_text;
+
+    #[test]
+    fn blocks_lists_and_inline() {
+        let html = "<p>\n  Hello,<br />\n  Another <b>year</b> has come to an end.\n</p>\n\n\
+                    <h3>Mod portal recap 2023<author>Vinzenz</author></h3>\
+                    <ul><li>One</li><li>Two <a href=\"x\">links</a></li></ul>\
+                    <script>alert(1)</script><img src=\"a.png\" alt=\"Chart\">\
+                    <pre>let x = 1;\n  x</pre>";
+        assert_eq!(
+            html_to_text(html),
+            "Hello,\nAnother year has come to an end.\n\n\
+             Mod portal recap 2023Vinzenz\n\n\
+             • One\n• Two links\n\n\
+             [Chart]\n\n\
+             let x = 1;\n  x"
+        );
+    }
+
+    #[test]
+    fn plain_text_is_kept() {
+        assert_eq!(html_to_text("Just  text &amp; more"), "Just text & more");
+        assert_eq!(html_to_text(""), "");
+    }
+}
@@ -0,0 +1,542 @@
+use std::io;
+use std::process::{Command as Process, Stdio};
+use std::sync::mpsc::TryRecvError;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::feed::Item;
+use crate::fetcher::{Command, Fetcher, Update};
+use crate::seen::Seen;
+use crate::text::html_to_text;
+
+const HELP: &str =
+    "tab focus  ↑↓ move  pgup/pgdn scroll  m read/unread  M all read  o open  r refresh  q quit";
+
+/// Runs the reader until quit, restoring the terminal however it ends.
+pub fn run(urls: Vec<String>, seen: Seen, fetcher: Fetcher) -> io::Result<()> {
+    enable_raw_mode()?;
+    let _restore = Restore;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let mut app = App::new(urls, seen);
+    loop {
+        loop {
+            match fetcher.updates.try_recv() {
+                Ok(update) => app.update(update),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    app.status = "The fetcher stopped".to_string();
+                    break;
+                }
+            }
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        match app.key(key) {
+            Some(Request::Quit) => return Ok(()),
+            Some(Request::Refresh) => {
+                let _ = fetcher.commands.send(Command::Refresh);
+                app.status = "Refreshing...".to_string();
+            }
+            Some(Request::Open(url)) => {
+                if let Err(e) = open_in_browser(&url) {
+                    app.status = format!("Could not open {}: {}", url, e);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Leaves raw mode and the alternate screen when dropped, so a panic does not wreck the shell.
+struct Restore;
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+fn open_in_browser(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Process::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Process::new("open")
+    } else {
+        Process::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// What a key asks of the loop, beyond changing the app.
+#[derive(Debug, PartialEq)]
+enum Request {
+    Quit,
+    Refresh,
+    Open(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Focus {
+    Feeds,
+    Entries,
+}
+
+#[derive(Debug)]
+struct FeedView {
+    url: String,
+    /// The URL until the feed was fetched once.
+    title: String,
+    items: Vec<Item>,
+    /// Why the last fetch failed; the items of the one before are kept.
+    error: Option<String>,
+}
+
+struct App {
+    feeds: Vec<FeedView>,
+    seen: Seen,
+    focus: Focus,
+    feed_list: ListState,
+    entry_list: ListState,
+    /// Lines scrolled down in the preview.
+    scroll: u16,
+    status: String,
+}
+
+impl App {
+    fn new(urls: Vec<String>, seen: Seen) -> Self {
+        Self {
+            feeds: urls
+                .into_iter()
+                .map(|url| FeedView {
+                    title: url.clone(),
+                    url,
+                    items: vec![],
+                    error: None,
+                })
+                .collect(),
+            seen,
+            focus: Focus::Feeds,
+            feed_list: ListState::default().with_selected(Some(0)),
+            entry_list: ListState::default(),
+            scroll: 0,
+            status: "Fetching...".to_string(),
+        }
+    }
+
+    fn update(&mut self, update: Update) {
+        let Some(index) = self.feeds.iter().position(|feed| feed.url == update.url) else {
+            return;
+        };
+        let selected = self.feed_list.selected() == Some(index);
+        let selected_id = self.entry().map(|item| item.id.clone());
+        let feed = &mut self.feeds[index];
+        match update.result {
+            Ok(fetched) => {
+                self.seen.update(&feed.url, &fetched.items);
+                feed.title = fetched.title;
+                feed.items = fetched.items;
+                feed.error = None;
+                self.status = format!("Updated {}", feed.title);
+                self.save();
+            }
+            Err(e) => {
+                self.status = format!("{}: {}", feed.title, e);
+                feed.error = Some(e);
+            }
+        }
+        if selected {
+            // keep the same entry selected when new ones are listed above it
+            let feed = &self.feeds[index];
+            let position = selected_id
+                .and_then(|id| feed.items.iter().position(|item| item.id == id))
+                .or((!feed.items.is_empty()).then_some(0));
+            if position != self.entry_list.selected() {
+                self.scroll = 0;
+            }
+            self.entry_list.select(position);
+        }
+    }
+
+    fn feed(&self) -> Option<&FeedView> {
+        self.feed_list.selected().and_then(|i| self.feeds.get(i))
+    }
+
+    fn entry(&self) -> Option<&Item> {
+        let feed = self.feed()?;
+        self.entry_list.selected().and_then(|i| feed.items.get(i))
+    }
+
+    fn unread(&self, feed: &FeedView) -> usize {
+        feed.items
+            .iter()
+            .filter(|item| !self.seen.is_read(&feed.url, &item.id))
+            .count()
+    }
+
+    fn save(&mut self) {
+        if let Err(e) = self.seen.save() {
+            self.status = format!("Could not save {}: {}", self.seen.path().display(), e);
+        }
+    }
+
+    fn set_read(&mut self, read: bool) {
+        let Some((url, id)) = self
+            .feed()
+            .zip(self.entry())
+            .map(|(feed, item)| (feed.url.clone(), item.id.clone()))
+        else {
+            return;
+        };
+        self.seen.set_read(&url, &id, read);
+        self.save();
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Option<Request> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Request::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(Request::Quit)
+            }
+            KeyCode::Char('r') => return Some(Request::Refresh),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Feeds => Focus::Entries,
+                    Focus::Entries => Focus::Feeds,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Feeds,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => self.focus = Focus::Entries,
+            KeyCode::Up | KeyCode::Char('k') => self.step(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.step(1),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::Char('m') => {
+                let read = match (self.feed(), self.entry()) {
+                    (Some(feed), Some(item)) => self.seen.is_read(&feed.url, &item.id),
+                    _ => return None,
+                };
+                self.set_read(!read);
+            }
+            KeyCode::Char('M') => {
+                let feed = self.feed()?;
+                let url = feed.url.clone();
+                let ids: Vec<String> = feed.items.iter().map(|item| item.id.clone()).collect();
+                for id in ids {
+                    self.seen.set_read(&url, &id, true);
+                }
+                self.save();
+            }
+            KeyCode::Char('o') => {
+                let link = self.entry()?.link.clone()?;
+                self.set_read(true);
+                return Some(Request::Open(link));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Moves the selection of the focused list, clamped to its ends.
+    fn step(&mut self, by: isize) {
+        let (list, len) = match self.focus {
+            Focus::Feeds => (&mut self.feed_list, self.feeds.len()),
+            Focus::Entries => {
+                let len = self.feed().map_or(0, |feed| feed.items.len());
+                (&mut self.entry_list, len)
+            }
+        };
+        if len == 0 {
+            return;
+        }
+        let next = list
+            .selected()
+            .map_or(0, |i| i.saturating_add_signed(by).min(len - 1));
+        list.select(Some(next));
+        self.scroll = 0;
+        if self.focus == Focus::Feeds {
+            let has_items = self.feed().is_some_and(|feed| !feed.items.is_empty());
+            *self.entry_list.offset_mut() = 0;
+            self.entry_list.select(has_items.then_some(0));
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::new(
+            Direction::Vertical,
+            [Constraint::Min(3), Constraint::Length(1)],
+        )
+        .split(frame.size());
+        let columns = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Percentage(30), Constraint::Percentage(70)],
+        )
+        .split(rows[0]);
+        let right = Layout::new(
+            Direction::Vertical,
+            [Constraint::Percentage(40), Constraint::Percentage(60)],
+        )
+        .split(columns[1]);
+
+        self.draw_feeds(frame, columns[0]);
+        self.draw_entries(frame, right[0]);
+        self.draw_preview(frame, right[1]);
+        let footer = match self.status.is_empty() {
+            true => HELP.to_string(),
+            false => format!("{}  |  {}", self.status, HELP),
+        };
+        frame.render_widget(
+            Paragraph::new(footer).style(Style::default().fg(Color::DarkGray)),
+            rows[1],
+        );
+    }
+
+    fn block(&self, title: &str, focus: Focus) -> Block<'static> {
+        let style = match self.focus == focus {
+            true => Style::default().fg(Color::Yellow),
+            false => Style::default(),
+        };
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(style)
+            .title(title.to_string())
+    }
+
+    fn draw_feeds(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .feeds
+            .iter()
+            .map(|feed| {
+                let unread = self.unread(feed);
+                let mut spans = vec![Span::raw(feed.title.clone())];
+                if unread > 0 {
+                    spans.push(Span::styled(
+                        format!(" ({})", unread),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ));
+                }
+                if feed.error.is_some() {
+                    spans.push(Span::styled(" !", Style::default().fg(Color::Red)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.block("Feeds", Focus::Feeds))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.feed_list);
+    }
+
+    fn draw_entries(&mut self, frame: &mut Frame, area: Rect) {
+        let (title, items) = match self.feed() {
+            Some(feed) => {
+                let items = feed
+                    .items
+                    .iter()
+                    .map(|item| {
+                        let date = item
+                            .published
+                            .map(|date| format!("{}  ", date.format("%Y-%m-%d")))
+                            .unwrap_or_default();
+                        match self.seen.is_read(&feed.url, &item.id) {
+                            true => ListItem::new(format!("  {}{}", date, item.title)),
+                            false => ListItem::new(format!("● {}{}", date, item.title))
+                                .style(Style::default().add_modifier(Modifier::BOLD)),
+                        }
+                    })
+                    .collect();
+                (feed.title.clone(), items)
+            }
+            None => (String::new(), vec![]),
+        };
+        let list = List::new(items)
+            .block(self.block(&title, Focus::Entries))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.entry_list);
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let mut lines = vec![];
+        if let Some(error) = self.feed().and_then(|feed| feed.error.as_ref()) {
+            lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red)));
+            lines.push(Line::default());
+        }
+        if let Some(item) = self.entry() {
+            lines.push(Line::styled(
+                item.title.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            if let Some(link) = &item.link {
+                lines.push(Line::styled(link.clone(), Style::default().fg(Color::Blue)));
+            }
+            if let Some(published) = item.published {
+                lines.push(Line::raw(published.format("%Y-%m-%d %H:%M").to_string()));
+            }
+            lines.push(Line::default());
+            let text = item
+                .summary
+                .as_deref()
+                .map(html_to_text)
+                .unwrap_or_default();
+            lines.extend(text.lines().map(|line| Line::raw(line.to_string())));
+        }
+        let preview = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(preview, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::{App, Request};
+    use crate::feed::Feed;
+    use crate::fetcher::Update;
+    use crate::seen::Seen;
+
+    /// Draws the app into a fresh buffer and returns its rows, for tests.
+    fn render(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Option<Request> {
+        app.key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn app(dir: &tempfile::TempDir) -> App {
+        let mut app = App::new(
+            vec!["factorio".to_string(), "down".to_string()],
+            Seen::load(dir.path().join("seen.json")).unwrap(),
+        );
+        app.update(Update {
+            url: "factorio".to_string(),
+            result: Ok(Feed::parse(include_str!("../rss")).unwrap()),
+        });
+        app.update(Update {
+            url: "down".to_string(),
+            result: Err("No response?".to_string()),
+        });
+        app
+    }
+
+    #[test]
+    fn read_state_and_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir);
+        let posts = app.feeds[0].items.len();
+        assert_eq!(app.unread(&app.feeds[0]), posts);
+
+        let screen = render(&mut app, 120, 30).join("\n");
+        assert!(
+            screen.contains(&format!("Factorio Blog ({})", posts)),
+            "{}",
+            screen
+        );
+        assert!(
+            screen.contains("● 2023-12-29  Friday Facts #391"),
+            "{}",
+            screen
+        );
+        assert!(screen.contains("down !"), "{}", screen);
+        // the summary is shown as text, not HTML
+        assert!(
+            screen.contains("Another year has come to an end,"),
+            "{}",
+            screen
+        );
+        assert!(!screen.contains("<br"), "{}", screen);
+
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(press(&mut app, KeyCode::Char('m')), None);
+        assert_eq!(app.unread(&app.feeds[0]), posts - 1);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(
+            press(&mut app, KeyCode::Char('o')),
+            Some(Request::Open(app.feeds[0].items[1].link.clone().unwrap()))
+        );
+        assert_eq!(app.unread(&app.feeds[0]), posts - 2);
+        let screen = render(&mut app, 120, 30).join("\n");
+        assert!(
+            screen.contains("  2023-12-29  Friday Facts #391"),
+            "{}",
+            screen
+        );
+
+        // read state outlives the app
+        drop(app);
+        let mut app = self::app(&dir);
+        assert_eq!(app.unread(&app.feeds[0]), posts - 2);
+        press(&mut app, KeyCode::Char('M'));
+        assert_eq!(app.unread(&app.feeds[0]), 0);
+        assert_eq!(press(&mut app, KeyCode::Char('r')), Some(Request::Refresh));
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Some(Request::Quit));
+    }
+
+    #[test]
+    fn selection_follows_new_posts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir);
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Down);
+        let selected = app.entry().unwrap().id.clone();
+
+        let mut feed = Feed::parse(include_str!("../rss")).unwrap();
+        let mut newer = feed.items[0].clone();
+        newer.id = "fff-392".to_string();
+        feed.items.insert(0, newer);
+        app.update(Update {
+            url: "factorio".to_string(),
+            result: Ok(feed),
+        });
+        assert_eq!(app.entry().unwrap().id, selected);
+        assert_eq!(app.entry_list.selected(), Some(2));
+
+        // an empty feed in the list does not break moving between feeds
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Down);
+        assert!(app.entry().is_none());
+        render(&mut app, 80, 20);
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+	fmt::{Display, Formatter},
+	num::ParseIntError,
+	str::FromStr
+};
+
+/// Typed into the terminal while watching, one per line.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Command {
+	Refresh,
+	Pause,
+	List,
+	Wait(u64),
+	Quit
+}
+impl Command {
+	pub const HELP: &'static str = "\
+Commands:
+  r         refresh now
+  p         pause or resume refreshing
+  l         list the entries in the hosts file
+  w <ms>    wait <ms> before the next refresh
+  q         quit (also Ctrl-C)";
+}
+#[derive(Debug, Eq, PartialEq)]
+pub enum CommandParseErr {
+	Empty,
+	Unknown(String),
+	MissingWait,
+	InvalidWait(ParseIntError),
+	UnexpectedArgument(String)
+}
+impl Display for CommandParseErr {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			CommandParseErr::Empty => write!(f, "No command given."),
+			CommandParseErr::Unknown(s) => write!(f, "'{}' is not a command.", s),
+			CommandParseErr::MissingWait => write!(f, "'w' needs a wait time in milliseconds."),
+			CommandParseErr::InvalidWait(e) => write!(f, "Invalid wait time: {}", e),
+			CommandParseErr::UnexpectedArgument(s) => write!(f, "Unexpected argument '{}'.", s)
+		}
+	}
+}
+impl FromStr for Command {
+	type Err = CommandParseErr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut tokens = s.split_whitespace();
+		let command = match tokens.next() {
+			None => Err(CommandParseErr::Empty)?,
+			Some("r") => Command::Refresh,
+			Some("p") => Command::Pause,
+			Some("l") => Command::List,
+			Some("q") => Command::Quit,
+			Some("w") => match tokens.next() {
+				None => Err(CommandParseErr::MissingWait)?,
+				Some(ms) => Command::Wait(ms.parse().map_err(CommandParseErr::InvalidWait)?)
+			},
+			Some(other) => Err(CommandParseErr::Unknown(other.to_string()))?
+		};
+		match tokens.next() {
+			Some(extra) => Err(CommandParseErr::UnexpectedArgument(extra.to_string())),
+			None => Ok(command)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::assert_matches;
+
+	use super::{Command, CommandParseErr};
+
+	#[test]
+	fn parsing_works() {
+		for (s, command) in [
+			("r", Command::Refresh),
+			(" p ", Command::Pause),
+			("l", Command::List),
+			("w 500", Command::Wait(500)),
+			("q\n", Command::Quit)
+		] {
+			assert_eq!(s.parse(), Ok(command));
+		}
+	}
+
+	#[test]
+	fn parsing_fails() {
+		assert_eq!("".parse::<Command>(), Err(CommandParseErr::Empty));
+		assert_eq!(
+			"refresh".parse::<Command>(),
+			Err(CommandParseErr::Unknown("refresh".to_string()))
+		);
+		assert_eq!("w".parse::<Command>(), Err(CommandParseErr::MissingWait));
+		assert_matches!(
+			"w soon".parse::<Command>(),
+			Err(CommandParseErr::InvalidWait(_))
+		);
+		assert_eq!(
+			"r now".parse::<Command>(),
+			Err(CommandParseErr::UnexpectedArgument("now".to_string()))
+		);
+	}
+}
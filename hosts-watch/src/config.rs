@@ -2,14 +2,15 @@ use std::{
 	env::Args,
 	fmt::{Display, Formatter},
 	num::ParseIntError,
-	path::PathBuf
+	path::{Path, PathBuf},
+	str::FromStr
 };
 
-use itertools::Itertools;
-
 #[derive(Debug)]
 pub struct Config {
 	pub url:          url::Url,
+	pub source:       SourceKind,
+	pub mode:         RunMode,
 	pub hosts_path:   PathBuf,
 	pub token:        String,
 	pub min_wait_ms:  u64,
@@ -20,75 +21,139 @@ pub struct Config {
 }
 #[derive(Debug)]
 pub enum ConfigErr {
+	/// Not a failure, `--help` was passed.
+	Help,
+	/// Not a failure, `--version` was passed.
+	Version,
 	InvalidOS(String),
 	InvalidUrl(String, url::ParseError),
 	MissingToken,
-	InvalidFlag(FlagAddErr)
+	InvalidFlag(FlagAddErr),
+	InvalidConfigFile(PathBuf, ConfigFileErr),
+	InvalidWaits {
+		min_wait_ms: u64,
+		mid_wait_ms: u64,
+		max_wait_ms: u64
+	}
 }
 impl Display for ConfigErr {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		match self {
+			ConfigErr::Help => write!(f, "{}", Config::USAGE),
+			ConfigErr::Version => write!(f, "hosts-watch {}", env!("CARGO_PKG_VERSION")),
 			ConfigErr::InvalidOS(name) => write!(f, "Operating System '{}' is unsupported.", name),
 			ConfigErr::InvalidUrl(s, e) => write!(f, "'{}' could not be parsed as URL: {}", s, e),
-			ConfigErr::MissingToken => write!(f, "Token was not provided."),
-			ConfigErr::InvalidFlag(s) => match s {
-				FlagAddErr::AlreadySet(s) => write!(f, "The flag '{}' was already set.", s),
-				FlagAddErr::ExpectedFlag(s) => write!(f, "Expected flag, found '{}'.", s),
-				FlagAddErr::InvalidFlag(s) => write!(f, "'{}' is not recognized as a flag.", s),
-				FlagAddErr::InvalidNum(e) => e.fmt(f)
-			}
+			ConfigErr::MissingToken => write!(
+				f,
+				"Token was not provided, pass '--token' or set '{}'.",
+				ConfigFlag::env_var(ConfigFlag::TOKEN_FLAG)
+			),
+			ConfigErr::InvalidFlag(e) => e.fmt(f),
+			ConfigErr::InvalidConfigFile(path, e) => {
+				write!(f, "Config file '{}' is invalid: {}", path.display(), e)
+			},
+			ConfigErr::InvalidWaits {
+				min_wait_ms,
+				mid_wait_ms,
+				max_wait_ms
+			} => write!(
+				f,
+				"Wait times must satisfy 0 < min_wait <= mid_wait <= max_wait, got {} / {} / {}.",
+				min_wait_ms, mid_wait_ms, max_wait_ms
+			)
 		}
 	}
 }
+#[derive(Debug)]
+pub enum ConfigFileErr {
+	Io(std::io::Error),
+	Toml(toml::de::Error),
+	Flag(FlagAddErr)
+}
+impl Display for ConfigFileErr {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ConfigFileErr::Io(e) => e.fmt(f),
+			ConfigFileErr::Toml(e) => e.fmt(f),
+			ConfigFileErr::Flag(e) => e.fmt(f)
+		}
+	}
+}
+
 impl TryFrom<Args> for Config {
 	type Error = ConfigErr;
 
 	fn try_from(args: Args) -> Result<Self, Self::Error> {
-		let mut args = args.skip(1);
-
-		// if any args are passed, assume that the first is for the target URL
-		// maybe we should just divide arguments up using `--<flag>` syntax for flags
-		// so that order is irrelevant
-		let url = match args.next() {
-			Some(maybe_url) => match maybe_url.parse::<url::Url>() {
-				Ok(valid) => valid,
-				Err(e) => Err(Self::Error::InvalidUrl(maybe_url, e))?
-			},
-			None => {
-				eprintln!(
-					"Url was not provided, using default ('{}') instead.",
-					Self::DEFAULT_URL
-				);
-				Self::DEFAULT_URL.parse().expect("default url is parsable")
-			}
-		};
+		Self::parse(args.skip(1), |key| std::env::var(key).ok())
+	}
+}
 
-		let Some(token) = args.next() else {
-			Err(ConfigErr::MissingToken)?
-		};
+/// Where entries are fetched from, see [`crate::source`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SourceKind {
+	Chrome,
+	Http,
+	File(PathBuf),
+	Stdin
+}
+impl SourceKind {
+	const FILE_PREFIX: &'static str = "file:";
 
-		// parse flags
-		let flags = args
-			.tuples()
-			.map(|(key, value)| ConfigFlag::try_from((key.as_str(), value.as_str())))
-			.collect::<Result<Vec<ConfigFlag>, FlagAddErr>>()
-			.map_err(Self::Error::InvalidFlag)?;
+	fn needs_token(&self) -> bool {
+		matches!(self, SourceKind::Chrome | SourceKind::Http)
+	}
+}
+impl FromStr for SourceKind {
+	type Err = FlagAddErr;
 
-		// windows or linux
-		let hosts_path = match std::env::consts::OS {
-			"linux" => Self::LINUX_HOST_FILE_LOCATION,
-			"windows" => Self::WINDOWS_HOST_FILE_LOCATION,
-			other => Err(Self::Error::InvalidOS(other.to_string()))?
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s {
+			"chrome" => Self::Chrome,
+			"http" => Self::Http,
+			"stdin" => Self::Stdin,
+			other => match other.strip_prefix(Self::FILE_PREFIX) {
+				Some(path) if !path.is_empty() => Self::File(path.into()),
+				_ => Err(FlagAddErr::InvalidSource(other.to_string()))?
+			}
+		})
+	}
+}
+impl Display for SourceKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SourceKind::Chrome => write!(f, "chrome"),
+			SourceKind::Http => write!(f, "http"),
+			SourceKind::File(path) => write!(f, "{}{}", Self::FILE_PREFIX, path.display()),
+			SourceKind::Stdin => write!(f, "stdin")
 		}
-		.parse()
-		.expect("OS constants are valid paths");
+	}
+}
 
-		Ok(Self::new(
-			url,
-			hosts_path,
-			token,
-			ConfigOptions::new(flags).map_err(Self::Error::InvalidFlag)?
-		))
+/// What to do with the fetched entries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RunMode {
+	/// Keep polling and updating the hosts file.
+	Watch,
+	/// Update the hosts file once and exit, see [`RunMode::CHANGED_EXIT_CODE`].
+	Once,
+	/// Print what would change in the hosts file and exit without writing.
+	DryRun
+}
+impl RunMode {
+	/// Exit code of a single run that changed (or would change) the hosts file.
+	/// Runs without changes exit with 0, failures with 1.
+	pub const CHANGED_EXIT_CODE: u8 = 2;
+}
+impl FromStr for RunMode {
+	type Err = FlagAddErr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s {
+			"watch" => Self::Watch,
+			"once" => Self::Once,
+			"dry-run" => Self::DryRun,
+			other => Err(FlagAddErr::InvalidMode(other.to_string()))?
+		})
 	}
 }
 
@@ -100,29 +165,121 @@ impl Config {
 	const DEFAULT_TARGET_END: &'static str = "/// ctf_bottom ///";
 	const DEFAULT_URL: &'static str = "https://hackeve.haaukins.dk/hosts";
 	const LINUX_HOST_FILE_LOCATION: &'static str = "/etc/hosts";
+	const USAGE: &'static str = "\
+Usage: hosts-watch [--<flag> <value> | --<flag>=<value>]...
+
+Flags:
+  --url <url>             Page listing the entries [default: https://hackeve.haaukins.dk/hosts]
+  --token <token>         Login token, required by the 'chrome' and 'http' sources
+  --source <source>       'chrome', 'http', 'stdin' or 'file:<path>' [default: chrome]
+  --mode <mode>           'watch', 'once' or 'dry-run' [default: watch]
+  --once                  Same as '--mode once', update the hosts file once and exit
+                          with 2 if anything changed, 0 if not
+  --dry-run               Same as '--mode dry-run', print the pending changes and exit
+                          without writing
+  --hosts_path <path>     Hosts file to update [default: the OS hosts file]
+  --min_wait <ms>         Wait after a change [default: 50]
+  --mid_wait <ms>         Wait when nothing changed [default: 3200]
+  --max_wait <ms>         Longest wait when nothing is found [default: 12800]
+  --target_begin <line>   Line starting the managed block [default: /// ctf_top ///]
+  --target_end <line>     Line ending the managed block [default: /// ctf_bottom ///]
+  --config <path>         TOML file setting any of the above by flag name
+  -h, --help              Print this help
+  -V, --version           Print the version
+
+Every flag can also be set through an environment variable, e.g. HOSTS_WATCH_TOKEN.
+Flags take precedence over environment variables, which take precedence over the config file.";
 	const WINDOWS_HOST_FILE_LOCATION: &'static str = "C:\\Windows\\System32\\drivers\\etc\\hosts";
 
-	fn new(url: url::Url, hosts_path: PathBuf, token: String, options: ConfigOptions) -> Self {
-		Self {
+	/// Builds the configuration from command line arguments (without the program name),
+	/// environment variables looked up through `env`, and the config file either of them points to.
+	pub fn parse(
+		args: impl IntoIterator<Item = String>,
+		env: impl Fn(&str) -> Option<String>
+	) -> Result<Self, ConfigErr> {
+		let from_args = ConfigOptions::new(ConfigFlag::from_args(args)?)
+			.map_err(ConfigErr::InvalidFlag)?;
+		let from_env = ConfigFlag::from_env(env)
+			.and_then(ConfigOptions::new)
+			.map_err(ConfigErr::InvalidFlag)?;
+		let options = match from_args.config.as_ref().or(from_env.config.as_ref()) {
+			Some(path) => {
+				let from_file = ConfigFlag::from_file(path)
+					.and_then(|flags| ConfigOptions::new(flags).map_err(ConfigFileErr::Flag))
+					.map_err(|e| ConfigErr::InvalidConfigFile(path.clone(), e))?;
+				from_args.or(from_env).or(from_file)
+			},
+			None => from_args.or(from_env)
+		};
+
+		let url = match options.url {
+			Some(maybe_url) => match maybe_url.parse::<url::Url>() {
+				Ok(valid) => valid,
+				Err(e) => Err(ConfigErr::InvalidUrl(maybe_url, e))?
+			},
+			None => Self::DEFAULT_URL.parse().expect("default url is parsable")
+		};
+
+		let source = options.source.unwrap_or(SourceKind::Chrome);
+		let mode = options.mode.unwrap_or(RunMode::Watch);
+		let token = match options.token {
+			Some(token) => token,
+			None if source.needs_token() => Err(ConfigErr::MissingToken)?,
+			None => String::new()
+		};
+
+		// windows or linux
+		let hosts_path = match options.hosts_path {
+			Some(path) => path,
+			None => match std::env::consts::OS {
+				"linux" => Self::LINUX_HOST_FILE_LOCATION,
+				"windows" => Self::WINDOWS_HOST_FILE_LOCATION,
+				other => Err(ConfigErr::InvalidOS(other.to_string()))?
+			}
+			.parse()
+			.expect("OS constants are valid paths")
+		};
+
+		let min_wait_ms = options.min_wait_ms.unwrap_or(Self::DEFAULT_MIN_WAIT_MS);
+		let mid_wait_ms = options.mid_wait_ms.unwrap_or(Self::DEFAULT_MID_WAIT_MS);
+		let max_wait_ms = options.max_wait_ms.unwrap_or(Self::DEFAULT_MAX_WAIT_MS);
+		if !(0 < min_wait_ms && min_wait_ms <= mid_wait_ms && mid_wait_ms <= max_wait_ms) {
+			Err(ConfigErr::InvalidWaits {
+				min_wait_ms,
+				mid_wait_ms,
+				max_wait_ms
+			})?
+		}
+
+		Ok(Self {
 			url,
+			source,
+			mode,
 			hosts_path,
 			token,
-			min_wait_ms: options.min_wait_ms.unwrap_or(Self::DEFAULT_MIN_WAIT_MS),
-			mid_wait_ms: options.mid_wait_ms.unwrap_or(Self::DEFAULT_MID_WAIT_MS),
-			max_wait_ms: options.max_wait_ms.unwrap_or(Self::DEFAULT_MAX_WAIT_MS),
+			min_wait_ms,
+			mid_wait_ms,
+			max_wait_ms,
 			target_begin: options
 				.target_begin
 				.unwrap_or(Self::DEFAULT_TARGET_BEGIN.to_string()),
 			target_end: options
 				.target_end
 				.unwrap_or(Self::DEFAULT_TARGET_END.to_string())
-		}
+		})
 	}
 }
 
 /// Basically a manually state-checked builder.
 /// Probably should be type-stated and/or generated?
+#[derive(Default)]
 struct ConfigOptions {
+	url:          Option<String>,
+	token:        Option<String>,
+	source:       Option<SourceKind>,
+	mode:         Option<RunMode>,
+	hosts_path:   Option<PathBuf>,
+	config:       Option<PathBuf>,
 	min_wait_ms:  Option<u64>,
 	mid_wait_ms:  Option<u64>,
 	max_wait_ms:  Option<u64>,
@@ -132,15 +289,9 @@ struct ConfigOptions {
 
 impl ConfigOptions {
 	pub fn new(options: impl IntoIterator<Item = ConfigFlag>) -> Result<Self, FlagAddErr> {
-		options.into_iter().try_fold(
-			Self {
-				min_wait_ms:  None,
-				mid_wait_ms:  None,
-				max_wait_ms:  None,
-				target_begin: None,
-				target_end:   None
-			},
-			|mut acc, flag| {
+		options
+			.into_iter()
+			.try_fold(Self::default(), |mut acc, flag| {
 				fn assign_none_or<T, E>(prev: &mut Option<T>, new: T, e: E) -> Result<(), E> {
 					if prev.is_none() {
 						*prev = Some(new);
@@ -151,40 +302,61 @@ impl ConfigOptions {
 				}
 				// assign option if not already present
 				// this could maybe be abstracted/generated as type-states
+				let already_set = FlagAddErr::AlreadySet(flag.name().to_string());
 				match flag {
-					ConfigFlag::MinWait(num) => assign_none_or(
-						&mut acc.min_wait_ms,
-						num,
-						FlagAddErr::AlreadySet(ConfigFlag::MIN_WAIT_FLAG.to_string())
-					)?,
-					ConfigFlag::MidWait(num) => assign_none_or(
-						&mut acc.mid_wait_ms,
-						num,
-						FlagAddErr::AlreadySet(ConfigFlag::MID_WAIT_FLAG.to_string())
-					)?,
-					ConfigFlag::MaxWait(num) => assign_none_or(
-						&mut acc.max_wait_ms,
-						num,
-						FlagAddErr::AlreadySet(ConfigFlag::MAX_WAIT_FLAG.to_string())
-					)?,
-					ConfigFlag::TargetBegin(pat) => assign_none_or(
-						&mut acc.target_begin,
-						pat,
-						FlagAddErr::AlreadySet(ConfigFlag::TARGET_BEGIN_FLAG.to_string())
-					)?,
-					ConfigFlag::TargetEnd(pat) => assign_none_or(
-						&mut acc.target_end,
-						pat,
-						FlagAddErr::AlreadySet(ConfigFlag::TARGET_END_FLAG.to_string())
-					)?
+					ConfigFlag::Url(url) => assign_none_or(&mut acc.url, url, already_set)?,
+					ConfigFlag::Token(token) => assign_none_or(&mut acc.token, token, already_set)?,
+					ConfigFlag::Source(kind) => assign_none_or(&mut acc.source, kind, already_set)?,
+					ConfigFlag::Mode(mode) => assign_none_or(&mut acc.mode, mode, already_set)?,
+					ConfigFlag::HostsPath(path) => {
+						assign_none_or(&mut acc.hosts_path, path, already_set)?
+					},
+					ConfigFlag::Config(path) => assign_none_or(&mut acc.config, path, already_set)?,
+					ConfigFlag::MinWait(num) => {
+						assign_none_or(&mut acc.min_wait_ms, num, already_set)?
+					},
+					ConfigFlag::MidWait(num) => {
+						assign_none_or(&mut acc.mid_wait_ms, num, already_set)?
+					},
+					ConfigFlag::MaxWait(num) => {
+						assign_none_or(&mut acc.max_wait_ms, num, already_set)?
+					},
+					ConfigFlag::TargetBegin(pat) => {
+						assign_none_or(&mut acc.target_begin, pat, already_set)?
+					},
+					ConfigFlag::TargetEnd(pat) => {
+						assign_none_or(&mut acc.target_end, pat, already_set)?
+					}
 				}
 				Ok(acc)
-			}
-		)
+			})
+	}
+
+	/// Fills in whatever is unset in `self` from a lower priority source.
+	fn or(self, other: Self) -> Self {
+		Self {
+			url:          self.url.or(other.url),
+			token:        self.token.or(other.token),
+			source:       self.source.or(other.source),
+			mode:         self.mode.or(other.mode),
+			hosts_path:   self.hosts_path.or(other.hosts_path),
+			config:       self.config.or(other.config),
+			min_wait_ms:  self.min_wait_ms.or(other.min_wait_ms),
+			mid_wait_ms:  self.mid_wait_ms.or(other.mid_wait_ms),
+			max_wait_ms:  self.max_wait_ms.or(other.max_wait_ms),
+			target_begin: self.target_begin.or(other.target_begin),
+			target_end:   self.target_end.or(other.target_end)
+		}
 	}
 }
 #[derive(Debug)]
 enum ConfigFlag {
+	Url(String),
+	Token(String),
+	Source(SourceKind),
+	Mode(RunMode),
+	HostsPath(PathBuf),
+	Config(PathBuf),
 	MinWait(u64),
 	MidWait(u64),
 	MaxWait(u64),
@@ -192,37 +364,310 @@ enum ConfigFlag {
 	TargetEnd(String)
 }
 impl ConfigFlag {
+	const CONFIG_FLAG: &'static str = "config";
+	const DRY_RUN_FLAG: &'static str = "--dry-run";
+	const ENV_PREFIX: &'static str = "HOSTS_WATCH_";
 	const FLAG_PATTERN: &'static str = "--";
+	const HELP_FLAGS: [&'static str; 2] = ["--help", "-h"];
+	const HOSTS_PATH_FLAG: &'static str = "hosts_path";
 	const MAX_WAIT_FLAG: &'static str = "max_wait";
 	const MID_WAIT_FLAG: &'static str = "mid_wait";
 	// waiting
 	const MIN_WAIT_FLAG: &'static str = "min_wait";
+	const MODE_FLAG: &'static str = "mode";
+	const NAMES: [&'static str; 11] = [
+		Self::URL_FLAG,
+		Self::TOKEN_FLAG,
+		Self::SOURCE_FLAG,
+		Self::MODE_FLAG,
+		Self::HOSTS_PATH_FLAG,
+		Self::CONFIG_FLAG,
+		Self::MIN_WAIT_FLAG,
+		Self::MID_WAIT_FLAG,
+		Self::MAX_WAIT_FLAG,
+		Self::TARGET_BEGIN_FLAG,
+		Self::TARGET_END_FLAG
+	];
+	const ONCE_FLAG: &'static str = "--once";
+	const SOURCE_FLAG: &'static str = "source";
 	// searching
 	const TARGET_BEGIN_FLAG: &'static str = "target_begin";
 	const TARGET_END_FLAG: &'static str = "target_end";
+	const TOKEN_FLAG: &'static str = "token";
+	const URL_FLAG: &'static str = "url";
+	const VERSION_FLAGS: [&'static str; 2] = ["--version", "-V"];
+
+	fn new(name: &str, value: &str) -> Result<Self, FlagAddErr> {
+		let parse = |value: &str| value.parse().map_err(FlagAddErr::InvalidNum);
+		Ok(match name {
+			Self::URL_FLAG => Self::Url(value.to_string()),
+			Self::TOKEN_FLAG => Self::Token(value.to_string()),
+			Self::SOURCE_FLAG => Self::Source(value.parse()?),
+			Self::MODE_FLAG => Self::Mode(value.parse()?),
+			Self::HOSTS_PATH_FLAG => Self::HostsPath(value.into()),
+			Self::CONFIG_FLAG => Self::Config(value.into()),
+			Self::MIN_WAIT_FLAG => Self::MinWait(parse(value)?),
+			Self::MID_WAIT_FLAG => Self::MidWait(parse(value)?),
+			Self::MAX_WAIT_FLAG => Self::MaxWait(parse(value)?),
+			Self::TARGET_BEGIN_FLAG => Self::TargetBegin(value.to_string()),
+			Self::TARGET_END_FLAG => Self::TargetEnd(value.to_string()),
+			unrecognized => Err(FlagAddErr::InvalidFlag(unrecognized.to_string()))?
+		})
+	}
+
+	fn name(&self) -> &'static str {
+		match self {
+			ConfigFlag::Url(_) => Self::URL_FLAG,
+			ConfigFlag::Token(_) => Self::TOKEN_FLAG,
+			ConfigFlag::Source(_) => Self::SOURCE_FLAG,
+			ConfigFlag::Mode(_) => Self::MODE_FLAG,
+			ConfigFlag::HostsPath(_) => Self::HOSTS_PATH_FLAG,
+			ConfigFlag::Config(_) => Self::CONFIG_FLAG,
+			ConfigFlag::MinWait(_) => Self::MIN_WAIT_FLAG,
+			ConfigFlag::MidWait(_) => Self::MID_WAIT_FLAG,
+			ConfigFlag::MaxWait(_) => Self::MAX_WAIT_FLAG,
+			ConfigFlag::TargetBegin(_) => Self::TARGET_BEGIN_FLAG,
+			ConfigFlag::TargetEnd(_) => Self::TARGET_END_FLAG
+		}
+	}
+
+	fn env_var(name: &str) -> String {
+		format!("{}{}", Self::ENV_PREFIX, name.to_uppercase())
+	}
+
+	/// Reads `--flag value` and `--flag=value` pairs in any order.
+	fn from_args(args: impl IntoIterator<Item = String>) -> Result<Vec<Self>, ConfigErr> {
+		let mut args = args.into_iter();
+		let mut flags = Vec::new();
+		while let Some(arg) = args.next() {
+			if Self::HELP_FLAGS.contains(&arg.as_str()) {
+				Err(ConfigErr::Help)?
+			}
+			if Self::VERSION_FLAGS.contains(&arg.as_str()) {
+				Err(ConfigErr::Version)?
+			}
+			let flag = match arg.split_once('=') {
+				// shorthands without a value
+				_ if arg == Self::ONCE_FLAG => Ok(Self::Mode(RunMode::Once)),
+				_ if arg == Self::DRY_RUN_FLAG => Ok(Self::Mode(RunMode::DryRun)),
+				Some((flag_raw, value)) => Self::try_from((flag_raw, value)),
+				None => match args.next() {
+					Some(value) => Self::try_from((arg.as_str(), value.as_str())),
+					None if arg.starts_with(Self::FLAG_PATTERN) => {
+						Err(FlagAddErr::MissingValue(arg))
+					},
+					None => Err(FlagAddErr::ExpectedFlag(arg))
+				}
+			};
+			flags.push(flag.map_err(ConfigErr::InvalidFlag)?);
+		}
+		Ok(flags)
+	}
+
+	fn from_env(env: impl Fn(&str) -> Option<String>) -> Result<Vec<Self>, FlagAddErr> {
+		Self::NAMES
+			.iter()
+			.filter_map(|name| env(&Self::env_var(name)).map(|value| Self::new(name, &value)))
+			.collect()
+	}
+
+	fn from_file(path: &Path) -> Result<Vec<Self>, ConfigFileErr> {
+		let content = std::fs::read_to_string(path).map_err(ConfigFileErr::Io)?;
+		let table = content.parse::<toml::Table>().map_err(ConfigFileErr::Toml)?;
+		table
+			.into_iter()
+			.map(|(name, value)| {
+				if name == Self::CONFIG_FLAG {
+					return Err(FlagAddErr::InvalidFlag(name));
+				}
+				let value = match value {
+					toml::Value::String(s) => s,
+					other => other.to_string()
+				};
+				Self::new(&name, &value)
+			})
+			.collect::<Result<_, _>>()
+			.map_err(ConfigFileErr::Flag)
+	}
 }
 #[derive(Debug)]
 pub enum FlagAddErr {
 	AlreadySet(String),
 	ExpectedFlag(String),
 	InvalidFlag(String),
-	InvalidNum(ParseIntError)
+	InvalidNum(ParseIntError),
+	InvalidSource(String),
+	InvalidMode(String),
+	MissingValue(String)
+}
+impl Display for FlagAddErr {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			FlagAddErr::AlreadySet(s) => write!(f, "The flag '{}' was already set.", s),
+			FlagAddErr::ExpectedFlag(s) => write!(f, "Expected flag, found '{}'.", s),
+			FlagAddErr::InvalidFlag(s) => write!(f, "'{}' is not recognized as a flag.", s),
+			FlagAddErr::InvalidNum(e) => e.fmt(f),
+			FlagAddErr::InvalidSource(s) => write!(
+				f,
+				"'{}' is not a source, expected 'chrome', 'http', 'stdin' or 'file:<path>'.",
+				s
+			),
+			FlagAddErr::InvalidMode(s) => write!(
+				f,
+				"'{}' is not a mode, expected 'watch', 'once' or 'dry-run'.",
+				s
+			),
+			FlagAddErr::MissingValue(s) => write!(f, "The flag '{}' is missing a value.", s)
+		}
+	}
 }
 impl TryFrom<(&str, &str)> for ConfigFlag {
 	type Error = FlagAddErr;
 
 	fn try_from((flag_raw, value): (&str, &str)) -> Result<Self, Self::Error> {
-		if !flag_raw.starts_with(Self::FLAG_PATTERN) {
-			Err(Self::Error::ExpectedFlag(flag_raw.to_string()))?
-		};
-		let parse = |value: &str| value.parse().map_err(Self::Error::InvalidNum);
-		Ok(match flag_raw.trim_start_matches(Self::FLAG_PATTERN) {
-			Self::MIN_WAIT_FLAG => Self::MinWait(parse(value)?),
-			Self::MID_WAIT_FLAG => Self::MidWait(parse(value)?),
-			Self::MAX_WAIT_FLAG => Self::MaxWait(parse(value)?),
-			Self::TARGET_BEGIN_FLAG => Self::TargetBegin(value.to_string()),
-			Self::TARGET_END_FLAG => Self::TargetEnd(value.to_string()),
-			unrecognized => Err(Self::Error::InvalidFlag(unrecognized.to_string()))?
+		match flag_raw.strip_prefix(Self::FLAG_PATTERN) {
+			Some(name) => Self::new(name, value),
+			None => Err(Self::Error::ExpectedFlag(flag_raw.to_string()))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{assert_matches, collections::HashMap};
+
+	use super::{Config, ConfigErr, FlagAddErr, RunMode, SourceKind};
+
+	fn parse(args: &[&str], env: &[(&str, &str)]) -> Result<Config, ConfigErr> {
+		let env = env
+			.iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect::<HashMap<_, _>>();
+		Config::parse(args.iter().map(|s| s.to_string()), |key| {
+			env.get(key).cloned()
 		})
 	}
+
+	#[test]
+	fn flags_in_any_order() {
+		let config = parse(
+			&[
+				"--min_wait=10",
+				"--token",
+				"secret",
+				"--hosts_path",
+				"hosts",
+				"--url",
+				"http://localhost/hosts"
+			],
+			&[]
+		)
+		.unwrap();
+		assert_eq!(config.token, "secret");
+		assert_eq!(config.url.as_str(), "http://localhost/hosts");
+		assert_eq!(config.hosts_path.to_str(), Some("hosts"));
+		assert_eq!(config.min_wait_ms, 10);
+		assert_eq!(config.source, SourceKind::Chrome);
+	}
+
+	#[test]
+	fn flags_fail() {
+		assert_matches!(parse(&["--help"], &[]), Err(ConfigErr::Help));
+		assert_matches!(parse(&["--token", "a", "-V"], &[]), Err(ConfigErr::Version));
+		assert_matches!(parse(&[], &[]), Err(ConfigErr::MissingToken));
+		assert_matches!(
+			parse(&["--token", "a", "--min_wait"], &[]),
+			Err(ConfigErr::InvalidFlag(FlagAddErr::MissingValue(_)))
+		);
+		assert_matches!(
+			parse(&["token", "a"], &[]),
+			Err(ConfigErr::InvalidFlag(FlagAddErr::ExpectedFlag(_)))
+		);
+		assert_matches!(
+			parse(&["--token", "a", "--token", "b"], &[]),
+			Err(ConfigErr::InvalidFlag(FlagAddErr::AlreadySet(_)))
+		);
+		assert_matches!(
+			parse(&["--tokn", "a"], &[]),
+			Err(ConfigErr::InvalidFlag(FlagAddErr::InvalidFlag(_)))
+		);
+		assert_matches!(
+			parse(&["--token", "a", "--url", "not a url"], &[]),
+			Err(ConfigErr::InvalidUrl(..))
+		);
+	}
+
+	#[test]
+	fn waits_are_validated() {
+		assert_matches!(
+			parse(&["--token", "a", "--mid_wait", "100000"], &[]),
+			Err(ConfigErr::InvalidWaits { .. })
+		);
+		assert_matches!(
+			parse(&["--token", "a", "--min_wait", "0"], &[]),
+			Err(ConfigErr::InvalidWaits { .. })
+		);
+		assert_matches!(
+			parse(&["--token", "a", "--min_wait=5", "--mid_wait=5", "--max_wait=5"], &[]),
+			Ok(_)
+		);
+	}
+
+	#[test]
+	fn layered_sources() {
+		let dir = tempfile::tempdir().unwrap();
+		let file = dir.path().join("hosts-watch.toml");
+		std::fs::write(
+			&file,
+			"token = \"from-file\"\nmax_wait = 20000\nsource = \"http\"\nmid_wait = 1000\n"
+		)
+		.unwrap();
+		let file = file.to_str().unwrap();
+
+		let config = parse(
+			&["--config", file, "--mid_wait", "2000"],
+			&[("HOSTS_WATCH_TOKEN", "from-env")]
+		)
+		.unwrap();
+		assert_eq!(config.token, "from-env");
+		assert_eq!(config.source, SourceKind::Http);
+		assert_eq!(config.mid_wait_ms, 2000);
+		assert_eq!(config.max_wait_ms, 20000);
+
+		let config = parse(&[], &[("HOSTS_WATCH_CONFIG", file)]).unwrap();
+		assert_eq!(config.token, "from-file");
+
+		std::fs::write(dir.path().join("bad.toml"), "tokn = 1").unwrap();
+		assert_matches!(
+			parse(
+				&["--config", dir.path().join("bad.toml").to_str().unwrap()],
+				&[]
+			),
+			Err(ConfigErr::InvalidConfigFile(..))
+		);
+	}
+
+	#[test]
+	fn run_modes() {
+		let mode = |args: &[&str]| parse(args, &[("HOSTS_WATCH_TOKEN", "a")]).map(|c| c.mode);
+		assert_matches!(mode(&[]), Ok(RunMode::Watch));
+		assert_matches!(mode(&["--once"]), Ok(RunMode::Once));
+		assert_matches!(mode(&["--dry-run", "--min_wait", "10"]), Ok(RunMode::DryRun));
+		assert_matches!(mode(&["--mode=dry-run"]), Ok(RunMode::DryRun));
+		assert_matches!(
+			mode(&["--once", "--dry-run"]),
+			Err(ConfigErr::InvalidFlag(FlagAddErr::AlreadySet(_)))
+		);
+		assert_matches!(
+			mode(&["--mode", "twice"]),
+			Err(ConfigErr::InvalidFlag(FlagAddErr::InvalidMode(_)))
+		);
+	}
+
+	#[test]
+	fn token_only_needed_for_web() {
+		let config = parse(&["--source", "file:entries.txt"], &[]).unwrap();
+		assert_eq!(config.source, SourceKind::File("entries.txt".into()));
+		assert_eq!(config.token, "");
+	}
 }
@@ -0,0 +1,418 @@
+use std::{
+	collections::HashSet,
+	fmt::{Display, Formatter},
+	net::{AddrParseError, IpAddr},
+	str::FromStr
+};
+
+use itertools::Itertools;
+use url::Host;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct HostFileEntries(pub HashSet<Entry>);
+impl HostFileEntries {
+	/// Reads hosts-file style text, skipping any line that isn't an entry.
+	pub fn parse_lines(s: &str) -> Self {
+		HostFileEntries(s.lines().filter_map(|l| l.parse().ok()).collect())
+	}
+
+	/// What it takes to go from `prev` to `self`.
+	/// Entries for the same hostname and address family count as changed rather than
+	/// removed and added.
+	pub fn changes_from<'a>(&'a self, prev: &'a Self) -> Vec<EntryChange<'a>> {
+		let key = |e: &Entry| (e.hosts[0].to_string(), e.ip.is_ipv4());
+		let mut added = self.0.difference(&prev.0).collect::<Vec<_>>();
+		let mut removed = prev.0.difference(&self.0).collect::<Vec<_>>();
+		added.sort_by_key(|e| e.to_string());
+		removed.sort_by_key(|e| e.to_string());
+		let mut changes = Vec::new();
+		for old in removed {
+			match added.iter().position(|new| key(new) == key(old)) {
+				Some(i) => changes.push(EntryChange::Changed {
+					old,
+					new: added.remove(i)
+				}),
+				None => changes.push(EntryChange::Removed(old))
+			}
+		}
+		changes.extend(added.into_iter().map(EntryChange::Added));
+		changes.sort_by_key(|c| c.entry().hosts[0].to_string());
+		changes
+	}
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum EntryChange<'a> {
+	Added(&'a Entry),
+	Removed(&'a Entry),
+	Changed { old: &'a Entry, new: &'a Entry }
+}
+impl EntryChange<'_> {
+	/// The entry as it ends up, or as it was if removed.
+	pub fn entry(&self) -> &Entry {
+		match self {
+			EntryChange::Added(entry) | EntryChange::Removed(entry) => entry,
+			EntryChange::Changed { new, .. } => new
+		}
+	}
+}
+impl From<scraper::Html> for HostFileEntries {
+	fn from(fragment: scraper::Html) -> Self {
+		eprintln!("HTML: {}\n\n\n", fragment.html());
+		//    let p_selector = Selector::parse("p").unwrap();
+		let selector = scraper::Selector::parse("p").unwrap();
+		let vec = fragment
+			.select(&selector)
+			.filter_map(|e_r| {
+				let inner = e_r.inner_html();
+				println!("Finding e_r:\n - dbg: {:?}\n - {}", e_r, inner);
+				inner
+					.parse()
+					.inspect_err(|e| {
+						eprintln!("Tried and failed to parse element reference:\n{:?}\n", e)
+					})
+					.ok()
+			})
+			.collect();
+		eprintln!();
+		HostFileEntries(vec)
+	}
+}
+
+/// Found in https://hackeve.haaukins.dk/hosts as `127.0.0.1 sanity-checks.hkn`,
+/// but may also be an IPv6 address with several aliases and a trailing `# comment`.
+#[derive(Debug, Eq, PartialEq, Hash)]
+pub struct Entry {
+	pub ip:      IpAddr,
+	/// Canonical hostname first, followed by any aliases. Never empty.
+	pub hosts:   Vec<Host>,
+	pub comment: Option<String>
+}
+impl Entry {
+	const COMMENT_PATTERN: char = '#';
+}
+#[derive(Debug, Eq, PartialEq)]
+pub enum EntryParseErr {
+	SplitError,
+	Ip(AddrParseError),
+	Url(url::ParseError),
+	Both {
+		ip_e:   AddrParseError,
+		host_e: url::ParseError
+	}
+}
+impl FromStr for Entry {
+	type Err = EntryParseErr;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (data, comment) = match s.split_once(Self::COMMENT_PATTERN) {
+			Some((data, comment)) => (data, Some(comment.trim().to_string())),
+			None => (s, None)
+		};
+		let mut tokens = data.split_whitespace();
+		let (Some(ip_s), Some(host_s)) = (tokens.next(), tokens.next()) else {
+			Err(Self::Err::SplitError)?
+		};
+		match (ip_s.parse::<IpAddr>(), Host::parse(host_s)) {
+			(Ok(ip), Ok(host)) => Ok(Entry {
+				ip,
+				hosts: std::iter::once(Ok(host))
+					.chain(tokens.map(Host::parse))
+					.collect::<Result<_, _>>()
+					.map_err(Self::Err::Url)?,
+				comment
+			}),
+			(Ok(_), Err(host_e)) => Err(Self::Err::Url(host_e)),
+			(Err(ip_e), Ok(_)) => Err(Self::Err::Ip(ip_e)),
+			(Err(ip_e), Err(host_e)) => Err(Self::Err::Both { ip_e, host_e })
+		}
+	}
+}
+impl Display for Entry {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {}", self.ip, self.hosts.iter().join(" "))?;
+		match &self.comment {
+			Some(comment) => write!(f, " {} {}", Self::COMMENT_PATTERN, comment),
+			None => Ok(())
+		}
+	}
+}
+
+/// The same hostname pointed at two different addresses of the same family,
+/// either twice in the managed block or once in it and once elsewhere in the hosts file.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Conflict {
+	pub host:    Host,
+	pub managed: IpAddr,
+	pub other:   IpAddr,
+	/// Whether `other` is in the managed block too.
+	pub inside:  bool
+}
+impl Conflict {
+	pub fn find<'a>(
+		managed: impl IntoIterator<Item = &'a Entry>,
+		other: impl IntoIterator<Item = &'a Entry>
+	) -> Vec<Self> {
+		let managed = managed.into_iter().collect::<Vec<_>>();
+		let other = other.into_iter().collect::<Vec<_>>();
+		// each pair inside the block is found from both ends, so only keep one of them
+		let mut conflicts = Self::between(&managed, &managed, true);
+		conflicts.retain(|c| c.managed < c.other);
+		conflicts.extend(Self::between(&managed, &other, false));
+		conflicts
+			.into_iter()
+			.unique_by(|c| (c.host.clone(), c.managed, c.other))
+			.collect()
+	}
+
+	fn between(managed: &[&Entry], other: &[&Entry], inside: bool) -> Vec<Self> {
+		managed
+			.iter()
+			.flat_map(|m| m.hosts.iter().map(move |host| (m.ip, host)))
+			.flat_map(|(ip, host)| {
+				other
+					.iter()
+					.filter(move |o| {
+						o.ip != ip && o.ip.is_ipv4() == ip.is_ipv4() && o.hosts.contains(host)
+					})
+					.map(move |o| Conflict {
+						host:    host.clone(),
+						managed: ip,
+						other:   o.ip,
+						inside
+					})
+			})
+			.collect()
+	}
+}
+impl Display for Conflict {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self.inside {
+			true => write!(
+				f,
+				"'{}' is mapped to both {} and {} in the managed block.",
+				self.host, self.managed, self.other
+			),
+			false => write!(
+				f,
+				"'{}' is mapped to {} in the managed block, but to {} elsewhere in the hosts file.",
+				self.host, self.managed, self.other
+			)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Conflict, Entry, EntryParseErr};
+	mod ip {
+		use std::net::IpAddr;
+
+		use super::Entry;
+
+		#[test]
+		fn parsing_works() {
+			let ips = [
+				("192.168.1.255", [192, 168, 1, 255]),
+				("192.168.1.23", [192, 168, 1, 23]),
+				("127.0.0.1", [127, 0, 0, 1])
+			];
+			for (s, bytes) in ips {
+				let ip = IpAddr::from(bytes);
+				let line = format!("{} test.hkn", s);
+				// test parsing works
+				assert_eq!(line.parse::<Entry>().map(|e| e.ip), Ok(ip));
+				// display works
+				assert_eq!(ip.to_string(), s);
+				// round-trip
+				assert_eq!(
+					line.parse::<Entry>().map(|entry| entry.to_string()),
+					Ok(line.clone())
+				);
+			}
+		}
+
+		#[test]
+		fn ipv6_parsing_works() {
+			for (s, ip) in [
+				("::1", IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1])),
+				(
+					"fe80::1:2",
+					IpAddr::from([0xfe80, 0, 0, 0, 0, 0, 1, 2])
+				)
+			] {
+				let line = format!("{} localhost", s);
+				assert_eq!(line.parse::<Entry>().map(|e| e.ip), Ok(ip));
+				assert_eq!(line.parse::<Entry>().unwrap().to_string(), line);
+			}
+		}
+	}
+
+	mod entry {
+		use std::{assert_matches, net::IpAddr};
+
+		use url::Host;
+
+		use super::{Entry, EntryParseErr};
+		#[test]
+		fn parsing_works() {
+			let string = "192.168.1.255 test.haaukins.hkn";
+			let entry = Entry {
+				ip:      IpAddr::from([192, 168, 1, 255]),
+				hosts:   vec![Host::parse("test.haaukins.hkn").unwrap()],
+				comment: None
+			};
+			assert_eq!(entry.to_string(), string);
+			assert_eq!(string.parse(), Ok(entry));
+		}
+		#[test]
+		fn aliases_and_comments() {
+			let string = "::1 localhost ip6-localhost ip6-loopback # loopback";
+			let entry = Entry {
+				ip:      IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]),
+				hosts:   ["localhost", "ip6-localhost", "ip6-loopback"]
+					.map(|h| Host::parse(h).unwrap())
+					.to_vec(),
+				comment: Some("loopback".to_string())
+			};
+			assert_eq!(entry.to_string(), string);
+			assert_eq!(string.parse(), Ok(entry));
+			// whitespace is normalized on the way back
+			assert_eq!(
+				"10.0.0.1\ta.hkn   b.hkn#note".parse::<Entry>().map(|e| e.to_string()),
+				Ok("10.0.0.1 a.hkn b.hkn # note".to_string())
+			);
+		}
+		#[test]
+		fn parsing_fails() {
+			assert_eq!(
+				"192.168.1.255test.haaukins.hkn".parse::<Entry>(),
+				Err(EntryParseErr::SplitError)
+			);
+			assert_eq!(
+				"192.168.1.255 # test.haaukins.hkn".parse::<Entry>(),
+				Err(EntryParseErr::SplitError)
+			);
+			assert_matches!(
+				"192.168.1. test.haaukins.hkn".parse::<Entry>(),
+				Err(EntryParseErr::Ip(_))
+			);
+			assert_matches!(
+				"192.168.1.256 test.haaukins.hkn".parse::<Entry>(),
+				Err(EntryParseErr::Ip(_))
+			);
+			assert_matches!(
+				"192.168.1 1test.haaukins.hkn".parse::<Entry>(),
+				Err(EntryParseErr::Ip(_))
+			);
+			assert_matches!(
+				"::1::2 localhost".parse::<Entry>(),
+				Err(EntryParseErr::Ip(_))
+			);
+			assert_matches!(
+				"192.168.1.255 test:hkn".parse::<Entry>(),
+				Err(EntryParseErr::Url(_))
+			);
+			assert_matches!(
+				"192.168.1.255 test.hkn alias:hkn".parse::<Entry>(),
+				Err(EntryParseErr::Url(_))
+			);
+			assert_matches!(
+				"192.168.125 haaukins<test.hkn".parse::<Entry>(),
+				Err(EntryParseErr::Both {
+					ip_e:   _,
+					host_e: _
+				})
+			);
+		}
+	}
+
+	mod changes {
+		use super::super::{EntryChange, HostFileEntries};
+
+		#[test]
+		fn added_removed_changed() {
+			let prev = HostFileEntries::parse_lines(
+				"10.0.0.1 a.hkn\n10.0.0.2 b.hkn\n10.0.0.3 c.hkn\n::1 d.hkn"
+			);
+			let next = HostFileEntries::parse_lines(
+				"10.0.0.1 a.hkn\n10.0.0.9 b.hkn\n10.0.0.4 e.hkn\n10.0.0.5 d.hkn"
+			);
+			let changes = next.changes_from(&prev);
+			assert_eq!(
+				changes
+					.iter()
+					.map(|c| match c {
+						EntryChange::Added(e) => format!("added {}", e),
+						EntryChange::Removed(e) => format!("removed {}", e),
+						EntryChange::Changed { old, new } => format!("changed {} to {}", old, new)
+					})
+					.collect::<Vec<_>>(),
+				[
+					"changed 10.0.0.2 b.hkn to 10.0.0.9 b.hkn",
+					"removed 10.0.0.3 c.hkn",
+					"removed ::1 d.hkn",
+					"added 10.0.0.5 d.hkn",
+					"added 10.0.0.4 e.hkn"
+				]
+			);
+			assert_eq!(prev.changes_from(&prev), vec![]);
+		}
+	}
+
+	mod conflict {
+		use std::net::IpAddr;
+
+		use url::Host;
+
+		use super::{Conflict, Entry};
+
+		fn entries(lines: &[&str]) -> Vec<Entry> {
+			lines.iter().map(|l| l.parse().unwrap()).collect()
+		}
+
+		#[test]
+		fn finds_remapped_hosts() {
+			let managed = entries(&["10.0.0.1 a.hkn alias.hkn", "10.0.0.2 b.hkn"]);
+			let other = entries(&[
+				"127.0.0.1 localhost",
+				"::1 localhost a.hkn",
+				"10.0.0.9 alias.hkn",
+				"10.0.0.2 b.hkn"
+			]);
+			assert_eq!(
+				Conflict::find(&managed, &other),
+				vec![Conflict {
+					host:    Host::parse("alias.hkn").unwrap(),
+					managed: IpAddr::from([10, 0, 0, 1]),
+					other:   IpAddr::from([10, 0, 0, 9]),
+					inside:  false
+				}]
+			);
+			assert_eq!(Conflict::find(&managed, &managed), vec![]);
+		}
+
+		#[test]
+		fn finds_remapped_hosts_inside_block() {
+			let managed = entries(&[
+				"10.0.0.2 a.hkn",
+				"10.0.0.1 b.hkn a.hkn",
+				"::1 a.hkn",
+				"10.0.0.3 c.hkn"
+			]);
+			assert_eq!(
+				Conflict::find(&managed, &[]),
+				vec![Conflict {
+					host:    Host::parse("a.hkn").unwrap(),
+					managed: IpAddr::from([10, 0, 0, 1]),
+					other:   IpAddr::from([10, 0, 0, 2]),
+					inside:  true
+				}]
+			);
+			assert_eq!(
+				Conflict::find(&managed, &[])[0].to_string(),
+				"'a.hkn' is mapped to both 10.0.0.1 and 10.0.0.2 in the managed block."
+			);
+		}
+	}
+}
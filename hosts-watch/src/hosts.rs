@@ -0,0 +1,329 @@
+use std::{
+	fs,
+	io::Write,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH}
+};
+
+use crate::entry::{Conflict, Entry, HostFileEntries};
+
+/// A hosts file, split up around the block that hosts-watch manages.
+/// Everything outside the block is kept exactly as it was read, in order.
+#[derive(Debug, Eq, PartialEq)]
+pub struct HostsFile {
+	before:           Vec<String>,
+	/// Lines between the markers, or `None` if the markers are not in the file (yet).
+	managed:          Option<Vec<String>>,
+	after:            Vec<String>,
+	target_begin:     String,
+	target_end:       String,
+	line_ending:      &'static str,
+	trailing_newline: bool
+}
+
+impl HostsFile {
+	pub fn read(path: &Path, target_begin: &str, target_end: &str) -> std::io::Result<Self> {
+		Ok(Self::parse(
+			&fs::read_to_string(path)?,
+			target_begin,
+			target_end
+		))
+	}
+
+	pub fn parse(content: &str, target_begin: &str, target_end: &str) -> Self {
+		let lines = content.lines().map(str::to_string).collect::<Vec<_>>();
+		let (before, managed, after) = match lines.iter().position(|l| l.trim() == target_begin) {
+			None => (lines, None, Vec::new()),
+			Some(begin) => {
+				let rest = &lines[begin + 1..];
+				// a missing end marker means the block runs to the end of the file
+				let end = rest
+					.iter()
+					.position(|l| l.trim() == target_end)
+					.unwrap_or(rest.len());
+				(
+					lines[..begin].to_vec(),
+					Some(rest[..end].to_vec()),
+					rest.get(end + 1..).unwrap_or_default().to_vec()
+				)
+			}
+		};
+		Self {
+			before,
+			managed,
+			after,
+			target_begin: target_begin.to_string(),
+			target_end: target_end.to_string(),
+			line_ending: if content.contains("\r\n") { "\r\n" } else { "\n" },
+			trailing_newline: content.is_empty() || content.ends_with('\n')
+		}
+	}
+
+	/// Entries currently written inside the managed block.
+	pub fn entries(&self) -> HostFileEntries {
+		HostFileEntries(
+			self.managed
+				.iter()
+				.flatten()
+				.filter_map(|l| l.parse::<Entry>().ok())
+				.collect()
+		)
+	}
+
+	/// Hostnames in the managed block that the block itself or the rest of the file maps somewhere
+	/// else.
+	pub fn conflicts(&self) -> Vec<Conflict> {
+		let managed = self.entries();
+		let other = self
+			.before
+			.iter()
+			.chain(&self.after)
+			.filter_map(|l| l.parse::<Entry>().ok())
+			.collect::<Vec<_>>();
+		Conflict::find(&managed.0, &other)
+	}
+
+	/// Replaces the contents of the managed block, adding the markers at the end of the file if
+	/// they are missing.
+	pub fn set_entries(&mut self, entries: &HostFileEntries) {
+		let mut lines = entries.0.iter().map(Entry::to_string).collect::<Vec<_>>();
+		lines.sort(); // sets are unordered, keep rewrites stable
+		self.managed = Some(lines);
+	}
+
+	pub fn render(&self) -> String {
+		let mut lines = self.before.iter().map(String::as_str).collect::<Vec<_>>();
+		if let Some(managed) = &self.managed {
+			lines.push(&self.target_begin);
+			lines.extend(managed.iter().map(String::as_str));
+			lines.push(&self.target_end);
+		}
+		lines.extend(self.after.iter().map(String::as_str));
+		let mut content = lines.join(self.line_ending);
+		if self.trailing_newline && !content.is_empty() {
+			content.push_str(self.line_ending);
+		}
+		content
+	}
+
+	/// Writes the file through a temporary file next to `path`, which is then renamed over it.
+	/// The previous version is copied to a timestamped backup first, whose path is returned.
+	/// Only that backup is kept, older ones are removed so watching does not fill the directory.
+	pub fn write(&self, path: &Path) -> std::io::Result<Option<PathBuf>> {
+		let file_name = path
+			.file_name()
+			.ok_or(std::io::ErrorKind::InvalidInput)?
+			.to_string_lossy();
+		let sibling = |name: String| path.with_file_name(name);
+
+		let backup = match fs::metadata(path) {
+			Ok(_) => {
+				let millis = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.unwrap_or_default()
+					.as_millis();
+				let backup = sibling(format!("{}.{}.bak", file_name, millis));
+				fs::copy(path, &backup)?;
+				remove_backups(path, &file_name, &backup)?;
+				Some(backup)
+			},
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+			Err(e) => Err(e)?
+		};
+
+		let temp = sibling(format!(".{}.hosts-watch.tmp", file_name));
+		let result = (|| {
+			let mut file = fs::File::create(&temp)?;
+			file.write_all(self.render().as_bytes())?;
+			file.sync_all()?;
+			if let Ok(metadata) = fs::metadata(path) {
+				fs::set_permissions(&temp, metadata.permissions())?;
+			}
+			fs::rename(&temp, path)
+		})();
+		if result.is_err() {
+			let _ = fs::remove_file(&temp);
+		}
+		result.map(|_| backup)
+	}
+}
+
+/// Removes the backups of the file at `path` made by [`HostsFile::write`], except `keep`.
+fn remove_backups(path: &Path, file_name: &str, keep: &Path) -> std::io::Result<()> {
+	let dir = match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new(".")
+	};
+	for entry in fs::read_dir(dir)? {
+		let backup = entry?.path();
+		let is_backup = backup
+			.file_name()
+			.and_then(|name| name.to_str())
+			.and_then(|name| name.strip_prefix(file_name))
+			.and_then(|name| name.strip_prefix('.'))
+			.and_then(|name| name.strip_suffix(".bak"))
+			.is_some_and(|millis| {
+				!millis.is_empty() && millis.bytes().all(|b| b.is_ascii_digit())
+			});
+		if is_backup && backup.file_name() != keep.file_name() {
+			fs::remove_file(backup)?;
+		}
+	}
+	Ok(())
+}
+
+/// The changes from `old` to `new` as a unified diff of the file at `path`, with three lines of
+/// context, so `patch` can apply it.
+pub fn unified_diff(old: &str, new: &str, path: &str) -> String {
+	similar::TextDiff::from_lines(old, new)
+		.unified_diff()
+		.context_radius(3)
+		.header(path, path)
+		.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use super::{unified_diff, HostsFile};
+	use crate::entry::{Entry, HostFileEntries};
+
+	const BEGIN: &str = "/// ctf_top ///";
+	const END: &str = "/// ctf_bottom ///";
+
+	fn entries(lines: &[&str]) -> HostFileEntries {
+		HostFileEntries(
+			lines
+				.iter()
+				.map(|l| l.parse::<Entry>().unwrap())
+				.collect::<HashSet<_>>()
+		)
+	}
+
+	#[test]
+	fn untouched_round_trip() {
+		for content in [
+			"",
+			"# comment\n\n127.0.0.1 localhost\n127.0.0.1 localhost\n",
+			"# no trailing newline",
+			"# crlf\r\n/// ctf_top ///\r\n10.0.0.1 a.hkn\r\n/// ctf_bottom ///\r\n",
+			"a\n/// ctf_top ///\n10.0.0.1 a.hkn\n/// ctf_bottom ///\nb\n\n"
+		] {
+			assert_eq!(HostsFile::parse(content, BEGIN, END).render(), content);
+		}
+	}
+
+	#[test]
+	fn reads_managed_block() {
+		let file = HostsFile::parse(
+			"10.0.0.9 outside.hkn\n/// ctf_top ///\n10.0.0.1 a.hkn\n# note\n/// ctf_bottom ///\n",
+			BEGIN,
+			END
+		);
+		assert_eq!(file.entries(), entries(&["10.0.0.1 a.hkn"]));
+	}
+
+	#[test]
+	fn rewrites_only_managed_block() {
+		let mut file = HostsFile::parse(
+			"# z\n\n# a\n/// ctf_top ///\n10.0.0.1 old.hkn\n/// ctf_bottom ///\n# z\n# z\n",
+			BEGIN,
+			END
+		);
+		file.set_entries(&entries(&["10.0.0.3 c.hkn", "10.0.0.2 b.hkn"]));
+		assert_eq!(
+			file.render(),
+			"# z\n\n# a\n/// ctf_top ///\n10.0.0.2 b.hkn\n10.0.0.3 c.hkn\n/// ctf_bottom ///\n# z\n# z\n"
+		);
+	}
+
+	#[test]
+	fn creates_missing_markers() {
+		let mut file = HostsFile::parse("127.0.0.1 localhost\n", BEGIN, END);
+		assert_eq!(file.entries(), entries(&[]));
+		file.set_entries(&entries(&["10.0.0.1 a.hkn"]));
+		assert_eq!(
+			file.render(),
+			"127.0.0.1 localhost\n/// ctf_top ///\n10.0.0.1 a.hkn\n/// ctf_bottom ///\n"
+		);
+	}
+
+	#[test]
+	fn keeps_aliases_and_comments() {
+		let content = concat!(
+			"::1 localhost ip6-localhost # loopback\n",
+			"/// ctf_top ///\n",
+			"fd00::1 a.hkn alias.hkn # from the page\n",
+			"/// ctf_bottom ///\n"
+		);
+		let mut file = HostsFile::parse(content, BEGIN, END);
+		let managed = file.entries();
+		assert_eq!(
+			managed,
+			entries(&["fd00::1 a.hkn alias.hkn # from the page"])
+		);
+		file.set_entries(&managed);
+		assert_eq!(file.render(), content);
+	}
+
+	#[test]
+	fn finds_conflicts_outside_block() {
+		let file = HostsFile::parse(
+			concat!(
+				"10.0.0.9 b.hkn\n",
+				"/// ctf_top ///\n",
+				"10.0.0.1 a.hkn\n",
+				"10.0.0.2 b.hkn\n",
+				"/// ctf_bottom ///\n",
+				"10.0.0.1 a.hkn\n",
+				"::1 b.hkn\n"
+			),
+			BEGIN,
+			END
+		);
+		assert_eq!(
+			file.conflicts()
+				.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>(),
+			[concat!(
+				"'b.hkn' is mapped to 10.0.0.2 in the managed block, ",
+				"but to 10.0.0.9 elsewhere in the hosts file."
+			)]
+		);
+	}
+
+	#[test]
+	fn closes_unterminated_block() {
+		let mut file = HostsFile::parse("# a\n/// ctf_top ///\n10.0.0.1 a.hkn\n", BEGIN, END);
+		assert_eq!(file.entries(), entries(&["10.0.0.1 a.hkn"]));
+		file.set_entries(&entries(&["10.0.0.2 b.hkn"]));
+		assert_eq!(
+			file.render(),
+			"# a\n/// ctf_top ///\n10.0.0.2 b.hkn\n/// ctf_bottom ///\n"
+		);
+	}
+
+	#[test]
+	fn diffs_with_context() {
+		let content = concat!(
+			"1\n2\n3\n4\n",
+			"/// ctf_top ///\n10.0.0.1 a.hkn\n10.0.0.2 b.hkn\n/// ctf_bottom ///\n",
+			"5\n"
+		);
+		let mut file = HostsFile::parse(content, BEGIN, END);
+		file.set_entries(&entries(&["10.0.0.1 a.hkn", "10.0.0.9 b.hkn", "10.0.0.3 c.hkn"]));
+		assert_eq!(
+			unified_diff(content, &file.render(), "/etc/hosts"),
+			concat!(
+				"--- /etc/hosts\n+++ /etc/hosts\n",
+				"@@ -4,6 +4,7 @@\n",
+				" 4\n /// ctf_top ///\n 10.0.0.1 a.hkn\n",
+				"-10.0.0.2 b.hkn\n+10.0.0.3 c.hkn\n+10.0.0.9 b.hkn\n",
+				" /// ctf_bottom ///\n 5\n"
+			)
+		);
+		assert_eq!(unified_diff(content, content, "/etc/hosts"), "");
+	}
+}
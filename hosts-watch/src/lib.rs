@@ -0,0 +1,75 @@
+#![feature(assert_matches)]
+
+pub mod command;
+pub mod config;
+pub mod entry;
+pub mod hosts;
+pub mod source;
+
+use crate::{
+	config::{Config, RunMode},
+	entry::HostFileEntries,
+	hosts::{unified_diff, HostsFile},
+	source::SharedSource
+};
+
+/// Fetches from the source and brings the hosts file up to date.
+/// Failing to fetch leaves the hosts file alone, and only backs off the wait.
+pub async fn refresh_state_from_source(
+	source: &SharedSource,
+	config: &Config,
+	ms_to_wait: &mut u64
+) -> std::io::Result<bool> {
+	match crate::source::fetch(source).await {
+		Ok(entries) => refresh_state(entries, config, ms_to_wait).await,
+		Err(e) => {
+			println!("{}", e);
+			*ms_to_wait = u64::min(ms_to_wait.saturating_mul(2), config.max_wait_ms);
+			println!("Doubling wait time...");
+			Ok(false)
+		}
+	}
+}
+
+/// Brings the hosts file up to date with `entries`, returning whether anything changed.
+/// No entries empty the managed block. In a dry run the changes are only printed.
+pub async fn refresh_state(
+	entries: HostFileEntries,
+	config: &Config,
+	ms_to_wait: &mut u64
+) -> std::io::Result<bool> {
+	// TODO: reading/writing requires an administrator shell
+	let mut hosts = HostsFile::read(
+		&config.hosts_path,
+		&config.target_begin,
+		&config.target_end
+	)?;
+	let prev_entries = hosts.entries();
+	let changes = entries.changes_from(&prev_entries);
+
+	if changes.is_empty() {
+		*ms_to_wait = config.mid_wait_ms;
+		println!("No difference between last check.");
+		return Ok(false);
+	}
+	*ms_to_wait = config.min_wait_ms; // reset
+	let before = hosts.render();
+	hosts.set_entries(&entries);
+	for conflict in hosts.conflicts() {
+		println!("Warning: {}", conflict);
+	}
+	let diff = unified_diff(
+		&before,
+		&hosts.render(),
+		&config.hosts_path.display().to_string()
+	);
+	if config.mode == RunMode::DryRun {
+		print!("Pending changes, not written (dry run):\n{}", diff);
+	} else {
+		if let Some(backup) = hosts.write(&config.hosts_path)? {
+			println!("Backed up previous hosts file to '{}'.", backup.display());
+		}
+		print!("Updated hosts file:\n{}", diff);
+	}
+	Ok(true)
+}
@@ -0,0 +1,226 @@
+use std::{
+	fmt::{Display, Formatter},
+	io::Read,
+	path::PathBuf,
+	sync::{Arc, Mutex}
+};
+
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use notify::Watcher;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::{
+	config::{Config, SourceKind},
+	entry::HostFileEntries
+};
+
+/// Somewhere to get the current entries from.
+/// Fetching is blocking, so async callers should go through [`fetch`].
+pub trait EntrySource: Send {
+	fn fetch(&mut self) -> Result<HostFileEntries, SourceErr>;
+
+	/// Sends a message whenever the source has changed,
+	/// for sources that can tell without being fetched.
+	fn watch(&mut self) -> Result<Option<UnboundedReceiver<()>>, SourceErr> {
+		Ok(None)
+	}
+}
+
+/// A source that can be lent to a blocking thread while it fetches.
+pub type SharedSource = Arc<Mutex<dyn EntrySource>>;
+
+pub fn shared(source: impl EntrySource + 'static) -> SharedSource {
+	Arc::new(Mutex::new(source))
+}
+
+/// Opens the source picked in the configuration.
+pub fn from_config(config: &Config) -> Result<SharedSource, SourceErr> {
+	Ok(match &config.source {
+		SourceKind::Chrome => shared(ChromeSource::new(&config.url, &config.token)?),
+		SourceKind::Http => shared(HttpSource::new(config.url.clone(), config.token.clone())),
+		SourceKind::File(path) => shared(FileSource::new(path.clone())),
+		SourceKind::Stdin => shared(StdinSource::default())
+	})
+}
+
+/// Fetches on tokio's blocking threads, which works on any runtime and keeps its workers free.
+pub async fn fetch(source: &SharedSource) -> Result<HostFileEntries, SourceErr> {
+	let source = Arc::clone(source);
+	match tokio::task::spawn_blocking(move || source.lock().unwrap().fetch()).await {
+		Ok(entries) => entries,
+		Err(e) => std::panic::resume_unwind(e.into_panic())
+	}
+}
+
+#[derive(Debug)]
+pub enum SourceErr {
+	Browser(Box<dyn std::error::Error + Send + Sync>),
+	Http(ureq::Error),
+	Io(std::io::Error),
+	Watch(notify::Error)
+}
+impl Display for SourceErr {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SourceErr::Browser(e) => write!(f, "Browser failed: {}", e),
+			SourceErr::Http(e) => write!(f, "HTTP request failed: {}", e),
+			SourceErr::Io(e) => write!(f, "Could not read source: {}", e),
+			SourceErr::Watch(e) => write!(f, "Could not watch source: {}", e)
+		}
+	}
+}
+
+/// Logs into the site through localStorage and scrapes the rendered page.
+pub struct ChromeSource {
+	// kept alive for as long as the tab is used
+	_browser: Browser,
+	tab:      Arc<Tab>
+}
+impl ChromeSource {
+	pub fn new(url: &url::Url, token: &str) -> Result<Self, SourceErr> {
+		let browser_err = |e: anyhow::Error| SourceErr::Browser(e.into());
+		let browser = Browser::new(LaunchOptions {
+			headless: false,
+			..Default::default()
+		})
+		.map_err(browser_err)?;
+		let tab = browser.new_tab().map_err(browser_err)?;
+		// Type "Tab" is effectively a mutable reference, but rust doesn't know that
+		// 		tab.navigate_to("https://prosa.haaukins.dk").unwrap();
+		let origin = url.origin().ascii_serialization();
+		tab.navigate_to(&origin).map_err(browser_err)?;
+		tab.wait_until_navigated().map_err(browser_err)?;
+		// Set a value in local storage
+		let set_local_storage_script = format!("localStorage.setItem('token', '{}')", token);
+		tab.evaluate(set_local_storage_script.as_str(), false)
+			.map_err(browser_err)?;
+		eprintln!("Configured storage (token=...)");
+		// 		tab.navigate_to(config.url.as_str()).unwrap();
+		// 		tab.reload(true, None).unwrap();
+		tab.navigate_to(url.as_str()).map_err(browser_err)?;
+		tab.wait_until_navigated().map_err(browser_err)?;
+		Ok(Self {
+			_browser: browser,
+			tab
+		})
+	}
+}
+impl EntrySource for ChromeSource {
+	fn fetch(&mut self) -> Result<HostFileEntries, SourceErr> {
+		let content = self
+			.tab
+			.get_content()
+			.map_err(|e| SourceErr::Browser(e.into()))?;
+		Ok(scraper::Html::parse_fragment(&content).into())
+	}
+}
+
+/// Plain GET with the token as bearer, accepting either an HTML page or a plain hosts list.
+pub struct HttpSource {
+	agent: ureq::Agent,
+	url:   url::Url,
+	token: String
+}
+impl HttpSource {
+	pub fn new(url: url::Url, token: String) -> Self {
+		Self {
+			agent: ureq::Agent::new_with_defaults(),
+			url,
+			token
+		}
+	}
+}
+impl EntrySource for HttpSource {
+	fn fetch(&mut self) -> Result<HostFileEntries, SourceErr> {
+		let mut response = self
+			.agent
+			.get(self.url.as_str())
+			.header("Authorization", &format!("Bearer {}", self.token))
+			.call()
+			.map_err(SourceErr::Http)?;
+		let is_html = response
+			.headers()
+			.get("Content-Type")
+			.and_then(|v| v.to_str().ok())
+			.is_some_and(|v| v.starts_with("text/html"));
+		let body = response
+			.body_mut()
+			.read_to_string()
+			.map_err(SourceErr::Http)?;
+		Ok(if is_html {
+			scraper::Html::parse_fragment(&body).into()
+		} else {
+			HostFileEntries::parse_lines(&body)
+		})
+	}
+}
+
+/// Reads a hosts list from a file, or from every file in a directory.
+pub struct FileSource {
+	path:     PathBuf,
+	// dropping the watcher stops the notifications
+	_watcher: Option<notify::RecommendedWatcher>
+}
+impl FileSource {
+	pub fn new(path: PathBuf) -> Self {
+		Self {
+			path,
+			_watcher: None
+		}
+	}
+}
+impl EntrySource for FileSource {
+	fn fetch(&mut self) -> Result<HostFileEntries, SourceErr> {
+		let content = if self.path.is_dir() {
+			let mut files = std::fs::read_dir(&self.path)
+				.and_then(|dir| dir.map(|e| e.map(|e| e.path())).collect::<Result<Vec<_>, _>>())
+				.map_err(SourceErr::Io)?;
+			files.retain(|p| p.is_file());
+			files.sort();
+			files
+				.iter()
+				.map(std::fs::read_to_string)
+				.collect::<Result<Vec<_>, _>>()
+				.map_err(SourceErr::Io)?
+				.join("\n")
+		} else {
+			std::fs::read_to_string(&self.path).map_err(SourceErr::Io)?
+		};
+		Ok(HostFileEntries::parse_lines(&content))
+	}
+
+	fn watch(&mut self) -> Result<Option<UnboundedReceiver<()>>, SourceErr> {
+		let (sender, receiver) = mpsc::unbounded_channel();
+		let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+			if event.is_ok_and(|e| !e.kind.is_access()) {
+				let _ = sender.send(());
+			}
+		})
+		.map_err(SourceErr::Watch)?;
+		watcher
+			.watch(&self.path, notify::RecursiveMode::NonRecursive)
+			.map_err(SourceErr::Watch)?;
+		self._watcher = Some(watcher);
+		Ok(Some(receiver))
+	}
+}
+
+/// Reads a hosts list from stdin until it is closed, then keeps serving what was read.
+#[derive(Default)]
+pub struct StdinSource {
+	entries: Option<String>
+}
+impl EntrySource for StdinSource {
+	fn fetch(&mut self) -> Result<HostFileEntries, SourceErr> {
+		if self.entries.is_none() {
+			let mut content = String::new();
+			std::io::stdin()
+				.read_to_string(&mut content)
+				.map_err(SourceErr::Io)?;
+			self.entries = Some(content);
+		}
+		Ok(HostFileEntries::parse_lines(
+			self.entries.as_deref().unwrap_or_default()
+		))
+	}
+}
@@ -1,7 +1,276 @@
-// todo: test that mock host file is changed the way we want it to be changed
-// using mock parameters this also forces a small API refactor/expose
+use std::{
+	fs,
+	io::{BufRead, BufReader, Write},
+	net::TcpListener,
+	path::Path,
+	sync::mpsc
+};
 
-// const HOSTS_DEFAULT: &str = include_str!("hosts_copy");
+use hosts_watch::{
+	config::{Config, RunMode, SourceKind},
+	entry::HostFileEntries,
+	refresh_state,
+	refresh_state_from_source,
+	source::{self, EntrySource, FileSource, HttpSource}
+};
 
-//#[test]
-// fn predictable_update() {}
+const HOSTS_DEFAULT: &str = include_str!("hosts_copy");
+
+fn mock_config(hosts_path: &Path) -> Config {
+	Config {
+		url:          "https://hackeve.haaukins.dk/hosts".parse().unwrap(),
+		source:       SourceKind::Http,
+		mode:         RunMode::Watch,
+		hosts_path:   hosts_path.to_path_buf(),
+		token:        String::new(),
+		min_wait_ms:  50,
+		mid_wait_ms:  100,
+		max_wait_ms:  200,
+		target_begin: "/// ctf_top ///".to_string(),
+		target_end:   "/// ctf_bottom ///".to_string()
+	}
+}
+
+fn backups(dir: &Path) -> usize {
+	fs::read_dir(dir)
+		.unwrap()
+		.filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "bak"))
+		.count()
+}
+
+/// Serves `body` to every request on a local port,
+/// passing on the `Authorization` header of each request.
+fn serve(content_type: &'static str, body: &'static str) -> (url::Url, mpsc::Receiver<String>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let url = format!("http://{}/hosts", listener.local_addr().unwrap())
+		.parse()
+		.unwrap();
+	let (sender, receiver) = mpsc::channel();
+	std::thread::spawn(move || {
+		for mut stream in listener.incoming().map_while(Result::ok) {
+			let authorization = BufReader::new(&stream)
+				.lines()
+				.map_while(Result::ok)
+				.take_while(|l| !l.is_empty())
+				.find_map(|l| l.strip_prefix("authorization: ").map(str::to_string))
+				.unwrap_or_default();
+			let _ = sender.send(authorization);
+			let _ = write!(
+				stream,
+				"HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+				content_type,
+				body.len(),
+				body
+			);
+		}
+	});
+	(url, receiver)
+}
+
+fn parse(fragment: &str) -> HostFileEntries {
+	scraper::Html::parse_fragment(fragment).into()
+}
+
+#[tokio::test]
+async fn predictable_update() {
+	let dir = tempfile::tempdir().unwrap();
+	let hosts_path = dir.path().join("hosts");
+	fs::write(&hosts_path, HOSTS_DEFAULT).unwrap();
+	let config = mock_config(&hosts_path);
+	let mut ms_to_wait = config.min_wait_ms;
+
+	let fragment = "<p>127.0.0.1 sanity-checks.hkn</p><p>10.0.0.2 web.hkn</p>";
+	refresh_state(parse(fragment), &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	let updated = fs::read_to_string(&hosts_path).unwrap();
+	assert_eq!(
+		updated,
+		format!(
+			"{}/// ctf_top ///\n10.0.0.2 web.hkn\n127.0.0.1 sanity-checks.hkn\n/// ctf_bottom ///\n",
+			HOSTS_DEFAULT
+		)
+	);
+	assert_eq!(ms_to_wait, config.min_wait_ms);
+	assert_eq!(backups(dir.path()), 1);
+
+	// same entries again leaves the file alone
+	refresh_state(parse(fragment), &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert_eq!(fs::read_to_string(&hosts_path).unwrap(), updated);
+	assert_eq!(ms_to_wait, config.mid_wait_ms);
+	assert_eq!(backups(dir.path()), 1);
+
+	// the next write replaces the backup instead of adding one
+	std::thread::sleep(std::time::Duration::from_millis(2));
+	refresh_state(parse("<p>10.0.0.2 web.hkn</p>"), &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert_eq!(backups(dir.path()), 1);
+	let backup = fs::read_dir(dir.path())
+		.unwrap()
+		.map(|e| e.unwrap().path())
+		.find(|p| p.extension().is_some_and(|ext| ext == "bak"))
+		.unwrap();
+	assert_eq!(fs::read_to_string(backup).unwrap(), updated);
+}
+
+#[tokio::test]
+async fn preserves_surrounding_lines() {
+	let dir = tempfile::tempdir().unwrap();
+	let hosts_path = dir.path().join("hosts");
+	let original = concat!(
+		"127.0.0.1 localhost\n# keep me\n127.0.0.1 localhost\n\n",
+		"/// ctf_top ///\n10.0.0.1 old.hkn\n/// ctf_bottom ///\n",
+		"::1 localhost\n"
+	);
+	fs::write(&hosts_path, original).unwrap();
+	let config = mock_config(&hosts_path);
+	let mut ms_to_wait = config.min_wait_ms;
+
+	refresh_state(parse("<p>10.0.0.3 new.hkn</p>"), &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert_eq!(
+		fs::read_to_string(&hosts_path).unwrap(),
+		original.replace("10.0.0.1 old.hkn", "10.0.0.3 new.hkn")
+	);
+	// backup holds the previous version
+	let backup = fs::read_dir(dir.path())
+		.unwrap()
+		.map(|e| e.unwrap().path())
+		.find(|p| p.extension().is_some_and(|ext| ext == "bak"))
+		.unwrap();
+	assert_eq!(fs::read_to_string(backup).unwrap(), original);
+	// no temporary file is left behind
+	assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+// the default single-threaded runtime, as fetching must not need a multi-threaded one
+#[tokio::test]
+async fn http_source_loop() {
+	let dir = tempfile::tempdir().unwrap();
+	let hosts_path = dir.path().join("hosts");
+	fs::write(&hosts_path, HOSTS_DEFAULT).unwrap();
+	let mut config = mock_config(&hosts_path);
+	let (url, authorizations) = serve("text/plain", "# served\n10.0.0.4 plain.hkn\n");
+	config.url = url;
+	config.token = "secret".to_string();
+	let mut ms_to_wait = config.min_wait_ms;
+
+	let source = source::shared(HttpSource::new(config.url.clone(), config.token.clone()));
+	refresh_state_from_source(&source, &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert_eq!(authorizations.recv().unwrap(), "Bearer secret");
+	assert!(fs::read_to_string(&hosts_path)
+		.unwrap()
+		.ends_with("/// ctf_top ///\n10.0.0.4 plain.hkn\n/// ctf_bottom ///\n"));
+	assert_eq!(ms_to_wait, config.min_wait_ms);
+
+	refresh_state_from_source(&source, &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert_eq!(ms_to_wait, config.mid_wait_ms);
+}
+
+#[test]
+fn http_source_reads_html() {
+	let (url, _) = serve(
+		"text/html; charset=utf-8",
+		"<html><body><p>10.0.0.5 html.hkn</p><p>not an entry</p></body></html>"
+	);
+	assert_eq!(
+		HttpSource::new(url, String::new()).fetch().unwrap(),
+		parse("<p>10.0.0.5 html.hkn</p>")
+	);
+}
+
+#[tokio::test]
+async fn unreachable_source_backs_off() {
+	let dir = tempfile::tempdir().unwrap();
+	let hosts_path = dir.path().join("hosts");
+	fs::write(&hosts_path, HOSTS_DEFAULT).unwrap();
+	let config = mock_config(&hosts_path);
+	let mut ms_to_wait = config.min_wait_ms;
+
+	let source = source::shared(FileSource::new(dir.path().join("missing")));
+	refresh_state_from_source(&source, &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert_eq!(fs::read_to_string(&hosts_path).unwrap(), HOSTS_DEFAULT);
+	// doubles instead of jumping straight to the maximum
+	assert_eq!(ms_to_wait, config.min_wait_ms * 2);
+}
+
+#[test]
+fn file_source_reads_directory() {
+	let dir = tempfile::tempdir().unwrap();
+	fs::write(dir.path().join("a"), "10.0.0.6 a.hkn\n").unwrap();
+	fs::write(dir.path().join("b"), "# comment\n10.0.0.7 b.hkn").unwrap();
+	let mut source = FileSource::new(dir.path().to_path_buf());
+	assert_eq!(
+		source.fetch().unwrap(),
+		HostFileEntries::parse_lines("10.0.0.6 a.hkn\n10.0.0.7 b.hkn")
+	);
+
+	// edits are announced through the watcher
+	let mut changes = source.watch().unwrap().unwrap();
+	fs::write(dir.path().join("c"), "10.0.0.8 c.hkn\n").unwrap();
+	changes.blocking_recv().unwrap();
+	assert_eq!(source.fetch().unwrap().0.len(), 3);
+}
+
+#[tokio::test]
+async fn dry_run_writes_nothing() {
+	let dir = tempfile::tempdir().unwrap();
+	let hosts_path = dir.path().join("hosts");
+	let original = "/// ctf_top ///\n10.0.0.1 old.hkn\n/// ctf_bottom ///\n";
+	fs::write(&hosts_path, original).unwrap();
+	let mut config = mock_config(&hosts_path);
+	config.mode = RunMode::DryRun;
+	let mut ms_to_wait = config.min_wait_ms;
+
+	let changed = refresh_state(parse("<p>10.0.0.2 new.hkn</p>"), &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert!(changed);
+	assert_eq!(fs::read_to_string(&hosts_path).unwrap(), original);
+	assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+	let changed = refresh_state(parse("<p>10.0.0.1 old.hkn</p>"), &config, &mut ms_to_wait)
+		.await
+		.unwrap();
+	assert!(!changed);
+}
+
+#[tokio::test]
+async fn removes_last_entry() {
+	let dir = tempfile::tempdir().unwrap();
+	let hosts_path = dir.path().join("hosts");
+	let original = "# keep\n/// ctf_top ///\n10.0.0.1 old.hkn\n/// ctf_bottom ///\n";
+	fs::write(&hosts_path, original).unwrap();
+	let entries_path = dir.path().join("entries");
+	fs::write(&entries_path, "# nothing left\n").unwrap();
+	let mut config = mock_config(&hosts_path);
+	let mut ms_to_wait = config.max_wait_ms;
+	let source = source::shared(FileSource::new(entries_path));
+
+	// an empty source is a removal, not a failure to back off from
+	config.mode = RunMode::DryRun;
+	assert!(refresh_state_from_source(&source, &config, &mut ms_to_wait)
+		.await
+		.unwrap());
+	assert_eq!(fs::read_to_string(&hosts_path).unwrap(), original);
+	assert_eq!(ms_to_wait, config.min_wait_ms);
+
+	config.mode = RunMode::Once;
+	assert!(refresh_state_from_source(&source, &config, &mut ms_to_wait)
+		.await
+		.unwrap());
+	assert_eq!(
+		fs::read_to_string(&hosts_path).unwrap(),
+		"# keep\n/// ctf_top ///\n/// ctf_bottom ///\n"
+	);
+}
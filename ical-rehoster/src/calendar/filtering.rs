@@ -1,103 +1,136 @@
-use ical::generator::{Emitter, IcalCalendar, IcalEvent};
-use ical::parser::ical::component::{
-    IcalAlarm, IcalFreeBusy, IcalJournal, IcalTimeZone, IcalTimeZoneTransition, IcalTodo,
-};
+use ical::generator::{IcalCalendar, IcalEvent};
+use ical::parser::ical::component::{IcalAlarm, IcalJournal, IcalTodo};
 use ical::property::Property;
 
+use super::rules::FilterRules;
+use super::transforms;
+
 pub(crate) trait FilterBuild {
-    fn build(&self) -> String;
+    /// The calendar with only the kept components, rewritten by the transforms.
+    fn filter(&self, rules: &FilterRules) -> IcalCalendar;
 }
 impl FilterBuild for IcalCalendar {
-    fn build(&self) -> String {
-        let new = IcalCalendar {
-            properties: self.properties.clone_filtered(),
-            events: self.events.clone_filtered(),
-            alarms: self.alarms.clone_filtered(),
-            todos: self.todos.clone_filtered(),
-            journals: self.journals.clone_filtered(),
-            free_busys: self.free_busys.clone_filtered(),
-            timezones: self.timezones.clone_filtered(),
-        };
-        new.generate()
+    fn filter(&self, rules: &FilterRules) -> IcalCalendar {
+        IcalCalendar {
+            properties: self.properties.clone(),
+            events: self.events.clone_filtered(rules),
+            alarms: self.alarms.clone(),
+            todos: self.todos.clone_filtered(rules),
+            journals: self.journals.clone_filtered(rules),
+            free_busys: self.free_busys.clone(),
+            timezones: self.timezones.clone(),
+        }
     }
 }
 
-trait PropFiltered {
-    fn prop_filter(property: &&Property) -> bool {
-        property
-            .value
-            .as_ref()
-            .is_some_and(|s| s.as_str() == "Beregnelighed og logik")
-            && property.name == "SUMMARY"
-    }
-    fn clone_filtered(&self) -> Self;
+/// Components that are kept or dropped as a whole, depending on their properties,
+/// and rewritten property by property.
+pub(super) trait PropFiltered: Clone {
+    fn properties(&self) -> &[Property];
+    fn properties_mut(&mut self) -> &mut Vec<Property>;
+    /// `None` for components that cannot hold VALARMs.
+    fn alarms_mut(&mut self) -> Option<&mut Vec<IcalAlarm>>;
 }
-impl PropFiltered for Vec<Property> {
-    fn clone_filtered(&self) -> Self {
-        self.iter().filter(Self::prop_filter).cloned().collect()
+impl PropFiltered for IcalEvent {
+    fn properties(&self) -> &[Property] {
+        &self.properties
     }
-}
-impl PropFiltered for Vec<IcalEvent> {
-    fn clone_filtered(&self) -> Self {
-        self.iter()
-            .map(|event| IcalEvent {
-                properties: event.properties.clone_filtered(),
-                alarms: event.alarms.clone_filtered(),
-            })
-            .collect()
+    fn properties_mut(&mut self) -> &mut Vec<Property> {
+        &mut self.properties
     }
-}
-impl PropFiltered for Vec<IcalAlarm> {
-    fn clone_filtered(&self) -> Self {
-        self.iter()
-            .map(|alarm| IcalAlarm {
-                properties: alarm.properties.clone_filtered(),
-            })
-            .collect()
+    fn alarms_mut(&mut self) -> Option<&mut Vec<IcalAlarm>> {
+        Some(&mut self.alarms)
     }
 }
-impl PropFiltered for Vec<IcalTodo> {
-    fn clone_filtered(&self) -> Self {
-        self.iter()
-            .map(|todo| IcalTodo {
-                alarms: todo.alarms.clone_filtered(),
-                properties: todo.properties.clone_filtered(),
-            })
-            .collect()
+impl PropFiltered for IcalTodo {
+    fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+    fn properties_mut(&mut self) -> &mut Vec<Property> {
+        &mut self.properties
+    }
+    fn alarms_mut(&mut self) -> Option<&mut Vec<IcalAlarm>> {
+        Some(&mut self.alarms)
     }
 }
-impl PropFiltered for Vec<IcalJournal> {
-    fn clone_filtered(&self) -> Self {
-        self.iter()
-            .map(|journal| IcalJournal {
-                properties: journal.properties.clone_filtered(),
-            })
-            .collect()
+impl PropFiltered for IcalJournal {
+    fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+    fn properties_mut(&mut self) -> &mut Vec<Property> {
+        &mut self.properties
+    }
+    fn alarms_mut(&mut self) -> Option<&mut Vec<IcalAlarm>> {
+        None
     }
 }
-impl PropFiltered for Vec<IcalFreeBusy> {
-    fn clone_filtered(&self) -> Self {
+
+trait CloneFiltered {
+    fn clone_filtered(&self, rules: &FilterRules) -> Self;
+}
+impl<T: PropFiltered> CloneFiltered for Vec<T> {
+    fn clone_filtered(&self, rules: &FilterRules) -> Self {
         self.iter()
-            .map(|free_busy| IcalFreeBusy {
-                properties: free_busy.properties.clone_filtered(),
+            .filter(|component| rules.keeps(component.properties()))
+            .map(|component| {
+                let mut component = component.clone();
+                transforms::apply(&rules.transform, &mut component);
+                component
             })
             .collect()
     }
 }
-impl PropFiltered for Vec<IcalTimeZone> {
-    fn clone_filtered(&self) -> Self {
-        self.iter()
-            .map(|timezone| IcalTimeZone {
-                transitions: timezone
-                    .transitions
-                    .iter()
-                    .map(|transition| IcalTimeZoneTransition {
-                        transition: transition.transition.clone(),
-                        properties: transition.properties.clone_filtered(),
-                    })
-                    .collect(),
-                properties: timezone.properties.clone_filtered(),
-            })
-            .collect()
+
+#[cfg(test)]
+mod tests {
+    use ical::generator::Emitter;
+
+    use super::FilterBuild;
+    use crate::calendar::rules::FilterRules;
+    use crate::fixtures::{self, assert_snapshot, fixture, parse};
+
+    fn snapshot(rules: &str, snapshot: &str) {
+        snapshot_of("sample.ics", rules, snapshot);
+    }
+
+    fn snapshot_of(source: &str, rules: &str, snapshot: &str) {
+        let rules = FilterRules::load(fixtures::path(rules)).unwrap();
+        assert_snapshot(snapshot, &fixture(source).filter(&rules).generate());
+    }
+
+    #[test]
+    fn course_only() {
+        snapshot("course.toml", "course.snap.ics");
+    }
+
+    #[test]
+    fn combined_rules() {
+        snapshot("combined.json", "combined.snap.ics");
+    }
+
+    #[test]
+    fn transformed() {
+        snapshot_of("work.ics", "transforms.toml", "transformed.snap.ics");
+    }
+
+    #[test]
+    fn no_rules_keeps_everything() {
+        let source = fixture("sample.ics");
+        let built = source.filter(&FilterRules::default()).generate();
+        assert_eq!(parse(&built).events.len(), source.events.len());
+        assert_snapshot("all.snap.ics", &built);
+    }
+
+    #[test]
+    fn kept_events_keep_all_properties() {
+        let rules = FilterRules::load(fixtures::path("course.toml")).unwrap();
+        let built = parse(&fixture("sample.ics").filter(&rules).generate());
+        assert!(!built.events.is_empty());
+        for event in built.events {
+            for name in ["DTSTART", "DTEND", "UID", "SUMMARY"] {
+                assert!(event.properties.iter().any(|p| p.name == name));
+            }
+        }
+        assert_eq!(built.timezones.len(), 1);
     }
 }
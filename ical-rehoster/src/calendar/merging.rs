@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use ical::generator::IcalCalendar;
+
+use super::filtering::PropFiltered;
+use super::rules::parse_ical_date;
+use super::value;
+
+/// Combines named calendars into one, in the given order.
+/// The first calendar's own properties are kept, VTIMEZONEs are merged by TZID,
+/// and components sharing a UID (and RECURRENCE-ID) are reduced to the newest one,
+/// staying where that UID was first seen.
+pub(crate) fn merge(sources: &[(&str, &IcalCalendar)], prefix_summary: bool) -> IcalCalendar {
+    let mut merged = IcalCalendar::new();
+    if let Some((_, first)) = sources.first() {
+        merged.properties = first.properties.clone();
+    }
+    for (_, calendar) in sources {
+        for timezone in &calendar.timezones {
+            let tzid = value(&timezone.properties, "TZID");
+            let known = merged
+                .timezones
+                .iter()
+                .any(|known| tzid.is_some() && value(&known.properties, "TZID") == tzid);
+            if !known {
+                merged.timezones.push(timezone.clone());
+            }
+        }
+        merged.alarms.extend(calendar.alarms.iter().cloned());
+        merged
+            .free_busys
+            .extend(calendar.free_busys.iter().cloned());
+    }
+    merged.events = newest(sources, |calendar| &calendar.events, prefix_summary);
+    merged.todos = newest(sources, |calendar| &calendar.todos, prefix_summary);
+    merged.journals = newest(sources, |calendar| &calendar.journals, prefix_summary);
+    merged
+}
+
+fn newest<T: PropFiltered>(
+    sources: &[(&str, &IcalCalendar)],
+    components: impl Fn(&IcalCalendar) -> &Vec<T>,
+    prefix_summary: bool,
+) -> Vec<T> {
+    let mut merged: Vec<T> = vec![];
+    let mut positions: HashMap<(String, Option<String>), usize> = HashMap::new();
+    for (name, calendar) in sources {
+        for component in components(calendar) {
+            let mut component = component.clone();
+            if prefix_summary {
+                prefix(&mut component, name);
+            }
+            let properties = component.properties();
+            let Some(uid) = value(properties, "UID") else {
+                merged.push(component);
+                continue;
+            };
+            let key = (
+                uid.to_string(),
+                value(properties, "RECURRENCE-ID").map(str::to_string),
+            );
+            match positions.get(&key) {
+                Some(&i) if version(&component) > version(&merged[i]) => merged[i] = component,
+                Some(_) => {}
+                None => {
+                    positions.insert(key, merged.len());
+                    merged.push(component);
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// SEQUENCE first, then LAST-MODIFIED, missing values counting as oldest.
+fn version(component: &impl PropFiltered) -> (u32, Option<NaiveDateTime>) {
+    let properties = component.properties();
+    (
+        value(properties, "SEQUENCE")
+            .and_then(|sequence| sequence.trim().parse().ok())
+            .unwrap_or(0),
+        value(properties, "LAST-MODIFIED").and_then(parse_ical_date),
+    )
+}
+
+fn prefix(component: &mut impl PropFiltered, name: &str) {
+    for property in component.properties_mut() {
+        if property.name.eq_ignore_ascii_case("SUMMARY") {
+            if let Some(summary) = &mut property.value {
+                *summary = format!("[{}] {}", name, summary);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ical::generator::{Emitter, IcalCalendar};
+
+    use super::merge;
+    use crate::calendar::value;
+    use crate::fixtures::{assert_snapshot, fixture};
+
+    fn summaries(calendar: &IcalCalendar) -> Vec<&str> {
+        calendar
+            .events
+            .iter()
+            .filter_map(|event| value(&event.properties, "SUMMARY"))
+            .collect()
+    }
+
+    #[test]
+    fn merged_snapshot() {
+        let (sample, work) = (fixture("sample.ics"), fixture("work.ics"));
+        let merged = merge(&[("uni", &sample), ("work", &work)], true).generate();
+        assert_snapshot("merged.snap.ics", &merged);
+    }
+
+    #[test]
+    fn newest_duplicate_wins() {
+        let (sample, work) = (fixture("sample.ics"), fixture("work.ics"));
+        let merged = merge(&[("uni", &sample), ("work", &work)], false);
+        // work.ics moves one lecture (higher SEQUENCE) and repeats another unchanged
+        assert_eq!(merged.events.len(), sample.events.len() + 2);
+        let moved = merged
+            .events
+            .iter()
+            .find(|event| {
+                value(&event.properties, "UID") == Some("2020!121612-27@mytimetable.au.dk")
+            })
+            .unwrap();
+        assert_eq!(
+            value(&moved.properties, "LOCATION"),
+            Some("5510-104 Aud. G1")
+        );
+        // the order of first appearance is kept
+        assert_eq!(merged.events[4].properties, moved.properties);
+        assert_eq!(merged.todos.len(), 2);
+        assert_eq!(merged.journals.len(), 1);
+
+        // LAST-MODIFIED decides between equal sequences, whichever order they come in
+        let reversed = merge(&[("work", &work), ("uni", &sample)], false);
+        let repeated = |calendar: &IcalCalendar| {
+            calendar
+                .events
+                .iter()
+                .find(|event| {
+                    value(&event.properties, "UID") == Some("2020!121601-27@mytimetable.au.dk")
+                })
+                .map(|event| {
+                    value(&event.properties, "LAST-MODIFIED")
+                        .unwrap()
+                        .to_string()
+                })
+        };
+        assert_eq!(repeated(&merged), repeated(&reversed));
+        assert_eq!(repeated(&merged).unwrap(), "20240401T080000Z");
+    }
+
+    #[test]
+    fn timezones_and_prefixes() {
+        let (sample, work) = (fixture("sample.ics"), fixture("work.ics"));
+        let merged = merge(&[("uni", &sample), ("work", &work)], true);
+        let tzids: Vec<_> = merged
+            .timezones
+            .iter()
+            .filter_map(|timezone| value(&timezone.properties, "TZID"))
+            .collect();
+        assert_eq!(tzids, ["Europe/Copenhagen", "Europe/London"]);
+        assert_eq!(merged.properties, sample.properties);
+        let summaries = summaries(&merged);
+        assert_eq!(summaries[0], "[uni] Programmeringssprog");
+        assert!(summaries.contains(&"[work] Standup"));
+        assert!(summaries.contains(&"[work] Beregnelighed og logik"));
+
+        let unprefixed = merge(&[("uni", &sample)], false);
+        assert_eq!(unprefixed.generate(), sample.generate());
+    }
+}
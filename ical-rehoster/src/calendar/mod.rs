@@ -1,16 +1,65 @@
-use ical::generator::IcalCalendar;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::SystemTime;
+
+use chrono::NaiveDateTime;
+use ical::generator::{Emitter, IcalCalendar};
 use ical::property::Property;
 use serde::{Deserialize, Serialize};
 
 mod filtering;
+mod merging;
+mod preview;
+mod rules;
+mod transforms;
 use filtering::FilterBuild;
+pub(crate) use merging::merge;
+pub(crate) use preview::Upcoming;
+pub(crate) use rules::FilterRules;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct ComparableCalendar {
     source: IcalCalendar,
+    kept: IcalCalendar,
     pub(crate) filtered: String,
+    /// Quoted strong validator of `filtered`.
+    pub(crate) etag: String,
+    /// When the served calendar last changed: when it was built, when the cache it was read from
+    /// was written, or kept from the previous calendar while the etag stays the same.
+    pub(crate) modified: SystemTime,
 }
 impl ComparableCalendar {
+    pub(crate) fn new(source: IcalCalendar, rules: &FilterRules) -> Self {
+        let kept = source.filter(rules);
+        let filtered = kept.generate();
+        let mut hasher = DefaultHasher::new();
+        filtered.hash(&mut hasher);
+        Self {
+            etag: format!("\"{:016x}\"", hasher.finish()),
+            modified: SystemTime::now(),
+            filtered,
+            kept,
+            source,
+        }
+    }
+    /// Takes the place of this calendar, keeping `modified` if the served text did not change.
+    pub(crate) fn replace(&mut self, mut new: Self) {
+        if new.etag == self.etag {
+            new.modified = self.modified;
+        }
+        *self = new;
+    }
+    /// Events kept by the rules, out of all events in the source.
+    pub(crate) fn event_counts(&self) -> (usize, usize) {
+        (self.kept.events.len(), self.source.events.len())
+    }
+    /// Kept events starting at or after `now`, soonest first.
+    pub(crate) fn upcoming(&self, now: NaiveDateTime) -> Vec<Upcoming> {
+        preview::upcoming(&self.kept.events, now)
+    }
+    /// The unfiltered calendar, as written to the cache.
+    pub(crate) fn generate_source(&self) -> String {
+        self.source.generate()
+    }
     fn invalidation_relevant_properties(&self) -> impl Iterator<Item = &Property> {
         // this seems to do what I want
         self.flattened_properties().filter(|&property| {
@@ -58,11 +107,11 @@ impl PartialEq for ComparableCalendar {
             .eq(other.invalidation_relevant_properties())
     }
 }
-impl From<IcalCalendar> for ComparableCalendar {
-    fn from(source: IcalCalendar) -> Self {
-        Self {
-            filtered: source.build(),
-            source,
-        }
-    }
+
+/// The value of the first property called `name`.
+fn value<'a>(properties: &'a [Property], name: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .find(|property| property.name.eq_ignore_ascii_case(name))
+        .and_then(|property| property.value.as_deref())
 }
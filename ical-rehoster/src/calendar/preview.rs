@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use ical::generator::IcalEvent;
+
+use super::rules::parse_ical_date;
+use super::value;
+
+/// An event as listed in the dashboard.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Upcoming {
+    /// As written, ignoring any TZID.
+    pub(crate) start: NaiveDateTime,
+    pub(crate) summary: String,
+    pub(crate) location: Option<String>,
+    /// Repeats by RRULE, so `start` may be long past.
+    pub(crate) recurring: bool,
+}
+
+pub(super) fn upcoming(events: &[IcalEvent], now: NaiveDateTime) -> Vec<Upcoming> {
+    let mut upcoming: Vec<_> = events
+        .iter()
+        .filter_map(|event| {
+            let properties = &event.properties;
+            let start = value(properties, "DTSTART").and_then(parse_ical_date)?;
+            let recurring = value(properties, "RRULE").is_some();
+            if start < now && !recurring {
+                return None;
+            }
+            Some(Upcoming {
+                start,
+                summary: value(properties, "SUMMARY").unwrap_or_default().to_string(),
+                location: value(properties, "LOCATION")
+                    .filter(|location| !location.is_empty())
+                    .map(str::to_string),
+                recurring,
+            })
+        })
+        .collect();
+    upcoming.sort_by_key(|event| event.start);
+    upcoming
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::upcoming;
+    use crate::fixtures::fixture;
+
+    #[test]
+    fn upcoming_events() {
+        let calendar = fixture("work.ics");
+        let now = NaiveDate::from_ymd_opt(2024, 2, 6)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let upcoming = upcoming(&calendar.events, now);
+        let summaries: Vec<_> = upcoming
+            .iter()
+            .map(|event| (event.summary.as_str(), event.recurring))
+            .collect();
+        // the standup repeats, the moved override of one day and the Databaser lecture are past
+        assert_eq!(
+            summaries,
+            [("Standup", true), ("Beregnelighed og logik", false)]
+        );
+        assert_eq!(upcoming[1].location.as_deref(), Some("5510-104 Aud. G1"));
+    }
+}
@@ -0,0 +1,329 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use ical::property::Property;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::transforms::Transform;
+
+/// Decides which VEVENT, VTODO and VJOURNAL components are kept, and how they are rewritten.
+/// A component is kept if it matches `include` (when set) and does not match `exclude`,
+/// then every `transform` is applied to it in turn.
+/// Everything else in the calendar is passed through untouched.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FilterRules {
+    #[serde(default)]
+    pub(crate) include: Option<Rule>,
+    #[serde(default)]
+    pub(crate) exclude: Option<Rule>,
+    #[serde(default)]
+    pub(crate) transform: Vec<Transform>,
+}
+impl FilterRules {
+    /// Reads rules from a `.json` file, or from TOML for any other extension.
+    pub(crate) fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Ok(match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&text)?,
+            _ => toml::from_str(&text)?,
+        })
+    }
+
+    pub(crate) fn keeps(&self, properties: &[Property]) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|rule| rule.matches(properties))
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|rule| rule.matches(properties))
+    }
+}
+
+/// A predicate over the properties of a single component.
+/// Property matchers hold if any property with that name matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Rule {
+    All {
+        rules: Vec<Rule>,
+    },
+    Any {
+        rules: Vec<Rule>,
+    },
+    Not {
+        rule: Box<Rule>,
+    },
+    Equals {
+        property: String,
+        value: String,
+    },
+    Contains {
+        property: String,
+        value: String,
+        #[serde(default)]
+        ignore_case: bool,
+    },
+    Regex {
+        property: String,
+        pattern: Pattern,
+    },
+    /// Dates are compared as written, ignoring any TZID. `after` is inclusive, `before` is not.
+    DateRange {
+        #[serde(default = "Rule::default_date_property")]
+        property: String,
+        #[serde(default)]
+        after: Option<DateBound>,
+        #[serde(default)]
+        before: Option<DateBound>,
+    },
+}
+impl Rule {
+    fn default_date_property() -> String {
+        "DTSTART".to_string()
+    }
+
+    pub(crate) fn matches(&self, properties: &[Property]) -> bool {
+        let values = |name: &str| {
+            properties
+                .iter()
+                .filter(move |property| property.name.eq_ignore_ascii_case(name))
+                .filter_map(|property| property.value.as_deref())
+                .collect::<Vec<_>>()
+        };
+        match self {
+            Rule::All { rules } => rules.iter().all(|rule| rule.matches(properties)),
+            Rule::Any { rules } => rules.iter().any(|rule| rule.matches(properties)),
+            Rule::Not { rule } => !rule.matches(properties),
+            Rule::Equals { property, value } => values(property).contains(&value.as_str()),
+            Rule::Contains {
+                property,
+                value,
+                ignore_case,
+            } => values(property).iter().any(|found| match ignore_case {
+                true => found.to_lowercase().contains(&value.to_lowercase()),
+                false => found.contains(value.as_str()),
+            }),
+            Rule::Regex { property, pattern } => values(property)
+                .iter()
+                .any(|found| pattern.0.is_match(found)),
+            Rule::DateRange {
+                property,
+                after,
+                before,
+            } => values(property)
+                .iter()
+                .filter_map(|found| parse_ical_date(found))
+                .any(|date| {
+                    after.as_ref().is_none_or(|after| date >= after.0)
+                        && before.as_ref().is_none_or(|before| date < before.0)
+                }),
+        }
+    }
+}
+
+/// Reads `DATE` (`20240129`) and `DATE-TIME` (`20240129T100000`, optionally UTC) values.
+pub(crate) fn parse_ical_date(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// A regex that is compiled when the rules are loaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct Pattern(pub(super) Regex);
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value).map(Pattern)
+    }
+}
+impl From<Pattern> for String {
+    fn from(value: Pattern) -> Self {
+        value.0.as_str().to_string()
+    }
+}
+
+/// `2024-02-01` or `2024-02-01T12:00:00` in rule files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct DateBound(NaiveDateTime);
+#[derive(Debug)]
+pub(crate) struct DateBoundError(String);
+impl Display for DateBoundError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` is not a date (YYYY-MM-DD) or date-time (YYYY-MM-DDTHH:MM:SS)",
+            self.0
+        )
+    }
+}
+impl TryFrom<String> for DateBound {
+    type Error = DateBoundError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S")
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .map(DateBound)
+            .ok_or(DateBoundError(value))
+    }
+}
+impl From<DateBound> for String {
+    fn from(value: DateBound) -> Self {
+        value.0.format("%Y-%m-%dT%H:%M:%S").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ical::property::Property;
+
+    use super::{FilterRules, Rule};
+
+    fn properties(pairs: &[(&str, &str)]) -> Vec<Property> {
+        pairs
+            .iter()
+            .map(|(name, value)| Property {
+                name: name.to_string(),
+                params: None,
+                value: Some(value.to_string()),
+            })
+            .collect()
+    }
+
+    fn rule(toml: &str) -> Rule {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn property_matchers() {
+        let event = properties(&[
+            ("SUMMARY", "Beregnelighed og logik"),
+            ("LOCATION", "5335-016 Peter Bøgh Aud."),
+            ("DTSTART", "20240129T100000"),
+        ]);
+        let cases = [
+            (
+                r#"type = "equals"
+                property = "SUMMARY"
+                value = "Beregnelighed og logik""#,
+                true,
+            ),
+            (
+                r#"type = "equals"
+                property = "SUMMARY"
+                value = "Beregnelighed""#,
+                false,
+            ),
+            (
+                r#"type = "contains"
+                property = "summary"
+                value = "LOGIK"
+                ignore_case = true"#,
+                true,
+            ),
+            (
+                r#"type = "contains"
+                property = "SUMMARY"
+                value = "LOGIK""#,
+                false,
+            ),
+            (
+                r#"type = "regex"
+                property = "LOCATION"
+                pattern = "^5335-\\d+""#,
+                true,
+            ),
+            (
+                r#"type = "date_range"
+                after = "2024-01-29"
+                before = "2024-01-30""#,
+                true,
+            ),
+            (
+                r#"type = "date_range"
+                after = "2024-01-29T10:00:01""#,
+                false,
+            ),
+            (
+                r#"type = "date_range"
+                property = "DTEND"
+                after = "2000-01-01""#,
+                false,
+            ),
+        ];
+        for (toml, expected) in cases {
+            assert_eq!(rule(toml).matches(&event), expected, "{}", toml);
+        }
+    }
+
+    #[test]
+    fn combinators() {
+        let event = properties(&[("SUMMARY", "Logik"), ("CATEGORIES", "Lecture")]);
+        let any = rule(
+            r#"type = "any"
+               rules = [
+                   { type = "equals", property = "SUMMARY", value = "Other" },
+                   { type = "equals", property = "CATEGORIES", value = "Lecture" },
+               ]"#,
+        );
+        let all = rule(
+            r#"type = "all"
+               rules = [
+                   { type = "equals", property = "SUMMARY", value = "Other" },
+                   { type = "equals", property = "CATEGORIES", value = "Lecture" },
+               ]"#,
+        );
+        assert!(any.matches(&event));
+        assert!(!all.matches(&event));
+        assert!(Rule::Not {
+            rule: Box::new(all)
+        }
+        .matches(&event));
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let rules: FilterRules = serde_json::from_str(
+            r#"{
+                "include": { "type": "contains", "property": "SUMMARY", "value": "logik" },
+                "exclude": { "type": "equals", "property": "STATUS", "value": "CANCELLED" }
+            }"#,
+        )
+        .unwrap();
+        assert!(rules.keeps(&properties(&[("SUMMARY", "logik")])));
+        assert!(!rules.keeps(&properties(&[
+            ("SUMMARY", "logik"),
+            ("STATUS", "CANCELLED")
+        ])));
+        assert!(!rules.keeps(&properties(&[("SUMMARY", "other")])));
+        assert!(FilterRules::default().keeps(&[]));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert!(
+            toml::from_str::<Rule>("type = \"regex\"\nproperty = \"A\"\npattern = \"(\"").is_err()
+        );
+        assert!(toml::from_str::<Rule>("type = \"date_range\"\nafter = \"monday\"").is_err());
+        assert!(toml::from_str::<Rule>("type = \"equals\"\nproperty = \"A\"").is_err());
+        assert!(toml::from_str::<FilterRules>("includ = {}").is_err());
+    }
+}
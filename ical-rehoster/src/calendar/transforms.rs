@@ -0,0 +1,347 @@
+use chrono::{Duration, NaiveDateTime};
+use ical::parser::ical::component::IcalAlarm;
+use ical::property::Property;
+use serde::{Deserialize, Serialize};
+
+use super::filtering::PropFiltered;
+use super::rules::{parse_ical_date, Pattern, Rule};
+use super::value;
+
+/// Properties that place a component in its recurring series, never rewritten or stripped.
+const SERIES_PROPERTIES: [&str; 6] = [
+    "UID",
+    "DTSTART",
+    "RRULE",
+    "RDATE",
+    "EXDATE",
+    "RECURRENCE-ID",
+];
+
+/// A rewrite of kept components, applied in the order they are listed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Transform {
+    /// Only components matching this rule are rewritten.
+    #[serde(default)]
+    pub(crate) when: Option<Rule>,
+    #[serde(flatten)]
+    pub(crate) action: Action,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Action {
+    /// Replaces every match of `pattern`, `$1` and `${name}` refer to its groups.
+    Replace {
+        property: String,
+        pattern: Pattern,
+        replacement: String,
+    },
+    /// Adds a display VALARM, unless one with the same trigger exists.
+    /// It is relative to DTSTART, so every occurrence of a recurring event gets it.
+    Alarm {
+        minutes_before: u32,
+        /// Defaults to the SUMMARY.
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Strip {
+        properties: Vec<String>,
+    },
+    /// Moves DTSTART, DTEND and DUE, all-day dates by whole days.
+    /// Events in a recurring series are left alone, as their overrides and exceptions would no
+    /// longer line up.
+    Shift {
+        minutes: i64,
+    },
+}
+
+pub(super) fn apply(transforms: &[Transform], component: &mut impl PropFiltered) {
+    for transform in transforms {
+        if transform
+            .when
+            .as_ref()
+            .is_none_or(|rule| rule.matches(component.properties()))
+        {
+            transform.action.apply(component);
+        }
+    }
+}
+
+impl Action {
+    fn apply(&self, component: &mut impl PropFiltered) {
+        match self {
+            Action::Replace {
+                property,
+                pattern,
+                replacement,
+            } => {
+                for found in named(component.properties_mut(), property) {
+                    if let Some(value) = &mut found.value {
+                        *value = pattern.0.replace_all(value, replacement).into_owned();
+                    }
+                }
+            }
+            Action::Alarm {
+                minutes_before,
+                description,
+            } => {
+                if value(component.properties(), "DTSTART").is_none() {
+                    return;
+                }
+                let trigger = match minutes_before {
+                    0 => "PT0M".to_string(),
+                    minutes => format!("-PT{}M", minutes),
+                };
+                let description = description
+                    .as_deref()
+                    .or(value(component.properties(), "SUMMARY"))
+                    .unwrap_or("Reminder")
+                    .to_string();
+                let Some(alarms) = component.alarms_mut() else {
+                    return;
+                };
+                let exists = alarms
+                    .iter()
+                    .any(|alarm| value(&alarm.properties, "TRIGGER") == Some(trigger.as_str()));
+                if !exists {
+                    let mut alarm = IcalAlarm::new();
+                    alarm.properties = vec![
+                        property("ACTION", "DISPLAY"),
+                        property("TRIGGER", &trigger),
+                        property("DESCRIPTION", &description),
+                    ];
+                    alarms.push(alarm);
+                }
+            }
+            Action::Strip { properties } => component.properties_mut().retain(|property| {
+                is_series(property) || !properties.iter().any(|name| is(property, name))
+            }),
+            Action::Shift { minutes } => {
+                let properties = component.properties();
+                if value(properties, "RRULE").is_some()
+                    || value(properties, "RDATE").is_some()
+                    || value(properties, "RECURRENCE-ID").is_some()
+                {
+                    return;
+                }
+                for property in component.properties_mut() {
+                    if !["DTSTART", "DTEND", "DUE"]
+                        .iter()
+                        .any(|name| is(property, name))
+                    {
+                        continue;
+                    }
+                    if let Some(value) = &mut property.value {
+                        if let Some(shifted) = shift(value, *minutes) {
+                            *value = shifted;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is(property: &Property, name: &str) -> bool {
+    property.name.eq_ignore_ascii_case(name)
+}
+
+fn is_series(property: &Property) -> bool {
+    SERIES_PROPERTIES.iter().any(|name| is(property, name))
+}
+
+/// The properties called `name` that may be rewritten.
+fn named<'a>(
+    properties: &'a mut [Property],
+    name: &'a str,
+) -> impl Iterator<Item = &'a mut Property> {
+    properties
+        .iter_mut()
+        .filter(move |property| is(property, name) && !is_series(property))
+}
+
+fn property(name: &str, value: &str) -> Property {
+    Property {
+        name: name.to_string(),
+        params: None,
+        value: Some(value.to_string()),
+    }
+}
+
+/// Writes the moved date back the way it was written, keeping a trailing `Z`.
+fn shift(value: &str, minutes: i64) -> Option<String> {
+    let date: NaiveDateTime = parse_ical_date(value)?;
+    if !value.contains('T') {
+        let date = date + Duration::days(minutes / (24 * 60));
+        return Some(date.format("%Y%m%d").to_string());
+    }
+    let date = date + Duration::minutes(minutes);
+    let utc = if value.ends_with('Z') { "Z" } else { "" };
+    Some(format!("{}{}", date.format("%Y%m%dT%H%M%S"), utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use ical::generator::IcalEvent;
+    use ical::property::Property;
+
+    use super::{apply, shift, Transform};
+    use crate::calendar::{value, FilterRules};
+
+    fn event(pairs: &[(&str, &str)]) -> IcalEvent {
+        let mut event = IcalEvent::new();
+        event.properties = pairs
+            .iter()
+            .map(|(name, value)| Property {
+                name: name.to_string(),
+                params: None,
+                value: Some(value.to_string()),
+            })
+            .collect();
+        event
+    }
+
+    fn transforms(toml: &str) -> Vec<Transform> {
+        toml::from_str::<FilterRules>(toml).unwrap().transform
+    }
+
+    #[test]
+    fn replace_and_strip() {
+        let mut lecture = event(&[
+            ("SUMMARY", "Beregnelighed og logik"),
+            ("LOCATION", "5335-016 Peter Bøgh Aud."),
+            ("DESCRIPTION", "Hold: F-BELO-F24"),
+            ("ATTENDEE", "mailto:someone@example.com"),
+            ("RRULE", "FREQ=WEEKLY"),
+        ]);
+        apply(
+            &transforms(
+                r#"
+                [[transform]]
+                type = "replace"
+                property = "LOCATION"
+                pattern = '^(\d{4})-(\d{3}) (.*)$'
+                replacement = "$3 (building $1, room $2)"
+
+                [[transform]]
+                type = "replace"
+                property = "summary"
+                pattern = "^Beregnelighed og logik$"
+                replacement = "BeLo"
+
+                [[transform]]
+                type = "replace"
+                property = "RRULE"
+                pattern = "WEEKLY"
+                replacement = "DAILY"
+
+                [[transform]]
+                type = "strip"
+                properties = ["DESCRIPTION", "attendee", "RRULE"]
+                "#,
+            ),
+            &mut lecture,
+        );
+        let names: Vec<_> = lecture.properties.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["SUMMARY", "LOCATION", "RRULE"]);
+        assert_eq!(value(&lecture.properties, "SUMMARY"), Some("BeLo"));
+        assert_eq!(
+            value(&lecture.properties, "LOCATION"),
+            Some("Peter Bøgh Aud. (building 5335, room 016)")
+        );
+        assert_eq!(value(&lecture.properties, "RRULE"), Some("FREQ=WEEKLY"));
+    }
+
+    #[test]
+    fn alarms() {
+        let alarm = transforms(
+            r#"
+            [[transform]]
+            type = "alarm"
+            minutes_before = 15
+
+            [[transform]]
+            when = { type = "equals", property = "SUMMARY", value = "Exam" }
+            type = "alarm"
+            minutes_before = 1440
+            description = "Exam tomorrow"
+            "#,
+        );
+        let mut standup = event(&[
+            ("DTSTART", "20240129T090000"),
+            ("RRULE", "FREQ=DAILY"),
+            ("SUMMARY", "Standup"),
+        ]);
+        apply(&alarm, &mut standup);
+        apply(&alarm, &mut standup);
+        assert_eq!(standup.alarms.len(), 1);
+        let properties = &standup.alarms[0].properties;
+        assert_eq!(value(properties, "ACTION"), Some("DISPLAY"));
+        assert_eq!(value(properties, "TRIGGER"), Some("-PT15M"));
+        assert_eq!(value(properties, "DESCRIPTION"), Some("Standup"));
+
+        let mut exam = event(&[("DTSTART", "20240612"), ("SUMMARY", "Exam")]);
+        apply(&alarm, &mut exam);
+        let descriptions: Vec<_> = exam
+            .alarms
+            .iter()
+            .map(|alarm| value(&alarm.properties, "DESCRIPTION").unwrap())
+            .collect();
+        assert_eq!(descriptions, ["Exam", "Exam tomorrow"]);
+
+        let mut undated = event(&[("SUMMARY", "Someday")]);
+        apply(&alarm, &mut undated);
+        assert!(undated.alarms.is_empty());
+    }
+
+    #[test]
+    fn shifts_single_events_only() {
+        let later = transforms("[[transform]]\ntype = \"shift\"\nminutes = 90");
+        let mut single = event(&[
+            ("DTSTART", "20240131T233000Z"),
+            ("DTEND", "20240201T001500Z"),
+            ("DTSTAMP", "20240101T000000Z"),
+        ]);
+        apply(&later, &mut single);
+        assert_eq!(
+            value(&single.properties, "DTSTART"),
+            Some("20240201T010000Z")
+        );
+        assert_eq!(value(&single.properties, "DTEND"), Some("20240201T014500Z"));
+        assert_eq!(
+            value(&single.properties, "DTSTAMP"),
+            Some("20240101T000000Z")
+        );
+
+        for series in [
+            ("RRULE", "FREQ=WEEKLY"),
+            ("RECURRENCE-ID", "20240131T090000"),
+        ] {
+            let mut event = event(&[("DTSTART", "20240131T100000"), series]);
+            apply(&later, &mut event);
+            assert_eq!(value(&event.properties, "DTSTART"), Some("20240131T100000"));
+        }
+
+        assert_eq!(
+            shift("20240131", 2 * 24 * 60 + 30).as_deref(),
+            Some("20240202")
+        );
+        assert_eq!(
+            shift("20240131T100000", -60).as_deref(),
+            Some("20240131T090000")
+        );
+        assert_eq!(shift("tomorrow", 60), None);
+    }
+
+    #[test]
+    fn invalid_transforms() {
+        for invalid in [
+            "[[transform]]\ntype = \"strip\"\nproperty = \"DESCRIPTION\"",
+            "[[transform]]\ntype = \"alarm\"\nminutes_before = -5",
+            "[[transform]]\ntype = \"replace\"\nproperty = \"A\"\npattern = \"(\"\nreplacement = \"\"",
+            "[[transform]]\ntype = \"rename\"",
+        ] {
+            assert!(toml::from_str::<FilterRules>(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+
+/// Fetches iCalendar feeds, filters them and serves the result over HTTP.
+///
+/// Feeds are read from `feeds.toml` (or `$CALENDAR_FEEDS_PATH`). Without that file a single
+/// feed is served at `/`, filtered by `rules.toml` (or `$CALENDAR_RULES_PATH`).
+#[derive(Debug, Parser)]
+#[command(version)]
+pub(crate) struct Cli {
+    /// Upstream URL of the single feed, when there is no feeds file.
+    /// Defaults to `$CALENDAR_ICAL_URL`, otherwise it is asked for.
+    pub(crate) url: Option<String>,
+    /// Address to serve the feeds on.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub(crate) bind: IpAddr,
+    #[arg(short, long, default_value_t = 1234)]
+    pub(crate) port: u16,
+    /// Where fetched calendars are cached between runs.
+    /// Defaults to `cache_dir` of the feeds file, or the working directory for a single feed.
+    #[arg(long)]
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// Seconds between polls of an upstream that answered.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub(crate) poll_interval: u64,
+    /// Seconds before retrying an upstream that failed, doubled for every failure in a row.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub(crate) error_delay: u64,
+    /// Show a dashboard of the feeds instead of printing log lines.
+    #[arg(long)]
+    pub(crate) tui: bool,
+}
+impl Cli {
+    pub(crate) fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+    pub(crate) fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval)
+    }
+    pub(crate) fn error_delay(&self) -> Duration {
+        Duration::from_secs(self.error_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use clap::{CommandFactory, Parser};
+
+    use super::Cli;
+
+    #[test]
+    fn defaults_match_the_old_constants() {
+        let cli = Cli::try_parse_from(["ical-rehoster"]).unwrap();
+        assert_eq!(cli.address().to_string(), "127.0.0.1:1234");
+        assert_eq!(cli.poll_interval(), Duration::from_secs(10));
+        assert_eq!(cli.error_delay(), Duration::from_secs(5));
+        assert_eq!(cli.cache_dir, None);
+        assert_eq!(cli.url, None);
+        assert!(!cli.tui);
+    }
+
+    #[test]
+    fn flags() {
+        let cli = Cli::try_parse_from([
+            "ical-rehoster",
+            "--bind=::",
+            "-p",
+            "8080",
+            "--cache-dir",
+            "/var/cache/ical",
+            "--poll-interval=60",
+            "--error-delay=1",
+            "--tui",
+            "https://example.com/personal.ical",
+        ])
+        .unwrap();
+        assert_eq!(cli.address().to_string(), "[::]:8080");
+        assert_eq!(cli.cache_dir, Some(PathBuf::from("/var/cache/ical")));
+        assert_eq!(cli.poll_interval(), Duration::from_secs(60));
+        assert_eq!(cli.error_delay(), Duration::from_secs(1));
+        assert!(cli.tui);
+        assert_eq!(
+            cli.url.as_deref(),
+            Some("https://example.com/personal.ical")
+        );
+
+        for invalid in [
+            ["ical-rehoster", "--poll-interval=0"],
+            ["ical-rehoster", "--port=65536"],
+            ["ical-rehoster", "--bind=localhost"],
+        ] {
+            assert!(Cli::try_parse_from(invalid).is_err(), "{:?}", invalid);
+        }
+        Cli::command().debug_assert();
+    }
+}
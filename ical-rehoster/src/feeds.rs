@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Local};
+use ical::generator::IcalCalendar;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::calendar::{merge, ComparableCalendar, FilterRules};
+use crate::logging::log;
+
+/// The feeds file, mapping routes to the upstream calendars served there:
+///
+/// ```toml
+/// cache_dir = "cache"
+///
+/// [feeds."courses/logic.ics"]
+/// url = "https://example.com/personal.ical"
+/// rules = "rules/logic.toml"
+///
+/// [feeds."work.ics"]
+/// url = "https://example.com/work.ical"
+/// rules.exclude = { type = "equals", property = "STATUS", value = "CANCELLED" }
+/// rules.transform = [
+///     { type = "strip", properties = ["DESCRIPTION", "ATTENDEE"] },
+///     { type = "alarm", minutes_before = 10 },
+/// ]
+///
+/// [feeds."everything.ics"]
+/// prefix_summary = true
+/// sources = [
+///     { name = "uni", url = "https://example.com/personal.ical" },
+///     { name = "work", url = "https://example.com/work.ical" },
+/// ]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeedsConfig {
+    #[serde(default = "FeedsConfig::default_cache_dir")]
+    cache_dir: PathBuf,
+    feeds: BTreeMap<String, FeedConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FeedConfig {
+    /// Either a single `url`, or several named `sources` merged into one calendar.
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    sources: Vec<Source>,
+    /// Puts `[<source name>] ` in front of every SUMMARY.
+    #[serde(default)]
+    prefix_summary: bool,
+    #[serde(default)]
+    rules: Option<RulesConfig>,
+}
+
+/// Rules written inline, or the path of a rules file relative to the feeds file.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RulesConfig {
+    Path(PathBuf),
+    Inline(FilterRules),
+}
+
+impl FeedsConfig {
+    fn default_cache_dir() -> PathBuf {
+        PathBuf::from("cache")
+    }
+
+    /// `cache_dir` replaces the one in the file. Paths in the file are relative to the file.
+    pub(crate) fn load(
+        path: impl AsRef<Path>,
+        cache_dir: Option<PathBuf>,
+    ) -> anyhow::Result<Vec<Feed>> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut config: Self = toml::from_str(&std::fs::read_to_string(path)?)?;
+        config.cache_dir = cache_dir.unwrap_or_else(|| dir.join(&config.cache_dir));
+        config.into_feeds(dir)
+    }
+
+    fn into_feeds(self, rules_dir: &Path) -> anyhow::Result<Vec<Feed>> {
+        if self.feeds.is_empty() {
+            anyhow::bail!("No feeds configured.");
+        }
+        let mut feeds: Vec<Feed> = Vec::with_capacity(self.feeds.len());
+        for (name, feed) in self.feeds {
+            let route = normalize_route(&name)?;
+            if feeds.iter().any(|other| other.route == route) {
+                anyhow::bail!("Feed route `/{}` is configured more than once.", route);
+            }
+            let rules = match feed.rules {
+                None => FilterRules::default(),
+                Some(RulesConfig::Inline(rules)) => rules,
+                Some(RulesConfig::Path(path)) => {
+                    let path = rules_dir.join(path);
+                    FilterRules::load(&path).map_err(|e| {
+                        e.context(format!("Invalid filter rules in `{}`", path.display()))
+                    })?
+                }
+            };
+            let sources = match (feed.url, feed.sources) {
+                (Some(url), sources) if sources.is_empty() => vec![Source {
+                    name: route.clone(),
+                    url,
+                }],
+                (None, sources) if !sources.is_empty() => sources,
+                _ => anyhow::bail!(
+                    "Feed `/{}` needs either a `url` or a list of `sources`.",
+                    route
+                ),
+            };
+            for (i, source) in sources.iter().enumerate() {
+                if sources[..i].iter().any(|other| other.name == source.name) {
+                    anyhow::bail!(
+                        "Feed `/{}` has more than one source named `{}`.",
+                        route,
+                        source.name
+                    );
+                }
+            }
+            let cache_path = self.cache_dir.join(cache_file_name(&route));
+            let mut new = Feed::new(route.clone(), sources, rules, cache_path);
+            new.prefix_summary = feed.prefix_summary;
+            feeds.push(new);
+        }
+        Ok(feeds)
+    }
+}
+
+/// Trims surrounding slashes and rejects anything that isn't a plain path.
+fn normalize_route(name: &str) -> anyhow::Result<String> {
+    let route = name.trim_matches('/');
+    if route.is_empty() {
+        anyhow::bail!("Feed route `{}` is empty, `/` lists the feeds.", name);
+    }
+    let valid_segment = |segment: &str| {
+        !segment.is_empty()
+            && segment != "."
+            && segment != ".."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    };
+    if !route.split('/').all(valid_segment) {
+        anyhow::bail!(
+            "Feed route `{}` may only contain ASCII letters, digits, `.`, `_`, `-` and `/`.",
+            name
+        );
+    }
+    Ok(route.to_string())
+}
+
+/// Keeps every cache file directly in the cache directory, so that routes like `a` and `a/b` don't
+/// need a file and a directory at the same path. Routes can't contain `%`, so none share a name.
+fn cache_file_name(route: &str) -> String {
+    route.replace('/', "%2F")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Source {
+    pub(crate) name: String,
+    pub(crate) url: String,
+}
+
+/// One or more upstream calendars, merged and served filtered at their own route.
+pub(crate) struct Feed {
+    /// Without the leading slash, empty for `/`.
+    pub(crate) route: String,
+    pub(crate) sources: Vec<Source>,
+    pub(crate) prefix_summary: bool,
+    pub(crate) rules: FilterRules,
+    pub(crate) cache_path: PathBuf,
+    pub(crate) calendar: Arc<Mutex<Option<ComparableCalendar>>>,
+    /// What was last fetched from each source, in the order of `sources`.
+    fetched: Mutex<Vec<Fetched>>,
+}
+
+#[derive(Default)]
+struct Fetched {
+    calendar: Option<IcalCalendar>,
+    status: SourceStatus,
+}
+
+/// How the last poll of a source went, for the dashboard.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceStatus {
+    pub(crate) last_fetch: Option<DateTime<Local>>,
+    /// The response status, or why there was none.
+    pub(crate) status: Option<Result<StatusCode, String>>,
+}
+impl Feed {
+    pub(crate) fn new(
+        route: String,
+        sources: Vec<Source>,
+        rules: FilterRules,
+        cache_path: PathBuf,
+    ) -> Self {
+        Self {
+            route,
+            fetched: Mutex::new(sources.iter().map(|_| Fetched::default()).collect()),
+            sources,
+            prefix_summary: false,
+            rules,
+            cache_path,
+            calendar: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub(crate) fn path(&self) -> String {
+        format!("/{}", self.route)
+    }
+
+    /// Names the source too when there is more than one, for log messages.
+    pub(crate) fn label(&self, source: usize) -> String {
+        match self.sources.len() {
+            1 => self.path(),
+            _ => format!("{} ({})", self.path(), self.sources[source].name),
+        }
+    }
+
+    pub(crate) fn record_poll(&self, source: usize, status: Result<StatusCode, String>) {
+        self.fetched.lock().unwrap()[source].status = SourceStatus {
+            last_fetch: Some(Local::now()),
+            status: Some(status),
+        };
+    }
+
+    /// In the order of `sources`.
+    pub(crate) fn statuses(&self) -> Vec<SourceStatus> {
+        let fetched = self.fetched.lock().unwrap();
+        fetched
+            .iter()
+            .map(|fetched| fetched.status.clone())
+            .collect()
+    }
+
+    /// Stores a freshly fetched calendar, returning the merge of all sources
+    /// once every one of them has been fetched.
+    pub(crate) fn merge_fetched(
+        &self,
+        source: usize,
+        calendar: IcalCalendar,
+    ) -> Option<IcalCalendar> {
+        let mut fetched = self.fetched.lock().unwrap();
+        fetched[source].calendar = Some(calendar);
+        let named = self
+            .sources
+            .iter()
+            .zip(fetched.iter())
+            .map(|(source, fetched)| Some((source.name.as_str(), fetched.calendar.as_ref()?)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(match named.as_slice() {
+            [(_, only)] if !self.prefix_summary => (*only).clone(),
+            _ => merge(&named, self.prefix_summary),
+        })
+    }
+
+    /// Uses pre-lived calendar data if there is a cache file.
+    pub(crate) fn load_cached(&self) -> anyhow::Result<()> {
+        let mut s = String::new();
+        let Ok(mut file) = File::open(&self.cache_path) else {
+            return Ok(()); // no previous file
+        };
+        let byte_count = file.read_to_string(&mut s)?;
+        log!(
+            "[{}] Found previous data, read {} bytes from `{}`.",
+            self.path(),
+            byte_count,
+            self.cache_path.display()
+        );
+        let mut calendar = ComparableCalendar::new(
+            ical::IcalParser::new(s.as_bytes())
+                .next()
+                .ok_or_else(|| anyhow::Error::msg("No (first) IcalCalendar in file?"))??,
+            &self.rules,
+        );
+        // the cache is written when the calendar changes, so a restart keeps serving the same
+        // Last-Modified
+        calendar.modified = file.metadata()?.modified()?;
+        *self.calendar.lock().unwrap() = Some(calendar);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{cache_file_name, normalize_route, Feed, FeedsConfig};
+    use crate::calendar::{ComparableCalendar, FilterRules};
+    use crate::fixtures::{self, fixture};
+
+    fn feeds(toml: &str) -> anyhow::Result<Vec<super::Feed>> {
+        toml::from_str::<FeedsConfig>(toml)?.into_feeds(&fixtures::path(""))
+    }
+
+    #[test]
+    fn routes() {
+        assert_eq!(
+            normalize_route("/courses/logic.ics/").unwrap(),
+            "courses/logic.ics"
+        );
+        assert_eq!(normalize_route("work_2-b.ics").unwrap(), "work_2-b.ics");
+        for invalid in [
+            "",
+            "/",
+            "a//b",
+            "../secret",
+            "a/./b",
+            ":param",
+            "*rest",
+            "a b",
+        ] {
+            assert!(normalize_route(invalid).is_err(), "{}", invalid);
+        }
+        // nested routes are cached side by side
+        assert_eq!(cache_file_name("a"), "a");
+        assert_eq!(cache_file_name("a/b"), "a%2Fb");
+    }
+
+    #[test]
+    fn loads_feeds() {
+        let feeds = feeds(
+            r#"
+            [feeds."/courses/logic.ics"]
+            url = "https://example.com/personal.ical"
+            rules = "course.toml"
+
+            [feeds."work.ics"]
+            url = "https://example.com/work.ical"
+            rules.exclude = { type = "equals", property = "STATUS", value = "CANCELLED" }
+            rules.transform = [{ type = "alarm", minutes_before = 10 }]
+
+            [feeds.everything]
+            prefix_summary = true
+            sources = [
+                { name = "uni", url = "https://example.com/personal.ical" },
+                { name = "work", url = "https://example.com/work.ical" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let routes: Vec<_> = feeds.iter().map(|feed| feed.path()).collect();
+        assert_eq!(routes, ["/courses/logic.ics", "/everything", "/work.ics"]);
+        assert!(feeds[0].rules.include.is_some());
+        assert!(feeds[1].rules.include.is_none() && feeds[1].rules.exclude.is_none());
+        assert!(feeds[2].rules.exclude.is_some());
+        assert_eq!(feeds[2].rules.transform.len(), 1);
+        assert_eq!(feeds[0].sources[0].name, "courses/logic.ics");
+        assert_eq!(feeds[0].label(0), "/courses/logic.ics");
+        assert!(!feeds[0].prefix_summary);
+        assert_eq!(feeds[1].sources.len(), 2);
+        assert_eq!(feeds[1].label(1), "/everything (work)");
+        assert!(feeds[1].prefix_summary);
+        assert_eq!(
+            feeds[0].cache_path,
+            PathBuf::from("cache/courses%2Flogic.ics")
+        );
+        assert!(feeds
+            .iter()
+            .all(|feed| feed.calendar.lock().unwrap().is_none()));
+    }
+
+    #[test]
+    fn cached_calendar_keeps_its_time() {
+        let cache_path = fixtures::path("work.ics");
+        let feed = Feed::new(
+            String::new(),
+            vec![],
+            FilterRules::default(),
+            cache_path.clone(),
+        );
+        feed.load_cached().unwrap();
+        let written = std::fs::metadata(&cache_path).unwrap().modified().unwrap();
+        let mut calendar = feed.calendar.lock().unwrap().take().unwrap();
+        assert_eq!(calendar.modified, written);
+
+        // fetching the same calendar after a restart serves the same Last-Modified
+        calendar.replace(ComparableCalendar::new(
+            fixture("work.ics"),
+            &FilterRules::default(),
+        ));
+        assert_eq!(calendar.modified, written);
+        calendar.replace(ComparableCalendar::new(
+            fixture("sample.ics"),
+            &FilterRules::default(),
+        ));
+        assert!(calendar.modified > written);
+    }
+
+    #[test]
+    fn invalid_feeds() {
+        assert!(feeds("feeds = {}").is_err());
+        assert!(feeds("[feeds.a]\nurl = \"x\"\nrules = \"missing.toml\"").is_err());
+        assert!(feeds("[feeds.a]\nurl = \"x\"\n[feeds.\"/a\"]\nurl = \"y\"").is_err());
+        assert!(feeds("[feeds.a]\nurl = \"x\"\nrule = \"course.toml\"").is_err());
+        assert!(feeds("[feeds.\"..\"]\nurl = \"x\"").is_err());
+        assert!(feeds("[feeds.a]\nrules = \"course.toml\"").is_err());
+        assert!(
+            feeds("[feeds.a]\nurl = \"x\"\nsources = [{ name = \"b\", url = \"y\" }]").is_err()
+        );
+        assert!(feeds(
+            "[feeds.a]\nsources = [{ name = \"b\", url = \"x\" }, { name = \"b\", url = \"y\" }]"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn merges_once_every_source_is_fetched() {
+        let feeds = feeds(
+            r#"
+            [feeds.single]
+            url = "https://example.com/personal.ical"
+
+            [feeds.both]
+            sources = [
+                { name = "uni", url = "https://example.com/personal.ical" },
+                { name = "work", url = "https://example.com/work.ical" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let (both, single) = (&feeds[0], &feeds[1]);
+        let sample = fixture("sample.ics");
+        let work = fixture("work.ics");
+
+        assert_eq!(
+            single
+                .merge_fetched(0, sample.clone())
+                .unwrap()
+                .events
+                .len(),
+            5
+        );
+        assert!(both.merge_fetched(1, work.clone()).is_none());
+        assert_eq!(
+            both.merge_fetched(0, sample.clone()).unwrap().events.len(),
+            7
+        );
+        // later fetches replace what that source had before
+        assert_eq!(both.merge_fetched(1, sample).unwrap().events.len(), 5);
+    }
+}
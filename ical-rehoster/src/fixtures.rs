@@ -0,0 +1,30 @@
+//! Calendars, rules and snapshots in `tests/fixtures`, for the unit tests.
+use std::path::{Path, PathBuf};
+
+use ical::generator::IcalCalendar;
+
+pub(crate) fn path(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+pub(crate) fn parse(ics: &str) -> IcalCalendar {
+    ical::IcalParser::new(ics.as_bytes())
+        .next()
+        .unwrap()
+        .unwrap()
+}
+
+pub(crate) fn fixture(name: &str) -> IcalCalendar {
+    parse(&std::fs::read_to_string(path(name)).unwrap())
+}
+
+/// Compares `actual` with the snapshot file, or rewrites it when `UPDATE_SNAPSHOTS` is set.
+pub(crate) fn assert_snapshot(name: &str, actual: &str) {
+    let path = path(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing snapshot {}: {}", path.display(), e));
+    assert_eq!(actual, expected, "snapshot {} differs", name);
+}
@@ -0,0 +1,66 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Local};
+
+/// Lines kept for the dashboard, older ones are dropped.
+const CAPTURED_LINES: usize = 1000;
+
+/// Set once the dashboard owns the terminal, `log!` lines are printed until then.
+static CAPTURED: OnceLock<Mutex<VecDeque<LogLine>>> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub(crate) struct LogLine {
+    pub(crate) time: DateTime<Local>,
+    pub(crate) error: bool,
+    pub(crate) text: String,
+}
+
+/// Keeps further lines for [`captured`] instead of printing them.
+pub(crate) fn capture() {
+    CAPTURED.get_or_init(|| Mutex::new(VecDeque::with_capacity(CAPTURED_LINES)));
+}
+
+/// The latest `count` captured lines, oldest first.
+pub(crate) fn captured(count: usize) -> Vec<LogLine> {
+    CAPTURED.get().map_or(vec![], |lines| {
+        let lines = lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    })
+}
+
+pub(crate) fn line(error: bool, text: String) {
+    match CAPTURED.get() {
+        Some(lines) => {
+            let mut lines = lines.lock().unwrap();
+            if lines.len() == CAPTURED_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(LogLine {
+                time: Local::now(),
+                error,
+                text,
+            });
+        }
+        None if error => eprintln!("{}", text),
+        None => println!("{}", text),
+    }
+}
+
+/// `println!`, or a line in the dashboard's log pane.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::logging::line(false, format!($($arg)*))
+    };
+}
+/// `eprintln!`, or an error line in the dashboard's log pane.
+macro_rules! log_err {
+    ($($arg:tt)*) => {
+        $crate::logging::line(true, format!($($arg)*))
+    };
+}
+pub(crate) use {log, log_err};
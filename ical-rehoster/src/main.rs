@@ -1,44 +1,135 @@
 use std::env;
 use std::fmt::Display;
 use std::fs::File;
-use std::io::{stdin, Read, Write};
-use std::ops::{Deref, DerefMut};
-use std::sync::{Arc, Mutex};
+use std::io::{stdin, Write};
+use std::ops::DerefMut;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
-use axum::routing::get;
-use axum::Router;
-use reqwest::{IntoUrl, Response};
+use axum::body::Bytes;
+use clap::Parser;
+use tokio::task::JoinSet;
 use tokio::time::Sleep;
 
-use calendar::ComparableCalendar;
+use calendar::{ComparableCalendar, FilterRules};
+use cli::Cli;
+use feeds::{Feed, FeedsConfig, Source};
+use logging::{log, log_err};
+use upstream::{PollErr, Polled, Upstream};
 
 mod calendar;
+mod cli;
+mod feeds;
+#[cfg(test)]
+mod fixtures;
+mod logging;
+mod serve;
+mod tui;
+mod upstream;
 
 const CALENDAR_ENV_VAR: &str = "CALENDAR_ICAL_URL";
 const ICAL_CACHE_PATH: &str = "latest_processed.ical";
+const RULES_ENV_VAR: &str = "CALENDAR_RULES_PATH";
+const DEFAULT_RULES_PATH: &str = "rules.toml";
+const FEEDS_ENV_VAR: &str = "CALENDAR_FEEDS_PATH";
+const DEFAULT_FEEDS_PATH: &str = "feeds.toml";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // get data handle
-    let calendar_handle: Arc<Mutex<Option<ComparableCalendar>>> = Arc::new(Mutex::new({
-        let mut s = String::new();
-        match File::open(ICAL_CACHE_PATH) {
-            Ok(mut file) => {
-                // use pre-lived calendar data if possible
-                let byte_count = file.read_to_string(&mut s)?;
-                println!("Found previous data, read {} bytes from file.", byte_count);
-                Some(ComparableCalendar::from(
-                    ical::IcalParser::new(s.as_bytes())
-                        .next()
-                        .ok_or_else(|| anyhow::Error::msg("No (first) IcalCalendar in file?"))??,
-                ))
-            }
-            Err(_) => None, // no previous file
+    let cli = Cli::parse();
+
+    // get the feeds, falling back to a single one at `/` without a feeds file
+    let feeds_path = match env::var(FEEDS_ENV_VAR) {
+        Ok(path) if !Path::new(&path).exists() => {
+            anyhow::bail!(
+                "Feeds file `{}` from {} does not exist.",
+                path,
+                FEEDS_ENV_VAR
+            )
         }
-    }));
+        Ok(path) => path,
+        Err(_) => DEFAULT_FEEDS_PATH.to_string(),
+    };
+    let feeds = if Path::new(&feeds_path).exists() {
+        let feeds = FeedsConfig::load(&feeds_path, cli.cache_dir.clone())
+            .map_err(|e| e.context(format!("Invalid feeds file `{}`", feeds_path)))?;
+        println!("Loaded {} feeds from `{}`.", feeds.len(), feeds_path);
+        feeds
+    } else {
+        vec![single_feed(&cli)?]
+    };
+
+    // get data handles
+    for feed in &feeds {
+        feed.load_cached()?;
+    }
+    let feeds: Vec<Arc<Feed>> = feeds.into_iter().map(Arc::new).collect();
 
     println!();
+    // the dashboard takes over the terminal from here
+    let dashboard_task = cli.tui.then(|| {
+        logging::capture();
+        let feeds = feeds.clone();
+        tokio::task::spawn_blocking(move || tui::run(feeds))
+    });
+    // create tasks to poll
+    let mut fetch_write_tasks = JoinSet::new();
+    for feed in &feeds {
+        for (source, Source { url, .. }) in feed.sources.iter().enumerate() {
+            let feed = feed.clone();
+            let mut upstream = Upstream::new(url.clone(), cli.poll_interval(), cli.error_delay());
+            // fetching
+            fetch_write_tasks.spawn(async move {
+                loop {
+                    match fetch_ical(&feed, source, &mut upstream).await {
+                        Ok(_) => {} // continue
+                        Err(e) => {
+                            log_err!("[{}] Error in fetch task:\n{}", feed.label(source), e);
+                            break e;
+                        }
+                    };
+                }
+            });
+        }
+    }
+    // providing
+    let listener = tokio::net::TcpListener::bind(cli.address()).await?;
+    log!("Serving on http://{}", cli.address());
+    let rehost_task =
+        tokio::task::spawn(async move { axum::serve(listener, serve::router(&feeds)).await });
+
+    tokio::select! {
+        served = rehost_task => {
+            served??;
+            Ok(())
+        }
+        Some(fetched) = fetch_write_tasks.join_next() => Err(fetched?),
+        Some(quit) = async { Some(dashboard_task?.await) } => {
+            quit??;
+            Ok(())
+        }
+    }
+}
+
+/// The feed configured through the environment, before there were feeds files.
+fn single_feed(cli: &Cli) -> anyhow::Result<Feed> {
+    // get filter rules, keeping every component if there are none
+    let rules_path = env::var(RULES_ENV_VAR).unwrap_or_else(|_| DEFAULT_RULES_PATH.to_string());
+    let rules = match FilterRules::load(&rules_path) {
+        Ok(rules) => {
+            println!("Loaded filter rules from `{}`.", rules_path);
+            rules
+        }
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+            println!(
+                "No filter rules read from `{}` ({}), keeping every component.",
+                rules_path, e
+            );
+            FilterRules::default()
+        }
+        Err(e) => return Err(e.context(format!("Invalid filter rules in `{}`", rules_path))),
+    };
 
     // get the target/update url
     let url = match env::var(CALENDAR_ENV_VAR) {
@@ -51,7 +142,7 @@ async fn main() -> anyhow::Result<()> {
                 "URL not found in environment variable `{}`... ",
                 CALENDAR_ENV_VAR
             );
-            match env::args().nth(1 /* skip directory argument */) {
+            match cli.url.clone() {
                 Some(s) => {
                     println!("Found passed argument `{}`, using it as fetch URL.", s);
                     s
@@ -66,117 +157,117 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    println!();
-    // create tasks to poll
-    let (fetch_write_task, rehost_task) = (
-        // fetching
-        tokio::task::spawn({
-            // make copy of handle for writing task (cannot move pre-clone)
-            let calendar_write_handle = calendar_handle.clone();
-            async move {
-                let mut calendar_handle = calendar_write_handle;
-                loop {
-                    match fetch_ical(&url, &mut calendar_handle).await {
-                        Ok(_) => {} // continue
-                        Err(e) => {
-                            eprintln!("Error in fetch task:\n{}", e);
-                            break e;
-                        }
-                    };
-                }
-            }
-        }),
-        // providing
-        tokio::task::spawn(async move {
-            axum::serve(
-                tokio::net::TcpListener::bind("127.0.0.1:1234")
-                    .await
-                    .unwrap(),
-                Router::new().route(
-                    "/",
-                    get(|| async move {
-                        println!("GET requested from server. ");
-                        match calendar_handle.lock().unwrap().deref() {
-                            None => "".into(),
-                            Some(val) => val.filtered.clone(),
-                        }
-                    }),
-                ),
-            )
-            .await
-        }),
-    );
-
-    let (a, b) = tokio::try_join!(rehost_task, fetch_write_task)?;
-    a?;
-    Err(b)
+    Ok(Feed::new(
+        String::new(),
+        vec![Source {
+            name: String::new(),
+            url,
+        }],
+        rules,
+        match &cli.cache_dir {
+            Some(dir) => dir.join(ICAL_CACHE_PATH),
+            None => PathBuf::from(ICAL_CACHE_PATH),
+        },
+    ))
 }
 
-async fn fetch_ical(
-    url: impl IntoUrl,
-    calendar_handle: &mut Arc<Mutex<Option<ComparableCalendar>>>,
-) -> anyhow::Result<()> {
-    match reqwest::get(url)
-        .await
-        .and_then(|response| response.error_for_status())
-    {
-        Ok(valid_response) => {
-            if let Err(e) = try_update(calendar_handle, valid_response).await {
-                eprintln!("Response was valid, but failed update - {}", e)
+async fn fetch_ical(feed: &Feed, source: usize, upstream: &mut Upstream) -> anyhow::Result<()> {
+    let polled = upstream.poll().await;
+    feed.record_poll(
+        source,
+        match &polled {
+            Err(PollErr::Request(e)) => Err(e.to_string()),
+            _ => upstream
+                .last_status()
+                .ok_or_else(|| "no response".to_string()),
+        },
+    );
+    let retry_after = match polled {
+        Ok(Polled::Changed(bytes, validators)) => {
+            match try_update(feed, source, bytes) {
+                Ok(()) => upstream.remember(validators),
+                Err(e) => log_err!(
+                    "[{}] Response was valid, but failed update - {}",
+                    feed.label(source),
+                    e
+                ),
             };
-
-            let valid_delay = Duration::from_secs(10);
-            wait_delay("delay before next update...", valid_delay).await;
+            None
+        }
+        Ok(Polled::NotModified) => {
+            log!(
+                "[{}] Upstream ical not modified, no changes needed.",
+                feed.label(source)
+            );
+            None
         }
         Err(e) => {
-            eprintln!("Request error - {}", e);
-            if let Some(code) = e.status() {
-                eprintln!("Status code for request: {}", code)
+            log_err!("[{}] {}", feed.label(source), e);
+            match e {
+                PollErr::Status { retry_after, .. } => retry_after,
+                PollErr::Request(_) => None,
             }
-            let err_delay = Duration::from_secs(5);
-            wait_delay("delay before trying again...", err_delay).await;
         }
     };
+    let msg = match upstream.failures() == 0 {
+        true => "delay before next update...",
+        false => "delay before trying again...",
+    };
+    wait_delay(&feed.label(source), msg, upstream.next_delay(retry_after)).await;
     Ok(())
 }
 
-fn wait_delay(msg: impl Display, duration: Duration) -> Sleep {
-    println!("WAIT {} SECONDS: {}", duration.as_secs(), msg);
+fn wait_delay(label: &str, msg: impl Display, duration: Duration) -> Sleep {
+    log!("[{}] WAIT {} SECONDS: {}", label, duration.as_secs(), msg);
     tokio::time::sleep(duration)
 }
 
-async fn try_update(
-    calendar_handle: &mut Arc<Mutex<Option<ComparableCalendar>>>,
-    success: Response,
-) -> anyhow::Result<()> {
-    let bytes = success.bytes().await?;
-    let new = ComparableCalendar::from(
-        ical::IcalParser::new(&bytes[..])
-            .next()
-            .ok_or_else(|| anyhow::Error::msg("No first IcalCalendar in fetched bytes?"))??,
-    );
+fn try_update(feed: &Feed, source: usize, bytes: Bytes) -> anyhow::Result<()> {
+    let fetched = ical::IcalParser::new(&bytes[..])
+        .next()
+        .ok_or_else(|| anyhow::Error::msg("No first IcalCalendar in fetched bytes?"))??;
+    let Some(merged) = feed.merge_fetched(source, fetched) else {
+        log!(
+            "[{}] Waiting for the other sources before merging.",
+            feed.label(source)
+        );
+        return Ok(());
+    };
+    let new = ComparableCalendar::new(merged, &feed.rules);
 
     // try to add/change calendar
-    match calendar_handle.lock().unwrap().deref_mut() {
+    match feed.calendar.lock().unwrap().deref_mut() {
         // match guard lock may break app(?)
         Some(prev) if *prev == new => {
-            println!("Newly fetched ical is identical to previous, no changes needed.")
+            log!(
+                "[{}] Newly fetched ical is identical to previous, no changes needed.",
+                feed.path()
+            )
         }
         should_change => {
+            let ical = new.generate_source();
             match should_change {
                 Some(prev) => {
-                    *prev = new;
-                    println!("Updated calendar: Newly parsed ical was different than previous.");
+                    prev.replace(new);
+                    log!(
+                        "[{}] Updated calendar: Newly parsed ical was different than previous.",
+                        feed.path()
+                    );
                 }
                 none_ref @ None => {
                     *none_ref = Some(new);
-                    println!("Added initial calendar from parsed ical.")
+                    log!("[{}] Added initial calendar from parsed ical.", feed.path())
                 }
             }
-            File::create(ICAL_CACHE_PATH)?.write_all(&bytes[..])?;
-            println!(
-                "Wrote changes to file of total size: {} bytes.",
-                bytes.len()
+            if let Some(dir) = feed.cache_path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            File::create(&feed.cache_path)?.write_all(ical.as_bytes())?;
+            log!(
+                "[{}] Wrote changes to `{}` of total size: {} bytes.",
+                feed.path(),
+                feed.cache_path.display(),
+                ical.len()
             )
         }
     }
@@ -0,0 +1,189 @@
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::http::header::{
+    CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+
+use crate::calendar::ComparableCalendar;
+use crate::feeds::Feed;
+use crate::logging::log;
+
+const CALENDAR_CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+
+/// One route per feed, plus an index of them at `/` unless a feed is served there.
+pub(crate) fn router(feeds: &[Arc<Feed>]) -> Router {
+    let mut router = Router::new();
+    for feed in feeds {
+        let feed = feed.clone();
+        router = router.route(
+            &feed.path(),
+            get(|headers: HeaderMap| async move {
+                log!("[{}] GET requested from server. ", feed.path());
+                match feed.calendar.lock().unwrap().deref() {
+                    None => "".into_response(),
+                    Some(val) => calendar_response(val, &headers),
+                }
+            }),
+        );
+    }
+    if !feeds.iter().any(|feed| feed.route.is_empty()) {
+        let feeds = feeds.to_vec();
+        router = router.route("/", get(|| async move { index(&feeds) }));
+    }
+    router
+}
+
+fn index(feeds: &[Arc<Feed>]) -> String {
+    feeds
+        .iter()
+        .map(|feed| match feed.calendar.lock().unwrap().deref() {
+            None => format!("{} (not fetched yet)\n", feed.path()),
+            Some(_) => format!("{}\n", feed.path()),
+        })
+        .collect()
+}
+
+/// The filtered calendar, or 304 if the client's copy is still current.
+fn calendar_response(calendar: &ComparableCalendar, request: &HeaderMap) -> Response {
+    let headers = [
+        (CONTENT_TYPE, CALENDAR_CONTENT_TYPE.to_string()),
+        (ETAG, calendar.etag.clone()),
+        (LAST_MODIFIED, httpdate::fmt_http_date(calendar.modified)),
+        // calendar clients should always ask, we answer cheaply
+        (CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if is_not_modified(calendar, request) {
+        (StatusCode::NOT_MODIFIED, headers).into_response()
+    } else {
+        (headers, calendar.filtered.clone()).into_response()
+    }
+}
+
+/// `If-None-Match` wins over `If-Modified-Since` when both are sent.
+fn is_not_modified(calendar: &ComparableCalendar, request: &HeaderMap) -> bool {
+    let header = |name| request.get(name).and_then(|value| value.to_str().ok());
+    if let Some(tags) = header(IF_NONE_MATCH) {
+        return tags
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == calendar.etag);
+    }
+    let seconds = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    };
+    header(IF_MODIFIED_SINCE)
+        .and_then(|since| httpdate::parse_http_date(since).ok())
+        .is_some_and(|since| seconds(calendar.modified) <= seconds(since))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    use reqwest::StatusCode;
+
+    use super::router;
+    use crate::calendar::{ComparableCalendar, FilterRules};
+    use crate::feeds::Feed;
+    use crate::fixtures::fixture;
+
+    async fn serve(feeds: Vec<Feed>) -> String {
+        let feeds: Vec<_> = feeds.into_iter().map(Arc::new).collect();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(&feeds)).await });
+        base
+    }
+
+    fn feed(route: &str, fetched: bool) -> Feed {
+        let feed = Feed::new(
+            route.to_string(),
+            vec![],
+            FilterRules::default(),
+            PathBuf::new(),
+        );
+        if fetched {
+            let calendar = fixture("sample.ics");
+            *feed.calendar.lock().unwrap() =
+                Some(ComparableCalendar::new(calendar, &FilterRules::default()));
+        }
+        feed
+    }
+
+    #[tokio::test]
+    async fn conditional_get() {
+        let base = serve(vec![feed("all.ics", true)]).await;
+        let url = format!("{}/all.ics", base);
+        let client = reqwest::Client::new();
+
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        assert_eq!(headers[CONTENT_TYPE], "text/calendar; charset=utf-8");
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .starts_with("BEGIN:VCALENDAR"));
+        let etag = headers[ETAG].clone();
+        let last_modified = headers[LAST_MODIFIED].clone();
+
+        let status = |request: reqwest::RequestBuilder| async move {
+            request.send().await.unwrap().status()
+        };
+        let not_modified = StatusCode::NOT_MODIFIED;
+        let get = || client.get(&url);
+        assert_eq!(
+            status(get().header(IF_NONE_MATCH, &etag)).await,
+            not_modified
+        );
+        assert_eq!(
+            status(get().header(IF_NONE_MATCH, "\"other\", *")).await,
+            not_modified
+        );
+        assert_eq!(
+            status(get().header(IF_NONE_MATCH, "\"other\"")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(get().header(IF_MODIFIED_SINCE, &last_modified)).await,
+            not_modified
+        );
+        let earlier = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(3600));
+        assert_eq!(
+            status(get().header(IF_MODIFIED_SINCE, earlier)).await,
+            StatusCode::OK
+        );
+        // the etag decides when both are sent
+        assert_eq!(
+            status(
+                get()
+                    .header(IF_NONE_MATCH, "\"other\"")
+                    .header(IF_MODIFIED_SINCE, &last_modified)
+            )
+            .await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn index_lists_feeds() {
+        let base = serve(vec![feed("a.ics", true), feed("b/c.ics", false)]).await;
+        let index = reqwest::get(&base).await.unwrap().text().await.unwrap();
+        assert_eq!(index, "/a.ics\n/b/c.ics (not fetched yet)\n");
+        let pending = reqwest::get(format!("{}/b/c.ics", base)).await.unwrap();
+        assert_eq!(pending.status(), StatusCode::OK);
+        assert_eq!(pending.text().await.unwrap(), "");
+    }
+}
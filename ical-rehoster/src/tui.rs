@@ -0,0 +1,398 @@
+use std::io::{self, stdout};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::ExecutableCommand;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use reqwest::StatusCode;
+
+use crate::feeds::{Feed, SourceStatus};
+use crate::logging;
+
+/// Redraws at least this often, so fetches and log lines show up without a key press.
+const TICK: Duration = Duration::from_millis(250);
+const PAGE: usize = 10;
+/// Source rows shown at once, so many routes still leave room for the events and the log.
+const LISTED: usize = 8;
+
+/// Shows the dashboard until it is quit with `q`, `Esc` or `Ctrl-C`.
+/// Blocking, so it should run on its own thread.
+pub(crate) fn run(feeds: Vec<Arc<Feed>>) -> io::Result<()> {
+    enable_raw_mode()?;
+    stdout().execute(EnterAlternateScreen)?;
+    // restores the terminal however this returns
+    let _restore = Restore;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    let mut dashboard = Dashboard::new(feeds);
+    loop {
+        terminal.draw(|frame| dashboard.draw(frame, Local::now().naive_local()))?;
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && dashboard.handle(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+struct Restore;
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = stdout().execute(LeaveAlternateScreen);
+    }
+}
+
+struct Dashboard {
+    feeds: Vec<Arc<Feed>>,
+    /// Whose upcoming events are listed.
+    selected: usize,
+    events: ListState,
+    /// How many events the last draw listed, which keeps the selection among them.
+    listed: usize,
+}
+impl Dashboard {
+    fn new(feeds: Vec<Arc<Feed>>) -> Self {
+        Self {
+            feeds,
+            selected: 0,
+            events: ListState::default().with_selected(Some(0)),
+            listed: 0,
+        }
+    }
+
+    /// Returns whether to quit.
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        let event = self.events.selected().unwrap_or_default();
+        let last = self.listed.saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Tab | KeyCode::Right => self.select_feed(self.selected + 1),
+            KeyCode::BackTab | KeyCode::Left => {
+                self.select_feed(self.selected + self.feeds.len() - 1)
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.events.select(Some(event.saturating_add(1).min(last)))
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.events.select(Some(event.saturating_sub(1))),
+            KeyCode::PageDown => self
+                .events
+                .select(Some(event.saturating_add(PAGE).min(last))),
+            KeyCode::PageUp => self.events.select(Some(event.saturating_sub(PAGE))),
+            KeyCode::Home => self.events.select(Some(0)),
+            KeyCode::End => self.events.select(Some(last)),
+            _ => {}
+        }
+        false
+    }
+
+    fn select_feed(&mut self, feed: usize) {
+        self.selected = feed % self.feeds.len().max(1);
+        self.events = ListState::default().with_selected(Some(0));
+    }
+
+    fn draw(&mut self, frame: &mut Frame, now: NaiveDateTime) {
+        let sources: usize = self.feeds.iter().map(|feed| feed.sources.len()).sum();
+        let [feeds, events, log] = Layout::vertical([
+            Constraint::Length(sources.min(LISTED) as u16 + 3),
+            Constraint::Min(5),
+            Constraint::Percentage(30),
+        ])
+        .areas(frame.size());
+        self.draw_feeds(frame, feeds);
+        self.draw_events(frame, events, now);
+        draw_log(frame, log);
+    }
+
+    /// One row per source, with the feed's event counts on its first row.
+    fn draw_feeds(&self, frame: &mut Frame, area: Rect) {
+        let mut rows = vec![];
+        for (i, feed) in self.feeds.iter().enumerate() {
+            let counts = match feed.calendar.lock().unwrap().deref() {
+                None => "not fetched yet".to_string(),
+                Some(calendar) => {
+                    let (kept, total) = calendar.event_counts();
+                    format!("{} kept, {} filtered out", kept, total - kept)
+                }
+            };
+            let style = match i == self.selected {
+                true => Style::new().add_modifier(Modifier::BOLD),
+                false => Style::new(),
+            };
+            for (j, (source, status)) in feed.sources.iter().zip(feed.statuses()).enumerate() {
+                let first = j == 0;
+                rows.push(
+                    Row::new([
+                        Span::raw(if first { feed.path() } else { String::new() }),
+                        Span::raw(match feed.sources.len() {
+                            1 => "",
+                            _ => source.name.as_str(),
+                        }),
+                        Span::raw(status.last_fetch.map_or("never".to_string(), |time| {
+                            time.format("%H:%M:%S").to_string()
+                        })),
+                        status_span(&status),
+                        Span::raw(if first { counts.clone() } else { String::new() }),
+                    ])
+                    .style(style),
+                );
+            }
+        }
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(24),
+                Constraint::Fill(2),
+            ],
+        )
+        .header(
+            Row::new(["Route", "Source", "Fetched", "Status", "Events"])
+                .add_modifier(Modifier::UNDERLINED),
+        )
+        .block(Block::bordered().title(" Feeds · Tab select · ↑↓ scroll · q quit "));
+        // scrolls down until the last row of the selected feed is in view, when not all fit
+        let last_row = self
+            .feeds
+            .iter()
+            .take(self.selected + 1)
+            .map(|feed| feed.sources.len())
+            .sum::<usize>()
+            .saturating_sub(1);
+        let mut state = TableState::default().with_selected(Some(last_row));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn draw_events(&mut self, frame: &mut Frame, area: Rect, now: NaiveDateTime) {
+        self.listed = 0;
+        let Some(feed) = self.feeds.get(self.selected) else {
+            return;
+        };
+        let block = Block::bordered().title(format!(" Upcoming in {} ", feed.path()));
+        let upcoming = feed
+            .calendar
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|calendar| calendar.upcoming(now));
+        let Some(upcoming) = upcoming else {
+            frame.render_widget(Paragraph::new("Not fetched yet.").block(block), area);
+            return;
+        };
+        if upcoming.is_empty() {
+            frame.render_widget(Paragraph::new("No upcoming events.").block(block), area);
+            return;
+        }
+        self.listed = upcoming.len();
+        let selected = self.events.selected().unwrap_or_default();
+        self.events.select(Some(selected.min(upcoming.len() - 1)));
+        let items = upcoming.into_iter().map(|event| {
+            let mut line = vec![
+                Span::raw(event.start.format("%a %d %b %Y %H:%M  ").to_string()).dim(),
+                Span::raw(match event.recurring {
+                    true => "↻ ",
+                    false => "  ",
+                }),
+                Span::raw(event.summary),
+            ];
+            if let Some(location) = event.location {
+                line.push(Span::raw(format!("  @ {}", location)).dim());
+            }
+            ListItem::new(Line::from(line))
+        });
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.events);
+    }
+}
+
+fn status_span(status: &SourceStatus) -> Span<'static> {
+    match &status.status {
+        None => Span::raw("-"),
+        Some(Ok(code)) if code.is_success() || *code == StatusCode::NOT_MODIFIED => {
+            Span::raw(code.to_string()).fg(Color::Green)
+        }
+        Some(Ok(code)) => Span::raw(code.to_string()).fg(Color::Red),
+        Some(Err(_)) => Span::raw("request failed").fg(Color::Red),
+    }
+}
+
+/// The latest lines that fit, newest at the bottom.
+fn draw_log(frame: &mut Frame, area: Rect) {
+    let lines: Vec<_> = logging::captured(area.height.saturating_sub(2) as usize)
+        .into_iter()
+        .map(|line| {
+            let text = Span::raw(line.text);
+            Line::from(vec![
+                Span::raw(line.time.format("%H:%M:%S ").to_string()).dim(),
+                match line.error {
+                    true => text.fg(Color::Red),
+                    false => text,
+                },
+            ])
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Log ")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use chrono::NaiveDate;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use reqwest::StatusCode;
+
+    use super::Dashboard;
+    use crate::calendar::{ComparableCalendar, FilterRules};
+    use crate::feeds::{Feed, Source};
+    use crate::fixtures::{self, fixture};
+
+    fn feed(route: &str, sources: &[&str], rules: &str) -> Arc<Feed> {
+        let sources = sources
+            .iter()
+            .map(|name| Source {
+                name: name.to_string(),
+                url: String::new(),
+            })
+            .collect();
+        let feed = Feed::new(
+            route.to_string(),
+            sources,
+            FilterRules::load(fixtures::path(rules)).unwrap(),
+            PathBuf::new(),
+        );
+        Arc::new(feed)
+    }
+
+    fn fetch(feed: &Feed) {
+        let calendar = fixture("sample.ics");
+        *feed.calendar.lock().unwrap() = Some(ComparableCalendar::new(calendar, &feed.rules));
+    }
+
+    fn render(dashboard: &mut Dashboard) -> String {
+        let now = NaiveDate::from_ymd_opt(2024, 1, 30)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame, now)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn shows_feeds_and_upcoming_events() {
+        let logic = feed("logic.ics", &["logic.ics"], "course.toml");
+        let combined = feed("combined.ics", &["uni", "work"], "combined.json");
+        fetch(&logic);
+        logic.record_poll(0, Ok(StatusCode::OK));
+        combined.record_poll(1, Err("connection refused".to_string()));
+        let mut dashboard = Dashboard::new(vec![logic, combined]);
+
+        let screen = render(&mut dashboard);
+        assert!(screen.contains("/logic.ics"), "{}", screen);
+        assert!(screen.contains("200 OK"), "{}", screen);
+        assert!(screen.contains("3 kept, 2 filtered out"), "{}", screen);
+        assert!(screen.contains("/combined.ics"), "{}", screen);
+        assert!(screen.contains("never"), "{}", screen);
+        assert!(screen.contains("request failed"), "{}", screen);
+        assert!(screen.contains("not fetched yet"), "{}", screen);
+        // the lecture on the 29th is past, the cancelled one on the 31st isn't
+        assert!(screen.contains("Upcoming in /logic.ics"), "{}", screen);
+        assert!(!screen.contains("Mon 29 Jan 2024"), "{}", screen);
+        assert!(screen.contains("Wed 31 Jan 2024 08:00"), "{}", screen);
+        assert!(screen.contains("Mon 12 Feb 2024 10:00"), "{}", screen);
+        assert!(screen.contains("@ 5510-103 Lille Aud."), "{}", screen);
+
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert!(!dashboard.handle(press(KeyCode::End)));
+        render(&mut dashboard);
+        assert_eq!(dashboard.events.selected(), Some(1));
+        assert!(!dashboard.handle(press(KeyCode::Tab)));
+        assert_eq!(dashboard.events.selected(), Some(0));
+        assert!(render(&mut dashboard).contains("Upcoming in /combined.ics"));
+        assert!(!dashboard.handle(press(KeyCode::Tab)));
+        assert_eq!(dashboard.selected, 0);
+        assert!(!dashboard.handle(press(KeyCode::BackTab)));
+        assert_eq!(dashboard.selected, 1);
+
+        assert!(dashboard.handle(press(KeyCode::Char('q'))));
+        assert!(dashboard.handle(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn scrolls_within_the_events() {
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        // nothing fetched, so nothing listed
+        let mut dashboard = Dashboard::new(vec![feed("empty.ics", &["empty.ics"], "course.toml")]);
+        render(&mut dashboard);
+        for code in [KeyCode::End, KeyCode::Down, KeyCode::PageDown] {
+            assert!(!dashboard.handle(press(code)));
+            assert_eq!(dashboard.events.selected(), Some(0));
+        }
+        render(&mut dashboard);
+
+        let logic = feed("logic.ics", &["logic.ics"], "course.toml");
+        fetch(&logic);
+        let mut dashboard = Dashboard::new(vec![logic]);
+        render(&mut dashboard);
+        assert!(!dashboard.handle(press(KeyCode::PageDown)));
+        assert_eq!(dashboard.events.selected(), Some(1));
+        assert!(!dashboard.handle(press(KeyCode::End)));
+        assert!(!dashboard.handle(press(KeyCode::Down)));
+        assert_eq!(dashboard.events.selected(), Some(1));
+        assert!(!dashboard.handle(press(KeyCode::Up)));
+        assert_eq!(dashboard.events.selected(), Some(0));
+    }
+
+    #[test]
+    fn long_feed_lists_leave_room_for_the_rest() {
+        let feeds: Vec<_> = (0..20)
+            .map(|i| {
+                let route = format!("feed{:02}.ics", i);
+                feed(&route, &[&route], "course.toml")
+            })
+            .collect();
+        fetch(&feeds[15]);
+        let mut dashboard = Dashboard::new(feeds);
+        let screen = render(&mut dashboard);
+        assert!(screen.contains("/feed07.ics"), "{}", screen);
+        assert!(!screen.contains("/feed08.ics"), "{}", screen);
+        assert!(screen.contains("Upcoming in /feed00.ics"), "{}", screen);
+        assert!(screen.contains(" Log "), "{}", screen);
+
+        // the selected feed is scrolled into view
+        dashboard.select_feed(15);
+        let screen = render(&mut dashboard);
+        assert!(screen.contains("/feed15.ics "), "{}", screen);
+        assert!(!screen.contains("/feed00.ics "), "{}", screen);
+        assert!(screen.contains("Wed 31 Jan 2024 08:00"), "{}", screen);
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime};
+
+use axum::body::Bytes;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::StatusCode;
+
+/// Upper bound for the exponential backoff, `Retry-After` may still ask for longer.
+const MAX_ERROR_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// Polls one upstream calendar with conditional GETs,
+/// so an unchanged calendar is not downloaded again.
+pub(crate) struct Upstream {
+    client: reqwest::Client,
+    url: String,
+    validators: Validators,
+    poll_interval: Duration,
+    error_delay: Duration,
+    failures: u32,
+    last_status: Option<StatusCode>,
+}
+
+/// What the upstream last told us about the version we hold.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Validators {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+}
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            etag: headers.get(ETAG).cloned(),
+            last_modified: headers.get(LAST_MODIFIED).cloned(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Polled {
+    /// The validators should be [remembered](Upstream::remember) once the body has been used.
+    Changed(Bytes, Validators),
+    NotModified,
+}
+
+#[derive(Debug)]
+pub(crate) enum PollErr {
+    Request(reqwest::Error),
+    Status {
+        status: StatusCode,
+        retry_after: Option<Duration>,
+    },
+}
+impl Display for PollErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PollErr::Request(e) => write!(f, "Request error - {}", e),
+            PollErr::Status {
+                status,
+                retry_after: None,
+            } => write!(f, "Status code for request: {}", status),
+            PollErr::Status {
+                status,
+                retry_after: Some(after),
+            } => write!(
+                f,
+                "Status code for request: {}, retry after {} seconds",
+                status,
+                after.as_secs()
+            ),
+        }
+    }
+}
+
+impl Upstream {
+    pub(crate) fn new(url: String, poll_interval: Duration, error_delay: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            validators: Validators::default(),
+            poll_interval,
+            error_delay,
+            failures: 0,
+            last_status: None,
+        }
+    }
+
+    pub(crate) async fn poll(&mut self) -> Result<Polled, PollErr> {
+        let result = self.request().await;
+        match &result {
+            Ok(_) => self.failures = 0,
+            Err(_) => self.failures = self.failures.saturating_add(1),
+        }
+        result
+    }
+
+    async fn request(&mut self) -> Result<Polled, PollErr> {
+        self.last_status = None;
+        let mut request = self.client.get(&self.url);
+        if let Some(etag) = &self.validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request.send().await.map_err(PollErr::Request)?;
+        self.last_status = Some(response.status());
+        match response.status() {
+            StatusCode::NOT_MODIFIED => Ok(Polled::NotModified),
+            status if status.is_success() => {
+                let validators = Validators::from_headers(response.headers());
+                let bytes = response.bytes().await.map_err(PollErr::Request)?;
+                Ok(Polled::Changed(bytes, validators))
+            }
+            status => Err(PollErr::Status {
+                status,
+                retry_after: response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after),
+            }),
+        }
+    }
+
+    /// The status of the last response, if the last poll got one.
+    pub(crate) fn last_status(&self) -> Option<StatusCode> {
+        self.last_status
+    }
+
+    /// Failed polls in a row.
+    pub(crate) fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Sends these validators with the next polls, until the upstream changes again.
+    pub(crate) fn remember(&mut self, validators: Validators) {
+        self.validators = validators;
+    }
+
+    /// The poll interval after a success, otherwise the error delay doubled for every failure
+    /// in a row, but never shorter than what the upstream asked for.
+    pub(crate) fn next_delay(&self, retry_after: Option<Duration>) -> Duration {
+        let backoff = match self.failures {
+            0 => self.poll_interval,
+            n => self
+                .error_delay
+                .saturating_mul(2u32.saturating_pow(n - 1))
+                .min(MAX_ERROR_DELAY),
+        };
+        backoff.max(retry_after.unwrap_or_default())
+    }
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    match value.trim().parse() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::Router;
+    use reqwest::header::{ETAG, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER};
+
+    use super::{parse_retry_after, PollErr, Polled, Upstream};
+
+    /// Plays the upstream at `/calendar.ics`, failing the first `fail` requests with a 503.
+    async fn stub(fail: usize) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let router = Router::new().route(
+            "/calendar.ics",
+            get(move |headers: HeaderMap| async move {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                if n < fail {
+                    return (StatusCode::SERVICE_UNAVAILABLE, [(RETRY_AFTER, "7")]).into_response();
+                }
+                if headers
+                    .get(IF_NONE_MATCH)
+                    .is_some_and(|tag| tag == "\"v1\"")
+                {
+                    return StatusCode::NOT_MODIFIED.into_response();
+                }
+                (
+                    [
+                        (ETAG, "\"v1\""),
+                        (LAST_MODIFIED, "Mon, 29 Jan 2024 10:00:00 GMT"),
+                    ],
+                    "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n",
+                )
+                    .into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/calendar.ics", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (url, requests)
+    }
+
+    fn upstream(url: String) -> Upstream {
+        Upstream::new(url, Duration::from_secs(10), Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn conditional_fetch() {
+        let (url, requests) = stub(0).await;
+        let mut upstream = upstream(url);
+
+        let Ok(Polled::Changed(bytes, validators)) = upstream.poll().await else {
+            panic!("first poll should download the calendar");
+        };
+        assert!(bytes.starts_with(b"BEGIN:VCALENDAR"));
+        assert_eq!(validators.etag.as_ref().unwrap(), "\"v1\"");
+        assert_eq!(
+            validators.last_modified.as_ref().unwrap(),
+            "Mon, 29 Jan 2024 10:00:00 GMT"
+        );
+
+        // nothing remembered yet, e.g. because the body could not be parsed
+        assert!(matches!(upstream.poll().await, Ok(Polled::Changed(..))));
+
+        upstream.remember(validators);
+        assert!(matches!(upstream.poll().await, Ok(Polled::NotModified)));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(upstream.last_status(), Some(StatusCode::NOT_MODIFIED));
+        assert_eq!(upstream.next_delay(None), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn backoff_and_retry_after() {
+        let (url, _) = stub(3).await;
+        let mut upstream = upstream(url);
+
+        let mut delays = vec![];
+        for _ in 0..3 {
+            let Err(PollErr::Status {
+                status,
+                retry_after,
+            }) = upstream.poll().await
+            else {
+                panic!("stub should be unavailable");
+            };
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(retry_after, Some(Duration::from_secs(7)));
+            delays.push(upstream.next_delay(retry_after).as_secs());
+        }
+        assert_eq!(delays, [7, 10, 20]);
+
+        assert!(matches!(upstream.poll().await, Ok(Polled::Changed(..))));
+        assert_eq!(upstream.next_delay(None), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn request_errors_back_off() {
+        // nothing listens on a port that was just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let mut upstream = upstream(url);
+        for expected in [5, 10, 20, 40] {
+            assert!(matches!(upstream.poll().await, Err(PollErr::Request(_))));
+            assert_eq!(upstream.last_status(), None);
+            assert_eq!(upstream.next_delay(None).as_secs(), expected);
+        }
+        upstream.failures = 30;
+        assert_eq!(upstream.next_delay(None), super::MAX_ERROR_DELAY);
+    }
+
+    #[test]
+    fn retry_after_values() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Mon, 29 Jan 2024 10:00:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+        let after = parse_retry_after(&later).unwrap();
+        assert!(after > Duration::from_secs(3500) && after <= Duration::from_secs(3600));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
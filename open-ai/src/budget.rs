@@ -0,0 +1,223 @@
+use anyhow::{bail, Result};
+
+use crate::conversation::{Conversation, Message};
+
+/// What every message costs on top of its content.
+const PER_MESSAGE: usize = 4;
+/// What every request costs on top of its messages, for starting the reply.
+const PER_REQUEST: usize = 3;
+/// Kept free for the reply when the budget is the model's whole context.
+pub const REPLY_RESERVE: usize = 1_000;
+
+/// Context sizes by model name prefix, the first match counting.
+const LIMITS: &[(&str, usize)] = &[
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-3.5-turbo", 16_385),
+];
+/// For models not in the list.
+const UNKNOWN_LIMIT: usize = 4_096;
+
+/// Put before the summary when it is sent.
+pub const SUMMARY_INTRO: &str = "Summary of the conversation so far: ";
+
+/// Roughly how many tokens `text` is: a token for every four letters or digits of a word, and one
+/// for every other symbol. Tends to guess a little high, which is the safe side.
+pub fn estimate(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word = 0_usize;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word += 1;
+            continue;
+        }
+        tokens += word.div_ceil(4);
+        word = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word.div_ceil(4)
+}
+
+fn message(content: &str) -> usize {
+    estimate(content) + PER_MESSAGE
+}
+
+fn summary(content: &str) -> usize {
+    estimate(SUMMARY_INTRO) + message(content)
+}
+
+/// How many tokens the model takes in at once.
+pub fn model_limit(model: &str) -> usize {
+    LIMITS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map_or(UNKNOWN_LIMIT, |&(_, limit)| limit)
+}
+
+/// The most tokens a request of the conversation may have.
+pub fn budget(conversation: &Conversation) -> usize {
+    let limit = model_limit(&conversation.model).saturating_sub(REPLY_RESERVE);
+    conversation
+        .budget
+        .map_or(limit, |budget| budget.min(limit))
+}
+
+/// The part of a conversation that is sent.
+#[derive(Debug)]
+pub struct Context<'a> {
+    pub system: Option<&'a str>,
+    pub summary: Option<&'a str>,
+    /// The newest messages, as many as fit.
+    pub messages: &'a [Message],
+    /// Older messages neither sent nor summarized.
+    pub left_out: usize,
+    pub tokens: usize,
+    pub budget: usize,
+}
+
+/// Where the messages that fit start, with `summary` tokens set aside for the summary.
+fn first_fitting(conversation: &Conversation, summary: usize) -> Result<usize> {
+    let budget = budget(conversation);
+    let mut tokens = PER_REQUEST + summary + conversation.system.as_deref().map_or(0, message);
+    if tokens > budget {
+        bail!(
+            "The system prompt and summary alone are over the budget of {} tokens",
+            budget
+        );
+    }
+    let messages = &conversation.messages;
+    let mut first = messages.len();
+    while first > conversation.summarized {
+        let cost = message(&messages[first - 1].content);
+        if tokens + cost > budget {
+            break;
+        }
+        tokens += cost;
+        first -= 1;
+    }
+    if first == messages.len() && first > conversation.summarized {
+        bail!(
+            "The last message alone is over the budget of {} tokens",
+            budget
+        );
+    }
+    Ok(first)
+}
+
+/// What a request would send, leaving out the oldest messages that don't fit.
+pub fn fit(conversation: &Conversation) -> Result<Context<'_>> {
+    let summary = match conversation.summarized {
+        0 => None,
+        _ => conversation.summary.as_deref(),
+    };
+    let first = first_fitting(conversation, summary.map_or(0, self::summary))?;
+    let messages = &conversation.messages[first..];
+    let tokens = PER_REQUEST
+        + conversation.system.as_deref().map_or(0, message)
+        + summary.map_or(0, self::summary)
+        + messages.iter().map(|m| message(&m.content)).sum::<usize>();
+    Ok(Context {
+        system: conversation.system.as_deref(),
+        summary,
+        messages,
+        left_out: first - conversation.summarized,
+        tokens,
+        budget: budget(conversation),
+    })
+}
+
+/// The messages to fold into the summary so that the rest fits next to a summary of up to
+/// `summary` tokens. Empty when everything fits already.
+pub fn to_summarize(conversation: &Conversation, summary: usize) -> Result<std::ops::Range<usize>> {
+    let summary = (estimate(SUMMARY_INTRO) + PER_MESSAGE + summary)
+        .max(conversation.summary.as_deref().map_or(0, self::summary));
+    let first = first_fitting(conversation, summary)?;
+    Ok(conversation.summarized..first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        budget, estimate, fit, model_limit, to_summarize, PER_MESSAGE, PER_REQUEST, SUMMARY_INTRO,
+    };
+    use crate::conversation::{Conversation, Message, Speaker};
+
+    #[test]
+    fn estimates() {
+        assert_eq!(estimate(""), 0);
+        assert_eq!(estimate("Hello, world!"), 6);
+        assert_eq!(estimate("internationalization"), 5);
+        assert_eq!(estimate("2 + 2"), 3);
+        assert_eq!(estimate("日本"), 2);
+        assert_eq!(model_limit("gpt-4o-mini"), 128_000);
+        assert_eq!(model_limit("gpt-4-0613"), 8_192);
+        assert_eq!(model_limit("my-own-model"), 4_096);
+    }
+
+    /// What each message of [`conversation`] costs: "message" is two tokens, the number one.
+    const EACH: usize = 3 + PER_MESSAGE;
+
+    fn conversation(budget: usize) -> Conversation {
+        let mut conversation = Conversation::new("gpt-3.5-turbo", None);
+        for i in 0..10 {
+            conversation.messages.push(Message {
+                role: [Speaker::User, Speaker::Assistant][i % 2],
+                content: format!("message {}", i),
+            });
+        }
+        conversation.budget = Some(budget);
+        conversation
+    }
+
+    #[test]
+    fn drops_the_oldest() {
+        let mut conversation = conversation(1_000_000);
+        assert_eq!(budget(&conversation), 16_385 - 1_000);
+        let context = fit(&conversation).unwrap();
+        assert_eq!(context.messages.len(), 10);
+        assert_eq!(context.left_out, 0);
+        assert_eq!(context.tokens, PER_REQUEST + 10 * EACH);
+
+        conversation.budget = Some(PER_REQUEST + 3 * EACH + 1);
+        let context = fit(&conversation).unwrap();
+        assert_eq!(context.messages, &conversation.messages[7..]);
+        assert_eq!(context.left_out, 7);
+        assert_eq!(context.tokens, PER_REQUEST + 3 * EACH);
+
+        conversation.system = Some("Be brief.".to_string());
+        let context = fit(&conversation).unwrap();
+        assert_eq!(context.messages.len(), 2);
+
+        conversation.system = None;
+        conversation.budget = Some(PER_REQUEST + EACH - 1);
+        assert!(fit(&conversation).is_err());
+    }
+
+    #[test]
+    fn summarizes_the_oldest() {
+        // room for three messages next to a summary of three tokens
+        let summary = estimate(SUMMARY_INTRO) + PER_MESSAGE + 3;
+        let mut conversation = conversation(PER_REQUEST + summary + 3 * EACH);
+        assert_eq!(to_summarize(&conversation, 3).unwrap(), 0..7);
+
+        conversation.summary = Some("a b c".to_string());
+        conversation.summarized = 7;
+        let context = fit(&conversation).unwrap();
+        assert_eq!(context.summary, conversation.summary.as_deref());
+        assert_eq!(context.messages.len(), 3);
+        assert_eq!(context.left_out, 0);
+        assert_eq!(to_summarize(&conversation, 3).unwrap(), 7..7);
+
+        conversation.messages.push(Message {
+            role: Speaker::User,
+            content: "message 10".to_string(),
+        });
+        assert_eq!(fit(&conversation).unwrap().left_out, 1);
+        assert_eq!(to_summarize(&conversation, 3).unwrap(), 7..8);
+    }
+}
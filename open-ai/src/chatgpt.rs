@@ -1,55 +1,337 @@
-use std::io;
-
-use anyhow::Ok;
+use anyhow::bail;
 use anyhow::Result;
 
-use async_openai::Chat;
-use async_openai::Client;
+use async_openai::config::OpenAIConfig;
+use async_openai::types::ChatCompletionRequestAssistantMessageArgs;
 use async_openai::types::ChatCompletionRequestMessage;
+use async_openai::types::ChatCompletionRequestSystemMessageArgs;
+use async_openai::types::ChatCompletionRequestUserMessageArgs;
 use async_openai::types::CreateChatCompletionRequestArgs;
-use async_openai::types::Role;
-use linefeed::DefaultTerminal;
-use linefeed::Interface;
-use linefeed::ReadResult;
-
-pub async fn talk(reader: &Interface<DefaultTerminal>) -> Result<()> {
-    let client = Client::new();
-    
-    let chat = Chat::new(&client);
-    let mut messages: Vec<ChatCompletionRequestMessage>  = vec![];
-    let mut default_args = CreateChatCompletionRequestArgs::default();
-    let request_args = default_args
-        .model("gpt-3.5-turbo")
+use async_openai::Client;
+use futures::StreamExt;
+
+use crate::budget::{self, SUMMARY_INTRO};
+use crate::conversation::{Conversation, Overflow, Speaker};
+
+pub const DEFAULT_MODEL: &str = "gpt-3.5-turbo";
+
+/// The most a summary may be.
+const SUMMARY_TOKENS: u32 = 300;
+const SUMMARIZE: &str = "Summarize the conversation below in at most 150 words. Keep the facts, \
+names and decisions needed to carry it on.";
+
+/// The API client. `OPENAI_API_BASE` points it somewhere else than OpenAI, like a proxy or a mock.
+pub fn client() -> Client<OpenAIConfig> {
+    let mut config = OpenAIConfig::new();
+    if let Ok(api_base) = std::env::var("OPENAI_API_BASE") {
+        config = config.with_api_base(api_base);
+    }
+    Client::with_config(config)
+}
+
+fn request_messages(conversation: &Conversation) -> Result<Vec<ChatCompletionRequestMessage>> {
+    let context = budget::fit(conversation)?;
+    let mut messages = vec![];
+    if let Some(system) = context.system {
+        messages.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system)
+                .build()?
+                .into(),
+        );
+    }
+    if let Some(summary) = context.summary {
+        messages.push(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(format!("{}{}", SUMMARY_INTRO, summary))
+                .build()?
+                .into(),
+        );
+    }
+    for message in context.messages {
+        messages.push(match message.role {
+            Speaker::User => ChatCompletionRequestUserMessageArgs::default()
+                .content(message.content.as_str())
+                .build()?
+                .into(),
+            Speaker::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+                .content(message.content.as_str())
+                .build()?
+                .into(),
+        });
+    }
+    Ok(messages)
+}
+
+/// Asks for the next message of the conversation, handing each piece of it to `on_token` as it
+/// streams in, and returns all of it. The oldest messages are left out when they don't all fit.
+pub async fn reply(
+    client: &Client<OpenAIConfig>,
+    conversation: &Conversation,
+    mut on_token: impl FnMut(&str),
+) -> Result<String> {
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(conversation.model.as_str())
         .user("async-openai")
-        // .stream(true)
-        .n(1);
-    
-    println!("[Type 'exit' to leave conversation.]");
-    
-    while let Some(user_input) = parse_exitable(reader.read_line()) {
-        messages.push(ChatCompletionRequestMessage { role: Role::User, content: user_input, name: None });
-        let request = request_args.messages(messages.clone()).build().expect("Build failed?");
-        let response = chat.create(request).await.expect("Never returned anything?");
-        let Some(choice) = response.choices.first() else { panic!("Didn't receive a answer/choice when we assumed that would happen.") };
-        let message = choice.message.clone();
-        let role = message.role;
-        let content = message.content;
-        println!("Response: '{}'", &content);
-        messages.push(ChatCompletionRequestMessage { role, content, name: None });
-    } // run untill we should exit
-    
-    print!("Finished ChatGPT conversation.");
-    
-    Ok(())
+        .n(1)
+        .messages(request_messages(conversation)?)
+        .build()?;
+    let mut stream = client.chat().create_stream(request).await?;
+    let mut reply = String::new();
+    let mut chosen = false;
+    while let Some(chunk) = stream.next().await {
+        let Some(choice) = chunk?.choices.into_iter().find(|choice| choice.index == 0) else {
+            continue;
+        };
+        chosen = true;
+        if let Some(content) = choice.delta.content {
+            on_token(&content);
+            reply.push_str(&content);
+        }
+    }
+    if !chosen {
+        bail!("Didn't receive an answer: the response had no choices");
+    }
+    Ok(reply)
 }
 
-fn parse_exitable(result: io::Result<ReadResult>) -> Option<String> {
-    let ReadResult::Input(line) = result.ok()? else {
-        return None;
+/// In conversations that summarize, folds the messages that no longer fit into the summary.
+/// Returns how many were folded in.
+pub async fn summarize(
+    client: &Client<OpenAIConfig>,
+    conversation: &mut Conversation,
+) -> Result<usize> {
+    if conversation.overflow != Overflow::Summarize {
+        return Ok(0);
+    }
+    let range = budget::to_summarize(conversation, SUMMARY_TOKENS as usize)?;
+    if range.is_empty() {
+        return Ok(0);
+    }
+
+    let mut transcript = String::new();
+    if let Some(summary) = &conversation.summary {
+        transcript += &format!("{}{}\n\n", SUMMARY_INTRO, summary);
+    }
+    for message in &conversation.messages[range.clone()] {
+        let speaker = match message.role {
+            Speaker::User => "User",
+            Speaker::Assistant => "Assistant",
+        };
+        transcript += &format!("{}: {}\n\n", speaker, message.content);
+    }
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(conversation.model.as_str())
+        .user("async-openai")
+        .n(1)
+        .max_completion_tokens(SUMMARY_TOKENS)
+        .messages([
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(SUMMARIZE)
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(transcript)
+                .build()?
+                .into(),
+        ])
+        .build()?;
+    let response = client.chat().create(request).await?;
+    let Some(summary) = response
+        .choices
+        .into_iter()
+        .find(|choice| choice.index == 0)
+        .and_then(|choice| choice.message.content)
+    else {
+        bail!("Didn't receive a summary: the response had no choices");
     };
-    if line.contains("exit") {
-        return None;
+    conversation.summary = Some(summary);
+    conversation.summarized = range.end;
+    Ok(range.len())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use async_openai::config::OpenAIConfig;
+    use async_openai::Client;
+    use axum::extract::State;
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    use super::{reply, summarize, SUMMARY_INTRO};
+    use crate::conversation::{Conversation, Message, Overflow, Speaker};
+
+    pub(crate) type Requests = Arc<Mutex<Vec<Value>>>;
+
+    fn chunk(choices: Value) -> String {
+        let chunk = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1_700_000_000,
+            "model": "gpt-3.5-turbo",
+            "choices": choices,
+        });
+        format!("data: {}\n\n", chunk)
+    }
+
+    /// Answers "Hello there!" in three pieces, or with no choices when the last message is
+    /// "nothing", or fails when it is "fail". Requests that don't stream are answered "Summary."
+    /// in one piece. Every request body is kept.
+    pub(crate) async fn serve() -> (SocketAddr, Requests) {
+        let requests = Requests::default();
+        let app = Router::new()
+            .route(
+                "/v1/chat/completions",
+                post(|State(requests): State<Requests>, Json(body): Json<Value>| async move {
+                    requests.lock().unwrap().push(body.clone());
+                    let last = body["messages"].as_array().and_then(|m| m.last()).cloned();
+                    let last = last.as_ref().and_then(|m| m["content"].as_str());
+                    if last == Some("fail") {
+                        let error = json!({"error": {"message": "Bad request", "type": "invalid_request_error"}});
+                        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+                    }
+                    if body["stream"] != true {
+                        return Json(json!({
+                            "id": "chatcmpl-1",
+                            "object": "chat.completion",
+                            "created": 1_700_000_000,
+                            "model": "gpt-3.5-turbo",
+                            "choices": [{
+                                "index": 0,
+                                "message": {"role": "assistant", "content": "Summary."},
+                                "finish_reason": "stop",
+                            }],
+                        }))
+                        .into_response();
+                    }
+                    let events = match last {
+                        Some("nothing") => chunk(json!([])),
+                        _ => ["Hello", " there", "!"]
+                            .iter()
+                            .map(|piece| {
+                                chunk(json!([{"index": 0, "delta": {"content": piece}, "finish_reason": null}]))
+                            })
+                            .collect(),
+                    };
+                    ([(header::CONTENT_TYPE, "text/event-stream")], events + "data: [DONE]\n\n")
+                        .into_response()
+                }),
+            )
+            .with_state(requests.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (address, requests)
+    }
+
+    pub(crate) fn mock_client(address: SocketAddr) -> Client<OpenAIConfig> {
+        Client::with_config(
+            OpenAIConfig::new()
+                .with_api_key("test")
+                .with_api_base(format!("http://{}/v1", address)),
+        )
+    }
+
+    #[tokio::test]
+    async fn streams_the_reply() {
+        let (address, requests) = serve().await;
+        let client = mock_client(address);
+        let mut conversation = Conversation::new("gpt-4o-mini", Some("Be brief.".to_string()));
+        conversation.messages.push(Message {
+            role: Speaker::User,
+            content: "Hi".to_string(),
+        });
+
+        let mut tokens = vec![];
+        let answer = reply(&client, &conversation, |token| {
+            tokens.push(token.to_string())
+        })
+        .await;
+        assert_eq!(answer.unwrap(), "Hello there!");
+        assert_eq!(tokens, ["Hello", " there", "!"]);
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(request["model"], "gpt-4o-mini");
+        assert_eq!(request["stream"], true);
+        assert_eq!(
+            request["messages"],
+            json!([
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Hi"},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_instead_of_panics() {
+        let (address, _) = serve().await;
+        let client = mock_client(address);
+        for content in ["fail", "nothing"] {
+            let mut conversation = Conversation::new("gpt-3.5-turbo", None);
+            conversation.messages.push(Message {
+                role: Speaker::User,
+                content: content.to_string(),
+            });
+            assert!(
+                reply(&client, &conversation, |_| {}).await.is_err(),
+                "{}",
+                content
+            );
+        }
+    }
+
+    fn long_conversation(overflow: Overflow) -> Conversation {
+        let mut conversation = Conversation::new("gpt-3.5-turbo", None);
+        for i in 0..200 {
+            conversation.messages.push(Message {
+                role: [Speaker::User, Speaker::Assistant][i % 2],
+                content: format!("message {}", i),
+            });
+        }
+        conversation.budget = Some(1_000);
+        conversation.overflow = overflow;
+        conversation
+    }
+
+    #[tokio::test]
+    async fn leaves_out_the_oldest() {
+        let (address, requests) = serve().await;
+        let client = mock_client(address);
+        let mut conversation = long_conversation(Overflow::Drop);
+        assert_eq!(summarize(&client, &mut conversation).await.unwrap(), 0);
+        reply(&client, &conversation, |_| {}).await.unwrap();
+
+        let request = requests.lock().unwrap()[0].clone();
+        let sent = request["messages"].as_array().unwrap();
+        assert!(sent.len() < 200);
+        assert_eq!(sent.last().unwrap()["content"], "message 199");
+        assert_eq!(conversation.messages.len(), 200);
+    }
+
+    #[tokio::test]
+    async fn summarizes_the_oldest() {
+        let (address, requests) = serve().await;
+        let client = mock_client(address);
+        let mut conversation = long_conversation(Overflow::Summarize);
+        let folded = summarize(&client, &mut conversation).await.unwrap();
+        assert!(folded > 0);
+        assert_eq!(conversation.summarized, folded);
+        assert_eq!(conversation.summary.as_deref(), Some("Summary."));
+        assert_eq!(summarize(&client, &mut conversation).await.unwrap(), 0);
+        reply(&client, &conversation, |_| {}).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let transcript = requests[0]["messages"][1]["content"].as_str().unwrap();
+        assert!(transcript.starts_with("User: message 0\n\nAssistant: message 1"));
+        let sent = requests[1]["messages"].as_array().unwrap();
+        assert_eq!(sent[0]["content"], format!("{}Summary.", SUMMARY_INTRO));
+        assert_eq!(sent[1]["content"], format!("message {}", folded));
+        assert_eq!(sent.len(), 201 - folded);
     }
-    
-    Some(line)   
-}
\ No newline at end of file
+}
@@ -0,0 +1,279 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+
+use crate::conversation::Overflow;
+use crate::dalle::Options;
+
+pub const HELP: &str = "\
+Type a message to send it to the model, or a command:
+  /model [name]                 show or change the model
+  /system [prompt | off]        show, change or remove the system prompt
+  /save [name]                  save the conversation; it is then saved after every answer
+  /load <name>                  go on with a saved conversation
+  /list                         list the saved conversations
+  /rename <name> <new name>     rename a saved conversation
+  /delete <name>                delete a saved conversation
+  /image [options] <prompt>     generate images
+  /variation [options] <file>   make images like a local one
+  /edit [options] <file> <prompt>
+                                change a local image; mask=<file> marks the part to change
+                                with transparent pixels
+  /gallery [number]             list the images made so far, or open those of one entry
+  /clear                        start over, keeping the model and system prompt
+  /tokens                       show how much of the context budget is used
+  /budget [tokens | auto] [drop | summarize]
+                                show or change the budget, and what happens to the oldest
+                                messages once they don't fit
+  /help                         show this
+  /quit                         leave
+Image options go before the rest: n=<1 to 10>, size=<256x256, 512x512, 1024x1024, 1792x1024
+or 1024x1792> and format=<url or b64>, like /image n=2 size=512x512 a red fox.
+Start a message with // to send one beginning with /.";
+
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Message(String),
+    Command(Command),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Model(Option<String>),
+    /// `Some(None)` removes the system prompt.
+    System(Option<Option<String>>),
+    Save(Option<String>),
+    Load(String),
+    List,
+    Rename(String, String),
+    Delete(String),
+    Image {
+        options: Options,
+        prompt: String,
+    },
+    Variation {
+        options: Options,
+        image: PathBuf,
+    },
+    Edit {
+        options: Options,
+        image: PathBuf,
+        prompt: String,
+    },
+    /// `Some` opens the images of an entry, counting from 1.
+    Gallery(Option<usize>),
+    Clear,
+    Tokens,
+    /// `Some(None)` goes back to the model's whole context.
+    Budget {
+        tokens: Option<Option<usize>>,
+        overflow: Option<Overflow>,
+    },
+    Help,
+    Quit,
+}
+
+/// `None` for a blank line.
+pub fn parse(line: &str) -> Result<Option<Input>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if let Some(message) = line.strip_prefix("//") {
+        return Ok(Some(Input::Message(format!("/{}", message))));
+    }
+    let Some(command) = line.strip_prefix('/') else {
+        return Ok(Some(Input::Message(line.to_string())));
+    };
+    let (name, rest) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, rest)| (name, rest.trim()));
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let optional = || (!rest.is_empty()).then(|| rest.to_string());
+
+    let command = match (name, words.as_slice()) {
+        ("model", [] | [_]) => Command::Model(optional()),
+        ("system", ["off"]) => Command::System(Some(None)),
+        ("system", _) => Command::System(optional().map(Some)),
+        ("save", [] | [_]) => Command::Save(optional()),
+        ("load", [name]) => Command::Load(name.to_string()),
+        ("list", []) => Command::List,
+        ("rename", [name, new_name]) => Command::Rename(name.to_string(), new_name.to_string()),
+        ("delete", [name]) => Command::Delete(name.to_string()),
+        ("image" | "variation" | "edit", words) => image(name, words)?,
+        ("gallery", []) => Command::Gallery(None),
+        ("gallery", [number]) => match number.parse() {
+            Ok(number @ 1..) => Command::Gallery(Some(number)),
+            _ => bail!("Usage: /gallery [number]"),
+        },
+        ("clear", []) => Command::Clear,
+        ("tokens", []) => Command::Tokens,
+        ("budget", settings) if settings.len() <= 2 => {
+            let (mut tokens, mut overflow) = (None, None);
+            for setting in settings {
+                match *setting {
+                    "auto" if tokens.is_none() => tokens = Some(None),
+                    "drop" if overflow.is_none() => overflow = Some(Overflow::Drop),
+                    "summarize" if overflow.is_none() => overflow = Some(Overflow::Summarize),
+                    setting => match setting.parse() {
+                        Ok(n) if n > 0 && tokens.is_none() => tokens = Some(Some(n)),
+                        _ => bail!("Usage: /budget [tokens | auto] [drop | summarize]"),
+                    },
+                }
+            }
+            Command::Budget { tokens, overflow }
+        }
+        ("help", []) => Command::Help,
+        ("quit" | "exit", []) => Command::Quit,
+        (
+            "model" | "save" | "load" | "list" | "rename" | "delete" | "gallery" | "clear"
+            | "tokens" | "budget" | "help" | "quit" | "exit",
+            _,
+        ) => bail!("Wrong arguments for /{}; /help shows how to use it", name),
+        _ => bail!("There is no /{} command; /help lists them", name),
+    };
+    Ok(Some(Input::Command(command)))
+}
+
+fn image(name: &str, words: &[&str]) -> Result<Command> {
+    let (options, rest) = Options::parse(words)?;
+    if options.mask.is_some() && name != "edit" {
+        bail!("Only edits take a mask");
+    }
+    Ok(match (name, rest) {
+        ("image", [_, ..]) => Command::Image {
+            options,
+            prompt: rest.join(" "),
+        },
+        ("variation", [image]) => Command::Variation {
+            options,
+            image: PathBuf::from(image),
+        },
+        ("edit", [image, _, ..]) => Command::Edit {
+            options,
+            image: PathBuf::from(image),
+            prompt: rest[1..].join(" "),
+        },
+        ("image", _) => bail!("Usage: /image [options] <prompt>"),
+        ("variation", _) => bail!("Usage: /variation [options] <file>"),
+        _ => bail!("Usage: /edit [options] <file> <prompt>"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{parse, Command, Input};
+    use crate::conversation::Overflow;
+    use crate::dalle::Options;
+
+    fn command(line: &str) -> Command {
+        match parse(line) {
+            Ok(Some(Input::Command(command))) => command,
+            other => panic!("{}: {:?}", line, other),
+        }
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(parse("  ").unwrap(), None);
+        for (line, message) in [
+            ("Please exit the building", "Please exit the building"),
+            ("Draw a chat bubble", "Draw a chat bubble"),
+            ("//model is a command", "/model is a command"),
+        ] {
+            assert_eq!(
+                parse(line).unwrap(),
+                Some(Input::Message(message.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(command("/model"), Command::Model(None));
+        assert_eq!(
+            command("/model gpt-4o"),
+            Command::Model(Some("gpt-4o".to_string()))
+        );
+        assert_eq!(
+            command("/system  Answer in French. "),
+            Command::System(Some(Some("Answer in French.".to_string())))
+        );
+        assert_eq!(command("/system off"), Command::System(Some(None)));
+        assert_eq!(command("/save"), Command::Save(None));
+        assert_eq!(
+            command("/rename a b"),
+            Command::Rename("a".to_string(), "b".to_string())
+        );
+        assert_eq!(
+            command("/image a  red fox"),
+            Command::Image {
+                options: Options::default(),
+                prompt: "a red fox".to_string()
+            }
+        );
+        let Command::Edit {
+            options,
+            image,
+            prompt,
+        } = command("/edit n=2 mask=mask.png fox.png add a hat")
+        else {
+            panic!("not an edit");
+        };
+        assert_eq!(
+            (options.n, options.mask, image, prompt.as_str()),
+            (
+                2,
+                Some(PathBuf::from("mask.png")),
+                PathBuf::from("fox.png"),
+                "add a hat"
+            )
+        );
+        assert_eq!(command("/gallery 3"), Command::Gallery(Some(3)));
+        assert_eq!(
+            command("/budget"),
+            Command::Budget {
+                tokens: None,
+                overflow: None
+            }
+        );
+        assert_eq!(
+            command("/budget summarize 2000"),
+            Command::Budget {
+                tokens: Some(Some(2000)),
+                overflow: Some(Overflow::Summarize)
+            }
+        );
+        assert_eq!(
+            command("/budget auto"),
+            Command::Budget {
+                tokens: Some(None),
+                overflow: None
+            }
+        );
+        assert_eq!(command("/quit"), Command::Quit);
+    }
+
+    #[test]
+    fn mistakes() {
+        for line in [
+            "/load",
+            "/image",
+            "/image n=2",
+            "/image mask=a.png fox",
+            "/variation a.png b.png",
+            "/edit fox.png",
+            "/gallery 0",
+            "/clear all",
+            "/budget 0",
+            "/budget drop summarize",
+            "/budget lots",
+            "/save a b",
+            "/teleport",
+        ] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+}
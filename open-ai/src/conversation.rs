@@ -0,0 +1,240 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A chat, as saved between runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    pub model: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    /// The most tokens sent with a request, when it should be fewer than the model takes.
+    #[serde(default)]
+    pub budget: Option<usize>,
+    /// What happens to the oldest messages once they no longer fit.
+    #[serde(default)]
+    pub overflow: Overflow,
+    /// Stands in for the first `summarized` messages.
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub summarized: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Speaker,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Speaker {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Left out of the request, but kept in the conversation.
+    #[default]
+    Drop,
+    /// Folded into a summary that is sent instead.
+    Summarize,
+}
+
+impl Conversation {
+    pub fn new(model: &str, system: Option<String>) -> Self {
+        Self {
+            model: model.to_string(),
+            system,
+            messages: vec![],
+            budget: None,
+            overflow: Overflow::Drop,
+            summary: None,
+            summarized: 0,
+        }
+    }
+
+    /// Forgets the messages, keeping the settings.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.summary = None;
+        self.summarized = 0;
+    }
+}
+
+/// One line of the conversation list.
+#[derive(Debug)]
+pub struct Summary {
+    pub name: String,
+    pub model: String,
+    pub messages: usize,
+    pub modified: SystemTime,
+}
+
+/// Conversations kept as `<name>.json` files in a directory.
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        if name.is_empty()
+            || name.starts_with('.')
+            || name.contains(['/', '\\', ':'])
+            || name.trim() != name
+        {
+            bail!("'{}' can't be the name of a conversation", name);
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    /// The most recently changed first. Files that are not conversations are warned about and
+    /// left out, so one broken file does not hide the rest.
+    pub fn list(&self) -> Result<Vec<Summary>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e).context(format!("Couldn't list {}", self.dir.display())),
+        };
+        let mut summaries = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let conversation = match self.load(name) {
+                Ok(conversation) => conversation,
+                Err(e) => {
+                    eprintln!("Warning: {:#}", e);
+                    continue;
+                }
+            };
+            summaries.push(Summary {
+                name: name.to_string(),
+                model: conversation.model,
+                messages: conversation.messages.len(),
+                modified: fs::metadata(&path)?.modified()?,
+            });
+        }
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.modified));
+        Ok(summaries)
+    }
+
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.path(name)?.exists())
+    }
+
+    pub fn load(&self, name: &str) -> Result<Conversation> {
+        let path = self.path(name)?;
+        let text = match fs::read_to_string(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                bail!("There is no conversation called '{}'", name)
+            }
+            result => result.context(format!("Couldn't read {}", path.display()))?,
+        };
+        serde_json::from_str(&text).context(format!("{} is not a conversation", path.display()))
+    }
+
+    pub fn save(&self, name: &str, conversation: &Conversation) -> Result<()> {
+        let path = self.path(name)?;
+        fs::create_dir_all(&self.dir).context(format!("Couldn't create {}", self.dir.display()))?;
+        let json = serde_json::to_string_pretty(conversation)?;
+        fs::write(&path, json).context(format!("Couldn't write {}", path.display()))
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        let (from, to) = (self.path(name)?, self.path(new_name)?);
+        if !from.exists() {
+            bail!("There is no conversation called '{}'", name);
+        }
+        if to.exists() {
+            bail!("There is a conversation called '{}' already", new_name);
+        }
+        fs::rename(from, to).context(format!("Couldn't rename '{}'", name))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.path(name)?) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                bail!("There is no conversation called '{}'", name)
+            }
+            result => result.context(format!("Couldn't delete '{}'", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Conversation, Message, Speaker, Store};
+
+    #[test]
+    fn save_list_rename_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path().join("conversations"));
+        assert!(store.list().unwrap().is_empty());
+
+        let mut conversation = Conversation::new("gpt-4o-mini", Some("Be brief.".to_string()));
+        conversation.messages.push(Message {
+            role: Speaker::User,
+            content: "Hi".to_string(),
+        });
+        store.save("greeting", &conversation).unwrap();
+        store
+            .save("empty", &Conversation::new("gpt-3.5-turbo", None))
+            .unwrap();
+        let names: Vec<_> = store.list().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names.len(), 2);
+        assert_eq!(store.load("greeting").unwrap(), conversation);
+
+        assert!(store.rename("greeting", "empty").is_err());
+        store.rename("greeting", "hello").unwrap();
+        assert!(store.load("greeting").is_err());
+        assert_eq!(store.load("hello").unwrap(), conversation);
+        store.delete("hello").unwrap();
+        assert!(store.delete("hello").is_err());
+        let summaries = store.list().unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(
+            (summaries[0].model.as_str(), summaries[0].messages),
+            ("gpt-3.5-turbo", 0)
+        );
+
+        // a broken file is left out instead of breaking the list
+        std::fs::write(
+            dir.path().join("conversations/edited.json"),
+            "{ \"model\": ",
+        )
+        .unwrap();
+        let names: Vec<_> = store.list().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["empty"]);
+    }
+
+    #[test]
+    fn names_stay_in_the_directory() {
+        let store = Store::new("conversations");
+        for name in ["", "../secrets", ".hidden", "a/b", " padded"] {
+            assert!(store.load(name).is_err(), "{}", name);
+            assert!(
+                store.save(name, &Conversation::new("m", None)).is_err(),
+                "{}",
+                name
+            );
+        }
+    }
+}
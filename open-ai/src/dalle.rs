@@ -1,33 +1,401 @@
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
 use async_openai::{
-	types::{CreateImageRequestArgs, ImageSize, ResponseFormat},
+	config::OpenAIConfig,
+	error::OpenAIError,
+	types::{
+		CreateImageEditRequestArgs, CreateImageRequestArgs, CreateImageVariationRequestArgs,
+		DallE2ImageSize, ImageModel, ImageResponseFormat, ImageSize, ImagesResponse
+	},
 	Client
 };
-use linefeed::{DefaultTerminal, Interface, ReadResult};
+use chrono::Local;
+
+use crate::gallery::{Generation, Kind};
+
+const SIZES: [(&str, ImageSize); 5] = [
+	("256x256", ImageSize::S256x256),
+	("512x512", ImageSize::S512x512),
+	("1024x1024", ImageSize::S1024x1024),
+	("1792x1024", ImageSize::S1792x1024),
+	("1024x1792", ImageSize::S1024x1792),
+];
+
+/// How many images are asked for and what they are like.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+	pub n: u8,
+	pub size: ImageSize,
+	pub format: ImageResponseFormat,
+	/// For edits, the part of the image to change, as a PNG whose transparent pixels mark it.
+	pub mask: Option<PathBuf>,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self { n: 1, size: ImageSize::S256x256, format: ImageResponseFormat::Url, mask: None }
+	}
+}
 
-pub async fn gen_image(reader: &Interface<DefaultTerminal>) -> Result<()> {
-	println!("Give an image prompt.");
-	let input: String = match reader.read_line()? {
-		ReadResult::Input(string) => string,
-		_ => panic!("Invalid input!")
-	};
+impl Options {
+	/// Reads the `key=value` words at the start, and returns the words after them.
+	pub fn parse<'a, 'w>(words: &'w [&'a str]) -> Result<(Self, &'w [&'a str])> {
+		let mut options = Options::default();
+		let mut rest = words;
+		while let Some((option, value)) = rest.first().and_then(|word| word.split_once('=')) {
+			match option {
+				"n" => match value.parse() {
+					Ok(n @ 1..=10) => options.n = n,
+					_ => bail!("n is how many images to make, from 1 to 10"),
+				},
+				"size" => match SIZES.iter().find(|(name, _)| *name == value) {
+					Some(&(_, size)) => options.size = size,
+					None => bail!(
+						"The size is one of {}",
+						SIZES.map(|(name, _)| name).join(", ")
+					),
+				},
+				"format" => match value {
+					"url" => options.format = ImageResponseFormat::Url,
+					"b64" | "base64" => options.format = ImageResponseFormat::B64Json,
+					_ => bail!("The format is url or b64"),
+				},
+				"mask" => options.mask = Some(PathBuf::from(value)),
+				// the prompt starts here
+				_ => break,
+			}
+			rest = &rest[1..];
+		}
+		if options.model() == ImageModel::DallE3 && options.n > 1 {
+			bail!("Images of {} are made one at a time", options.size_name());
+		}
+		Ok((options, rest))
+	}
+
+	/// Only DALL·E 3 makes the wide and tall sizes, and only one image per request.
+	fn model(&self) -> ImageModel {
+		match self.size {
+			ImageSize::S1792x1024 | ImageSize::S1024x1792 => ImageModel::DallE3,
+			_ => ImageModel::DallE2,
+		}
+	}
+
+	fn size_name(&self) -> String {
+		SIZES.iter().find(|(_, size)| *size == self.size).map_or("", |(name, _)| name).to_string()
+	}
+
+	/// Variations and edits come in fewer sizes.
+	fn small_size(&self) -> Result<DallE2ImageSize> {
+		Ok(match self.size {
+			ImageSize::S256x256 => DallE2ImageSize::S256x256,
+			ImageSize::S512x512 => DallE2ImageSize::S512x512,
+			ImageSize::S1024x1024 => DallE2ImageSize::S1024x1024,
+			_ => bail!("Variations and edits are 256x256, 512x512 or 1024x1024"),
+		})
+	}
+}
 
-	let client = Client::new();
+/// The API's own message, rather than its error type and code.
+fn readable(error: OpenAIError) -> anyhow::Error {
+	match error {
+		OpenAIError::ApiError(error) => anyhow!("The API refused: {}", error.message),
+		error => error.into(),
+	}
+}
+
+fn check_prompt(prompt: &str) -> Result<()> {
+	if prompt.trim().is_empty() {
+		bail!("The prompt is empty");
+	}
+	Ok(())
+}
+
+fn check_image(path: &Path) -> Result<()> {
+	if !path.is_file() {
+		bail!("There is no image at {}", path.display());
+	}
+	Ok(())
+}
+
+async fn save(response: ImagesResponse, dir: &Path) -> Result<Vec<PathBuf>> {
+	if response.data.is_empty() {
+		bail!("The API didn't send any images");
+	}
+	Ok(response.save(dir).await?)
+}
+
+/// Makes images of a prompt, saved in `dir`.
+pub async fn generate(
+	client: &Client<OpenAIConfig>,
+	dir: &Path,
+	options: &Options,
+	prompt: &str,
+) -> Result<Generation> {
+	check_prompt(prompt)?;
 	let request = CreateImageRequestArgs::default()
-		.prompt(input)
-		.n(1)
-		.response_format(ResponseFormat::Url)
-		.size(ImageSize::S256x256)
+		.prompt(prompt)
+		.model(options.model())
+		.n(options.n)
+		.response_format(options.format)
+		.size(options.size)
+		.user("async-openai")
+		.build()?;
+
+	let response = client.images().create(request).await.map_err(readable)?;
+	Ok(Generation {
+		time: Local::now(),
+		kind: Kind::Image,
+		prompt: Some(prompt.to_string()),
+		source: None,
+		size: options.size_name(),
+		files: save(response, dir).await?,
+	})
+}
+
+/// Makes images like a local one, saved in `dir`.
+pub async fn vary(
+	client: &Client<OpenAIConfig>,
+	dir: &Path,
+	options: &Options,
+	image: &Path,
+) -> Result<Generation> {
+	check_image(image)?;
+	let request = CreateImageVariationRequestArgs::default()
+		.image(image)
+		.n(options.n)
+		.response_format(options.format)
+		.size(options.small_size()?)
 		.user("async-openai")
 		.build()?;
 
-	let response = client.images().create(request).await?;
-	let paths = response.save("./data").await?;
+	let response = client.images().create_variation(request).await.map_err(readable)?;
+	Ok(Generation {
+		time: Local::now(),
+		kind: Kind::Variation,
+		prompt: None,
+		source: Some(image.to_path_buf()),
+		size: options.size_name(),
+		files: save(response, dir).await?,
+	})
+}
 
-	for path in &paths {
-		println!("Image file path: {}", path.display());
-		open::that(path).ok();
+/// Changes a local image as the prompt says, saving the results in `dir`.
+pub async fn edit(
+	client: &Client<OpenAIConfig>,
+	dir: &Path,
+	options: &Options,
+	image: &Path,
+	prompt: &str,
+) -> Result<Generation> {
+	check_prompt(prompt)?;
+	check_image(image)?;
+	let mut request = CreateImageEditRequestArgs::default();
+	request
+		.image(image)
+		.prompt(prompt)
+		.n(options.n)
+		.response_format(options.format)
+		.size(options.small_size()?)
+		.user("async-openai");
+	if let Some(mask) = &options.mask {
+		check_image(mask)?;
+		request.mask(mask.as_path());
 	}
 
-	Ok(())
+	let response = client.images().create_edit(request.build()?).await.map_err(readable)?;
+	Ok(Generation {
+		time: Local::now(),
+		kind: Kind::Edit,
+		prompt: Some(prompt.to_string()),
+		source: Some(image.to_path_buf()),
+		size: options.size_name(),
+		files: save(response, dir).await?,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::SocketAddr;
+	use std::path::Path;
+	use std::sync::{Arc, Mutex};
+
+	use async_openai::types::{ImageModel, ImageResponseFormat, ImageSize};
+	use axum::extract::{Multipart, State};
+	use axum::http::StatusCode;
+	use axum::response::IntoResponse;
+	use axum::routing::{get, post};
+	use axum::{Json, Router};
+	use serde_json::{json, Map, Value};
+
+	use super::{edit, generate, vary, Options};
+	use crate::chatgpt::tests::mock_client;
+	use crate::gallery::Kind;
+
+	/// The first bytes of every PNG.
+	const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+	const PNG_B64: &str = "iVBORw0KGgo=";
+
+	type Requests = Arc<Mutex<Vec<Value>>>;
+
+	fn images(address: SocketAddr, request: &Value) -> impl IntoResponse {
+		if request["prompt"] == "forbidden" {
+			let error = json!({"error": {
+				"message": "Your request was rejected by the safety system.",
+				"type": "invalid_request_error",
+				"code": "content_policy_violation",
+			}});
+			return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+		}
+		let n = match &request["n"] {
+			Value::String(n) => n.parse().unwrap(),
+			n => n.as_u64().unwrap_or(1),
+		};
+		let data: Vec<Value> = (0..n)
+			.map(|i| match request["response_format"].as_str() {
+				Some("b64_json") => json!({"b64_json": PNG_B64}),
+				_ => json!({"url": format!("http://{}/files/{}.png", address, i)}),
+			})
+			.collect();
+		Json(json!({"created": 1_700_000_000, "data": data})).into_response()
+	}
+
+	/// Makes as many images as asked for, refusing the prompt "forbidden". Every request is kept,
+	/// uploads as their file names.
+	async fn serve() -> (SocketAddr, Requests) {
+		let requests = Requests::default();
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let address = listener.local_addr().unwrap();
+		let multipart = move |State(requests): State<Requests>, mut form: Multipart| async move {
+			let mut request = Map::new();
+			while let Some(field) = form.next_field().await.unwrap() {
+				let name = field.name().unwrap().to_string();
+				let value = match field.file_name() {
+					Some(file_name) => file_name.to_string(),
+					None => field.text().await.unwrap(),
+				};
+				request.insert(name, Value::String(value));
+			}
+			let request = Value::Object(request);
+			requests.lock().unwrap().push(request.clone());
+			images(address, &request)
+		};
+		let app = Router::new()
+			.route(
+				"/v1/images/generations",
+				post(move |State(requests): State<Requests>, Json(request): Json<Value>| async move {
+					requests.lock().unwrap().push(request.clone());
+					images(address, &request)
+				}),
+			)
+			.route("/v1/images/variations", post(multipart))
+			.route("/v1/images/edits", post(multipart))
+			.route("/files/:name", get(|| async { PNG }))
+			.with_state(requests.clone());
+		tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+		(address, requests)
+	}
+
+	#[test]
+	fn options() {
+		let words = ["n=3", "size=512x512", "format=b64", "a", "size=large", "fox"];
+		let (options, rest) = Options::parse(&words).unwrap();
+		assert_eq!(options.n, 3);
+		assert_eq!(options.size, ImageSize::S512x512);
+		assert_eq!(options.format, ImageResponseFormat::B64Json);
+		assert_eq!(rest, ["a", "size=large", "fox"]);
+		assert_eq!(Options::parse(&[]).unwrap().0, Options::default());
+		let (wide, _) = Options::parse(&["size=1792x1024", "fox"]).unwrap();
+		assert_eq!(wide.model(), ImageModel::DallE3);
+		assert_eq!(options.model(), ImageModel::DallE2);
+		for wrong in ["n=0", "n=11", "size=300x300", "format=gif"] {
+			assert!(Options::parse(&[wrong, "fox"]).is_err(), "{}", wrong);
+		}
+		assert!(Options::parse(&["n=2", "size=1024x1792", "fox"]).is_err());
+	}
+
+	#[tokio::test]
+	async fn generates() {
+		let (address, requests) = serve().await;
+		let client = mock_client(address);
+		let dir = tempfile::tempdir().unwrap();
+
+		let (options, _) = Options::parse(&["n=2", "size=512x512"]).unwrap();
+		let generation = generate(&client, dir.path(), &options, "a red fox").await.unwrap();
+		assert_eq!(generation.kind, Kind::Image);
+		assert_eq!(generation.size, "512x512");
+		assert_eq!(generation.files.len(), 2);
+		for file in &generation.files {
+			assert!(file.starts_with(dir.path()));
+			assert_eq!(std::fs::read(file).unwrap(), PNG);
+		}
+		let request = requests.lock().unwrap()[0].clone();
+		assert_eq!(
+			(&request["n"], &request["size"], &request["response_format"]),
+			(&json!(2), &json!("512x512"), &json!("url"))
+		);
+		assert_eq!(request["model"], "dall-e-2");
+
+		let (options, _) = Options::parse(&["size=1024x1792"]).unwrap();
+		generate(&client, dir.path(), &options, "a tall fox").await.unwrap();
+		let request = requests.lock().unwrap()[1].clone();
+		assert_eq!(
+			(&request["model"], &request["size"]),
+			(&json!("dall-e-3"), &json!("1024x1792"))
+		);
+
+		let (options, _) = Options::parse(&["format=b64"]).unwrap();
+		let generation = generate(&client, dir.path(), &options, "a red fox").await.unwrap();
+		assert_eq!(std::fs::read(&generation.files[0]).unwrap(), PNG);
+	}
+
+	#[tokio::test]
+	async fn varies_and_edits() {
+		let (address, requests) = serve().await;
+		let client = mock_client(address);
+		let dir = tempfile::tempdir().unwrap();
+		let fox = dir.path().join("fox.png");
+		std::fs::write(&fox, PNG).unwrap();
+
+		let (options, _) = Options::parse(&["n=3"]).unwrap();
+		let generation = vary(&client, dir.path(), &options, &fox).await.unwrap();
+		assert_eq!((generation.kind, generation.files.len()), (Kind::Variation, 3));
+		assert_eq!(generation.source.as_deref(), Some(fox.as_path()));
+
+		let (options, _) = Options::parse(&[&format!("mask={}", fox.display())]).unwrap();
+		let generation = edit(&client, dir.path(), &options, &fox, "add a hat").await.unwrap();
+		assert_eq!(generation.prompt.as_deref(), Some("add a hat"));
+
+		let requests = requests.lock().unwrap();
+		assert_eq!(requests[0]["image"], "fox.png");
+		assert_eq!(requests[0]["n"], "3");
+		assert_eq!(
+			(&requests[1]["prompt"], &requests[1]["mask"]),
+			(&json!("add a hat"), &json!("fox.png"))
+		);
+	}
+
+	#[tokio::test]
+	async fn readable_errors() {
+		let (address, requests) = serve().await;
+		let client = mock_client(address);
+		let dir = tempfile::tempdir().unwrap();
+		let options = Options::default();
+
+		let error = generate(&client, dir.path(), &options, "  ").await.unwrap_err();
+		assert_eq!(error.to_string(), "The prompt is empty");
+		let error = generate(&client, dir.path(), &options, "forbidden").await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"The API refused: Your request was rejected by the safety system."
+		);
+		let missing = Path::new("missing.png");
+		let error = vary(&client, dir.path(), &options, missing).await.unwrap_err();
+		assert_eq!(error.to_string(), "There is no image at missing.png");
+		let (large, _) = Options::parse(&["size=1792x1024"]).unwrap();
+		let fox = dir.path().join("fox.png");
+		std::fs::write(&fox, PNG).unwrap();
+		assert!(vary(&client, dir.path(), &large, &fox).await.is_err());
+		assert_eq!(requests.lock().unwrap().len(), 1);
+	}
 }
@@ -0,0 +1,130 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// One request for images and what came of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Generation {
+    pub time: DateTime<Local>,
+    pub kind: Kind,
+    /// What was asked for; variations have none.
+    pub prompt: Option<String>,
+    /// The image that was varied or edited.
+    pub source: Option<PathBuf>,
+    pub size: String,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Image,
+    Variation,
+    Edit,
+}
+
+impl Generation {
+    /// What was asked for, in a line.
+    pub fn describe(&self) -> String {
+        let kind = match self.kind {
+            Kind::Image => "image",
+            Kind::Variation => "variation",
+            Kind::Edit => "edit",
+        };
+        let mut line = format!(
+            "{}  {} {}{}",
+            self.time.format("%Y-%m-%d %H:%M"),
+            self.files.len(),
+            kind,
+            if self.files.len() == 1 { "" } else { "s" },
+        );
+        if let Some(source) = &self.source {
+            line += &format!(" of {}", source.display());
+        }
+        line += &format!(", {}", self.size);
+        if let Some(prompt) = &self.prompt {
+            line += &format!(": {}", prompt);
+        }
+        line
+    }
+}
+
+/// Every generation so far, oldest first, in a JSON file.
+pub struct Gallery {
+    index: PathBuf,
+}
+
+impl Gallery {
+    pub fn new(index: impl Into<PathBuf>) -> Self {
+        Self {
+            index: index.into(),
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<Generation>> {
+        let text = match fs::read_to_string(&self.index) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            result => result.context(format!("Couldn't read {}", self.index.display()))?,
+        };
+        serde_json::from_str(&text)
+            .context(format!("{} is not a gallery index", self.index.display()))
+    }
+
+    pub fn add(&self, generation: Generation) -> Result<()> {
+        let mut generations = self.load()?;
+        generations.push(generation);
+        if let Some(dir) = self.index.parent() {
+            fs::create_dir_all(dir).context(format!("Couldn't create {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(&generations)?;
+        fs::write(&self.index, json).context(format!("Couldn't write {}", self.index.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{Local, TimeZone};
+
+    use super::{Gallery, Generation, Kind};
+
+    #[test]
+    fn keeps_every_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let gallery = Gallery::new(dir.path().join("data").join("gallery.json"));
+        assert!(gallery.load().unwrap().is_empty());
+
+        let fox = Generation {
+            time: Local.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+            kind: Kind::Image,
+            prompt: Some("a red fox".to_string()),
+            source: None,
+            size: "512x512".to_string(),
+            files: vec![PathBuf::from("data/a.png"), PathBuf::from("data/b.png")],
+        };
+        let variation = Generation {
+            kind: Kind::Variation,
+            prompt: None,
+            source: Some(PathBuf::from("data/a.png")),
+            files: vec![PathBuf::from("data/c.png")],
+            ..fox.clone()
+        };
+        gallery.add(fox.clone()).unwrap();
+        gallery.add(variation.clone()).unwrap();
+        assert_eq!(gallery.load().unwrap(), [fox.clone(), variation.clone()]);
+
+        assert_eq!(
+            fox.describe(),
+            "2024-05-01 12:30  2 images, 512x512: a red fox"
+        );
+        assert_eq!(
+            variation.describe(),
+            "2024-05-01 12:30  1 variation of data/a.png, 512x512"
+        );
+    }
+}
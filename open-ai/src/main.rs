@@ -1,20 +1,52 @@
+mod budget;
 mod chatgpt;
+mod command;
+mod conversation;
 mod dalle;
+mod gallery;
 
-use anyhow::Result;
-use linefeed::{DefaultTerminal, Interface, ReadResult};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use async_openai::{config::OpenAIConfig, Client};
+use command::{Command, Input};
+use conversation::{Conversation, Message, Overflow, Speaker, Store};
+use gallery::{Gallery, Generation};
+use linefeed::{Interface, ReadResult};
+
+/// Where images are saved.
+const IMAGES: &str = "./data";
+/// What was asked for and which images it made.
+const GALLERY: &str = "./data/gallery.json";
+/// Where conversations are saved, next to the generated images.
+const CONVERSATIONS: &str = "./data/conversations";
 
 #[tokio::main]
 async fn main() -> Result<()> {
 	let reader = Interface::new("my-application")?;
 	reader.set_prompt("> ")?;
+	let mut session = Session {
+		client: chatgpt::client(),
+		store: Store::new(CONVERSATIONS),
+		gallery: Gallery::new(GALLERY),
+		name: None,
+		conversation: Conversation::new(chatgpt::DEFAULT_MODEL, None),
+	};
 
-	while let Some(c) = ask_type(&reader) {
-		println!();
-		match c {
-			AIType::DallE => dalle::gen_image(&reader).await,
-			AIType::ChatGPT => chatgpt::talk(&reader).await
-		}?;
+	println!("[Talking to {}. Type /help for the commands.]", chatgpt::DEFAULT_MODEL);
+	while let ReadResult::Input(line) = reader.read_line()? {
+		reader.add_history_unique(line.clone());
+		let result = match command::parse(&line) {
+			Ok(None) => Ok(()),
+			Ok(Some(Input::Message(message))) => session.send(message).await,
+			Ok(Some(Input::Command(Command::Quit))) => break,
+			Ok(Some(Input::Command(command))) => session.run(command).await,
+			Err(e) => Err(e),
+		};
+		if let Err(e) = result {
+			eprintln!("Error: {:#}", e);
+		}
 	} // runs untill we exit
 
 	println!("Finished using OpenAI tool.");
@@ -22,30 +54,215 @@ async fn main() -> Result<()> {
 	Ok(())
 }
 
-enum AIType {
-	DallE,
-	ChatGPT
+struct Session {
+	client: Client<OpenAIConfig>,
+	store: Store,
+	gallery: Gallery,
+	/// Once the conversation has a name, it is saved under it after every change.
+	name: Option<String>,
+	conversation: Conversation,
 }
 
-// impl FromStr for Option<AIType> {
-//     type Err = ();
+impl Session {
+	async fn send(&mut self, content: String) -> Result<()> {
+		self.conversation.messages.push(Message { role: Speaker::User, content });
+		match self.answer().await {
+			Ok(content) => {
+				self.conversation.messages.push(Message { role: Speaker::Assistant, content });
+				self.autosave()
+			}
+			Err(e) => {
+				// unanswered, so it is not part of the conversation; it can be asked again
+				self.conversation.messages.pop();
+				Err(e)
+			}
+		}
+	}
 
-//     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-//         todo!()
-//     }
-// }
+	async fn answer(&mut self) -> Result<String> {
+		let folded = chatgpt::summarize(&self.client, &mut self.conversation).await?;
+		if folded > 0 {
+			println!("[Summarized the {} oldest messages to stay within the budget.]", folded);
+		}
+		let left_out = budget::fit(&self.conversation)?.left_out;
+		if left_out > 0 {
+			println!("[Leaving out the {} oldest messages to stay within the budget.]", left_out);
+		}
 
-fn ask_type(reader: &Interface<DefaultTerminal>) -> Option<AIType> {
-	let ReadResult::Input(user_input) = reader.read_line().ok()? else {
-		return None;
-	};
+		print!("Response: ");
+		let answer = chatgpt::reply(&self.client, &self.conversation, |token| {
+			print!("{}", token);
+			let _ = io::stdout().flush();
+		})
+		.await;
+		println!();
+		answer
+	}
+
+	fn autosave(&self) -> Result<()> {
+		match &self.name {
+			Some(name) => self.store.save(name, &self.conversation),
+			None => Ok(()),
+		}
+	}
 
-	if user_input.contains("chat") {
-		return Some(AIType::ChatGPT);
+	/// Keeps the images in the gallery, and opens them.
+	fn show(&self, generation: Generation) -> Result<()> {
+		open_all(&generation.files);
+		self.gallery.add(generation)
 	}
-	if user_input.contains("dall") {
-		return Some(AIType::DallE);
+
+	async fn run(&mut self, command: Command) -> Result<()> {
+		match command {
+			Command::Model(None) => println!(
+				"Model: {} (takes {} tokens)",
+				self.conversation.model,
+				budget::model_limit(&self.conversation.model)
+			),
+			Command::Model(Some(model)) => {
+				self.conversation.model = model;
+				self.autosave()?;
+			}
+			Command::System(None) => match &self.conversation.system {
+				Some(system) => println!("System prompt: {}", system),
+				None => println!("No system prompt."),
+			},
+			Command::System(Some(system)) => {
+				self.conversation.system = system;
+				self.autosave()?;
+			}
+			Command::Save(name) => {
+				let Some(name) = name.or_else(|| self.name.clone()) else {
+					bail!("The conversation has no name yet: /save <name>");
+				};
+				if self.name.as_ref() != Some(&name) && self.store.exists(&name)? {
+					bail!("There is a conversation called '{}' already", name);
+				}
+				self.store.save(&name, &self.conversation)?;
+				println!("[Saved as '{}'.]", name);
+				self.name = Some(name);
+			}
+			Command::Load(name) => {
+				self.conversation = self.store.load(&name)?;
+				println!(
+					"[Going on with '{}': {}, {} messages.]",
+					name,
+					self.conversation.model,
+					self.conversation.messages.len()
+				);
+				self.name = Some(name);
+			}
+			Command::List => list(&self.store)?,
+			Command::Rename(name, new_name) => {
+				self.store.rename(&name, &new_name)?;
+				if self.name.as_ref() == Some(&name) {
+					self.name = Some(new_name);
+				}
+			}
+			Command::Delete(name) => {
+				self.store.delete(&name)?;
+				if self.name.as_ref() == Some(&name) {
+					self.name = None;
+				}
+			}
+			Command::Image { options, prompt } => {
+				let generation =
+					dalle::generate(&self.client, Path::new(IMAGES), &options, &prompt).await?;
+				self.show(generation)?;
+			}
+			Command::Variation { options, image } => {
+				let generation =
+					dalle::vary(&self.client, Path::new(IMAGES), &options, &image).await?;
+				self.show(generation)?;
+			}
+			Command::Edit { options, image, prompt } => {
+				let generation =
+					dalle::edit(&self.client, Path::new(IMAGES), &options, &image, &prompt).await?;
+				self.show(generation)?;
+			}
+			Command::Gallery(None) => {
+				let generations = self.gallery.load()?;
+				if generations.is_empty() {
+					println!("No images yet.");
+				}
+				for (i, generation) in generations.iter().enumerate() {
+					println!("{:>3}. {}", i + 1, generation.describe());
+				}
+			}
+			Command::Gallery(Some(number)) => {
+				let generations = self.gallery.load()?;
+				let Some(generation) = generations.get(number - 1) else {
+					bail!("The gallery has {} entries", generations.len());
+				};
+				println!("{}", generation.describe());
+				open_all(&generation.files);
+			}
+			Command::Clear => {
+				// a saved conversation stays as it was
+				self.conversation.clear();
+				self.name = None;
+			}
+			Command::Tokens => {
+				let context = budget::fit(&self.conversation)?;
+				println!(
+					"{} of {} tokens, {} messages sent",
+					context.tokens,
+					context.budget,
+					context.messages.len()
+				);
+				if self.conversation.summarized > 0 {
+					println!("{} older messages summarized", self.conversation.summarized);
+				}
+				if context.left_out > 0 {
+					println!("{} older messages left out", context.left_out);
+				}
+			}
+			Command::Budget { tokens, overflow } => {
+				if let Some(tokens) = tokens {
+					self.conversation.budget = tokens;
+				}
+				if let Some(overflow) = overflow {
+					self.conversation.overflow = overflow;
+				}
+				self.autosave()?;
+				let overflow = match self.conversation.overflow {
+					Overflow::Drop => "left out",
+					Overflow::Summarize => "summarized",
+				};
+				println!(
+					"Budget: {} tokens; older messages are {} once they don't fit",
+					budget::budget(&self.conversation),
+					overflow
+				);
+			}
+			Command::Help => println!("{}", command::HELP),
+			Command::Quit => unreachable!("the session ends before"),
+		}
+		Ok(())
 	}
+}
 
-	None
+fn open_all(files: &[PathBuf]) {
+	for path in files {
+		if path.is_file() {
+			println!("Image file path: {}", path.display());
+			open::that(path).ok();
+		} else {
+			println!("Image file path: {} (gone)", path.display());
+		}
+	}
+}
+
+fn list(store: &Store) -> Result<()> {
+	let summaries = store.list()?;
+	if summaries.is_empty() {
+		println!("No conversations yet.");
+	}
+	for summary in summaries {
+		println!(
+			"{}  ({}, {} messages)",
+			summary.name, summary.model, summary.messages
+		);
+	}
+	Ok(())
 }
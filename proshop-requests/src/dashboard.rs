@@ -0,0 +1,411 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{BarChart, Block, Borders, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::{Frame, Terminal};
+use tokio::sync::mpsc::{self, Receiver};
+
+use crate::monitor::{Outcome, Sample};
+use crate::stats::{format_duration, format_latency, TargetStats};
+use crate::Recorder;
+
+/// Failures kept in the log.
+const LOGGED: usize = 200;
+/// Targets shown at once, so a long list leaves room for the graphs and the log.
+const LISTED: usize = 8;
+
+const HELP: &str = "←→/tab target  ↑↓ scroll log  q quit";
+
+/// Shows the dashboard until quit, restoring the terminal however it ends.
+pub async fn run(recorder: &mut Recorder, mut samples: Receiver<Sample>) -> io::Result<()> {
+    let _screen = Screen::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    // crossterm reads events blocking, so on a thread of their own
+    let (sender, mut events) = mpsc::channel(16);
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if sender.blocking_send(event).is_err() {
+                return;
+            }
+        }
+    });
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    let mut app = App::default();
+    loop {
+        terminal.draw(|frame| app.draw(frame, &recorder.stats, Local::now()))?;
+        tokio::select! {
+            Some(sample) = samples.recv() => {
+                if let Err(e) = recorder.record(&sample) {
+                    app.status = format!("Could not export the sample: {}", e);
+                }
+                app.sample(sample);
+            }
+            Some(event) = events.recv() => {
+                if let Event::Key(key) = event {
+                    if app.key(key, recorder.stats.len()) {
+                        return Ok(());
+                    }
+                }
+            }
+            // the durations shown keep counting between samples
+            _ = tick.tick() => {}
+        }
+    }
+}
+
+/// The dashboard owns the terminal while this lives: keys come in unechoed and the shell's
+/// scrollback is hidden. The shell gets both back on drop, also when `run` unwinds.
+struct Screen;
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        // made before the alternate screen, so raw mode is undone if entering it fails
+        let screen = Self;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(screen)
+    }
+}
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+#[derive(Debug, Default)]
+struct App {
+    /// The target shown in the graphs.
+    selected: usize,
+    /// Newest first.
+    failures: VecDeque<Sample>,
+    /// Lines scrolled down in the failure log.
+    log_scroll: usize,
+    status: String,
+}
+
+impl App {
+    fn sample(&mut self, sample: Sample) {
+        if sample.is_up() {
+            return;
+        }
+        self.failures.push_front(sample);
+        self.failures.truncate(LOGGED);
+        // keep the lines being read in place while new ones come in on top
+        if self.log_scroll > 0 {
+            self.log_scroll = (self.log_scroll + 1).min(self.failures.len() - 1);
+        }
+    }
+
+    /// Returns whether to quit.
+    fn key(&mut self, key: KeyEvent, targets: usize) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Right | KeyCode::Tab | KeyCode::Char('l') => {
+                self.selected = (self.selected + 1) % targets.max(1)
+            }
+            KeyCode::Left | KeyCode::BackTab | KeyCode::Char('h') => {
+                self.selected = (self.selected + targets.max(1) - 1) % targets.max(1)
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.log_scroll = self.log_scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.log_scroll = (self.log_scroll + 1).min(self.failures.len().saturating_sub(1))
+            }
+            KeyCode::Home => self.log_scroll = 0,
+            _ => {}
+        }
+        false
+    }
+
+    fn draw(&self, frame: &mut Frame, stats: &[TargetStats], now: DateTime<Local>) {
+        let rows = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Length(stats.len().min(LISTED) as u16 + 3),
+                Constraint::Length(10),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ],
+        )
+        .split(frame.size());
+        let graphs = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Percentage(65), Constraint::Percentage(35)],
+        )
+        .split(rows[1]);
+
+        self.draw_targets(frame, rows[0], stats, now);
+        if let Some(target) = stats.get(self.selected) {
+            draw_latency(frame, graphs[0], target);
+            draw_statuses(frame, graphs[1], target);
+        }
+        self.draw_failures(frame, rows[2]);
+        let footer = match self.status.is_empty() {
+            true => HELP.to_string(),
+            false => format!("{}  |  {}", self.status, HELP),
+        };
+        frame.render_widget(
+            Paragraph::new(footer).style(Style::default().fg(Color::DarkGray)),
+            rows[3],
+        );
+    }
+
+    fn draw_targets(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        stats: &[TargetStats],
+        now: DateTime<Local>,
+    ) {
+        let rows = stats.iter().enumerate().map(|(i, target)| {
+            let state = match (target.requests, target.is_down()) {
+                (0, _) => Span::raw("..."),
+                (_, true) => Span::styled("DOWN", Style::default().fg(Color::Red)),
+                (_, false) => Span::styled("UP", Style::default().fg(Color::Green)),
+            };
+            let lasted = target
+                .state_since()
+                .map(|since| format_duration((now - since).to_std().unwrap_or_default()))
+                .unwrap_or_default();
+            let uptime = target
+                .uptime()
+                .map_or("-".to_string(), |uptime| format!("{:.2}%", uptime));
+            let row = Row::new(vec![
+                Line::raw(target.target.clone()),
+                Line::from(state),
+                Line::raw(lasted),
+                Line::raw(uptime),
+                Line::raw(format_latency(target.percentile(50.0))),
+                Line::raw(format_latency(target.percentile(95.0))),
+                Line::raw(format_latency(target.percentile(99.0))),
+                Line::raw(target.requests.to_string()),
+            ]);
+            match i == self.selected {
+                true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+                false => row,
+            }
+        });
+        let widths = [
+            Constraint::Min(20),
+            Constraint::Length(5),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new([
+                    "Target", "State", "For", "Uptime", "p50", "p95", "p99", "Requests",
+                ])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().borders(Borders::ALL).title("Targets"));
+        // scrolls the selected target into view when not all fit
+        let mut state = TableState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn draw_failures(&self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = self
+            .failures
+            .iter()
+            .skip(self.log_scroll)
+            .map(|sample| {
+                let reason = match &sample.outcome {
+                    Outcome::Status(status) => format!("HTTP {}", status),
+                    Outcome::Error(e) => e.clone(),
+                };
+                Line::from(vec![
+                    Span::styled(
+                        sample.time.format("%Y-%m-%d %H:%M:%S ").to_string(),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(format!("{}  ", sample.target)),
+                    Span::styled(reason, Style::default().fg(Color::Red)),
+                ])
+            })
+            .collect();
+        let title = format!("Failures ({})", self.failures.len());
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+}
+
+/// Latency of the latest samples that fit, failed requests as gaps.
+fn draw_latency(frame: &mut Frame, area: Rect, target: &TargetStats) {
+    let width = area.width.saturating_sub(2) as usize;
+    let latencies: Vec<u64> = target
+        .recent
+        .iter()
+        .skip(target.recent.len().saturating_sub(width))
+        .map(|sample| match sample.outcome {
+            Outcome::Status(_) => sample.latency.as_millis() as u64,
+            Outcome::Error(_) => 0,
+        })
+        .collect();
+    let max = latencies.iter().copied().max().unwrap_or_default();
+    let title = format!("Latency of {} (max {}ms)", target.target, max);
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .data(&latencies)
+        .style(Style::default().fg(Color::Cyan));
+    frame.render_widget(sparkline, area);
+}
+
+fn draw_statuses(frame: &mut Frame, area: Rect, target: &TargetStats) {
+    let mut labels: Vec<(String, u64)> = target
+        .statuses
+        .iter()
+        .map(|(status, count)| (status.to_string(), *count))
+        .collect();
+    if target.errors > 0 {
+        labels.push(("none".to_string(), target.errors));
+    }
+    let data: Vec<(&str, u64)> = labels
+        .iter()
+        .map(|(l, count)| (l.as_str(), *count))
+        .collect();
+    let chart = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title("Responses"))
+        .data(data.as_slice())
+        .bar_width(5)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Yellow))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+    frame.render_widget(chart, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Local, TimeZone};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::App;
+    use crate::monitor::{Outcome, Sample};
+    use crate::stats::TargetStats;
+
+    fn at(secs: i64) -> DateTime<Local> {
+        Local.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn render(app: &App, stats: &[TargetStats], now: DateTime<Local>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| app.draw(frame, stats, now)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn shows_state_statuses_and_failures() {
+        let mut app = App::default();
+        let mut stats = vec![
+            TargetStats::new("https://www.proshop.dk/"),
+            TargetStats::new("https://example.com/"),
+        ];
+        for (secs, outcome) in [
+            (0, Outcome::Status(200)),
+            (5, Outcome::Status(200)),
+            (10, Outcome::Status(503)),
+            (15, Outcome::Error("Timed out".to_string())),
+        ] {
+            let sample = Sample {
+                target: "https://www.proshop.dk/".to_string(),
+                time: at(secs),
+                latency: Duration::from_millis(100 + secs as u64),
+                outcome,
+            };
+            stats[0].record(&sample);
+            app.sample(sample);
+        }
+
+        let screen = render(&app, &stats, at(70));
+        assert!(screen.contains("https://www.proshop.dk/"), "{}", screen);
+        assert!(screen.contains("DOWN  1m 0s"), "{}", screen);
+        assert!(screen.contains("50.00%"), "{}", screen);
+        assert!(
+            screen.contains("Latency of https://www.proshop.dk/ (max 110ms)"),
+            "{}",
+            screen
+        );
+        assert!(screen.contains(" 200   503  none"), "{}", screen);
+        assert!(screen.contains("Failures (2)"), "{}", screen);
+        assert!(
+            screen.contains("https://www.proshop.dk/  Timed out"),
+            "{}",
+            screen
+        );
+        assert!(
+            screen.contains("https://www.proshop.dk/  HTTP 503"),
+            "{}",
+            screen
+        );
+        // the newest failure is on top
+        assert!(screen.find("Timed out") < screen.find("HTTP 503"));
+
+        let press = |app: &mut App, code| app.key(KeyEvent::new(code, KeyModifiers::NONE), 2);
+        press(&mut app, KeyCode::Down);
+        let screen = render(&app, &stats, at(70));
+        assert!(!screen.contains("Timed out"), "{}", screen);
+        press(&mut app, KeyCode::Right);
+        let screen = render(&app, &stats, at(70));
+        assert!(
+            screen.contains("Latency of https://example.com/"),
+            "{}",
+            screen
+        );
+        assert!(press(&mut app, KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn long_target_lists_leave_room_for_the_rest() {
+        let mut app = App::default();
+        let stats: Vec<TargetStats> = (0..20)
+            .map(|i| TargetStats::new(&format!("https://example.com/{}", i)))
+            .collect();
+        let screen = render(&app, &stats, at(0));
+        assert!(screen.contains("https://example.com/7 "), "{}", screen);
+        assert!(!screen.contains("https://example.com/8 "), "{}", screen);
+        assert!(screen.contains("Failures (0)"), "{}", screen);
+
+        // the selected target is scrolled into view
+        app.selected = 15;
+        let screen = render(&app, &stats, at(0));
+        assert!(screen.contains("https://example.com/15 "), "{}", screen);
+        assert!(
+            screen.contains("Latency of https://example.com/15"),
+            "{}",
+            screen
+        );
+    }
+}
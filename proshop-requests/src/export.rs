@@ -0,0 +1,176 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::monitor::{Outcome, Sample};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+impl Format {
+    /// CSV for `.csv` files, JSON lines otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Jsonl,
+        }
+    }
+}
+
+/// One exported sample, also the CSV columns in order.
+#[derive(Debug, Serialize)]
+struct Row<'a> {
+    target: &'a str,
+    /// RFC 3339 in local time.
+    time: String,
+    latency_ms: f64,
+    status: Option<u16>,
+    error: Option<&'a str>,
+}
+
+const CSV_HEADER: &str = "target,time,latency_ms,status,error";
+
+/// Appends every sample to a file as it comes in, so an export survives the monitor being killed.
+pub struct Exporter {
+    format: Format,
+    file: LineWriter<File>,
+}
+
+impl Exporter {
+    pub fn open(path: &Path, format: Format) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut exporter = Self {
+            format,
+            file: LineWriter::new(file),
+        };
+        if empty && format == Format::Csv {
+            writeln!(exporter.file, "{}", CSV_HEADER)?;
+        }
+        Ok(exporter)
+    }
+
+    pub fn write(&mut self, sample: &Sample) -> io::Result<()> {
+        let (status, error) = match &sample.outcome {
+            Outcome::Status(status) => (Some(*status), None),
+            Outcome::Error(e) => (None, Some(e.as_str())),
+        };
+        let row = Row {
+            target: &sample.target,
+            time: sample.time.to_rfc3339(),
+            latency_ms: sample.latency.as_secs_f64() * 1000.0,
+            status,
+            error,
+        };
+        match self.format {
+            Format::Csv => writeln!(
+                self.file,
+                "{},{},{:.3},{},{}",
+                csv_field(row.target),
+                row.time,
+                row.latency_ms,
+                row.status
+                    .map(|status| status.to_string())
+                    .unwrap_or_default(),
+                csv_field(row.error.unwrap_or_default())
+            ),
+            Format::Jsonl => {
+                let line = serde_json::to_string(&row).map_err(io::Error::other)?;
+                writeln!(self.file, "{}", line)
+            }
+        }
+    }
+}
+
+/// Quotes a field when it has a comma, quote or line break in it.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use chrono::{Local, TimeZone};
+
+    use super::{Exporter, Format};
+    use crate::monitor::{Outcome, Sample};
+
+    fn samples() -> [Sample; 2] {
+        let time = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+        [
+            Sample {
+                target: "https://example.com/".to_string(),
+                time,
+                latency: Duration::from_micros(120_500),
+                outcome: Outcome::Status(200),
+            },
+            Sample {
+                target: "https://example.com/".to_string(),
+                time,
+                latency: Duration::from_secs(10),
+                outcome: Outcome::Error("Could not connect: refused, \"again\"".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_appends_after_one_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("samples.csv");
+        assert_eq!(Format::from_path(&path), Format::Csv);
+        for _ in 0..2 {
+            let mut exporter = Exporter::open(&path, Format::Csv).unwrap();
+            exporter.write(&samples()[0]).unwrap();
+        }
+        let mut exporter = Exporter::open(&path, Format::Csv).unwrap();
+        exporter.write(&samples()[1]).unwrap();
+        drop(exporter);
+
+        let time = samples()[0].time.to_rfc3339();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!(
+                "target,time,latency_ms,status,error\n\
+                 https://example.com/,{0},120.500,200,\n\
+                 https://example.com/,{0},120.500,200,\n\
+                 https://example.com/,{0},10000.000,,\"Could not connect: refused, \"\"again\"\"\"\n",
+                time
+            )
+        );
+    }
+
+    #[test]
+    fn jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("samples.jsonl");
+        assert_eq!(Format::from_path(Path::new("samples")), Format::Jsonl);
+        let mut exporter = Exporter::open(&path, Format::Jsonl).unwrap();
+        for sample in samples() {
+            exporter.write(&sample).unwrap();
+        }
+        drop(exporter);
+
+        let rows: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows[0]["status"], 200);
+        assert_eq!(rows[0]["latency_ms"], 120.5);
+        assert_eq!(rows[0]["error"], serde_json::Value::Null);
+        assert_eq!(rows[1]["status"], serde_json::Value::Null);
+        assert_eq!(rows[1]["error"], "Could not connect: refused, \"again\"");
+        assert_eq!(rows[1]["time"], samples()[1].time.to_rfc3339());
+    }
+}
@@ -1,35 +1,105 @@
-use reqwest::StatusCode;
-use std::collections::HashMap;
-use std::ops::AddAssign;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
 use std::time::Duration;
 
-const URL: &str = "https://www.proshop.dk/";
-const GET_REQUEST_SECS: u64 = 5;
-const TRY_AGAIN_SECS: u64 = 10;
+use chrono::Local;
+use clap::Parser;
+
+mod dashboard;
+mod export;
+mod monitor;
+mod plain;
+mod stats;
+use export::{Exporter, Format};
+use monitor::{Intervals, Sample};
+use stats::TargetStats;
+
+/// Checks that websites are up, with response times and outages.
+#[derive(Debug, Parser)]
+struct Args {
+    /// URLs to request.
+    #[arg(default_value = "https://www.proshop.dk/")]
+    targets: Vec<String>,
+    /// Seconds between requests while a target is up.
+    #[arg(short, long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+    /// Seconds between requests while a target is down.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    retry_interval: u64,
+    /// Seconds before a request counts as failed.
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+    /// Append every sample to this file.
+    #[arg(long)]
+    export: Option<PathBuf>,
+    /// Format of the export; by default CSV for .csv files and JSON lines otherwise.
+    #[arg(long, value_enum, requires = "export")]
+    format: Option<Format>,
+    /// Print a line per request instead of the dashboard, as when stdout is not a terminal.
+    #[arg(long)]
+    plain: bool,
+}
+
+/// Keeps the statistics and the export up to date with every sample.
+pub struct Recorder {
+    pub stats: Vec<TargetStats>,
+    exporter: Option<Exporter>,
+}
+
+impl Recorder {
+    /// Fails only when the sample could not be exported; it is counted anyway.
+    pub fn record(&mut self, sample: &Sample) -> io::Result<()> {
+        if let Some(target) = self.stats.iter_mut().find(|s| s.target == sample.target) {
+            target.record(sample);
+        }
+        match &mut self.exporter {
+            Some(exporter) => exporter.write(sample),
+            None => Ok(()),
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let mut response_count: u64 = 0;
-    let mut delays: u64 = 0;
-    let mut responses: HashMap<StatusCode, u64> = HashMap::new();
-    loop {
-        while let Ok(response) = reqwest::get(URL).await {
-            let code = response.status();
-            responses.entry(code).or_default().add_assign(1);
-            print!("\x1B[2J\x1B[1;1H"); // clears screen: https://stackoverflow.com/a/62101709
-            println!(
-                "Time elapsed: {} seconds",
-                response_count * GET_REQUEST_SECS + delays * TRY_AGAIN_SECS
-            );
-            println!("Responses: {:?}", responses);
-            response_count += 1;
-            tokio::time::sleep(Duration::from_secs(GET_REQUEST_SECS)).await;
+    let args = Args::parse();
+    for (i, target) in args.targets.iter().enumerate() {
+        if let Err(e) = reqwest::Url::parse(target) {
+            eprintln!("Invalid url ('{}'): {}", target, e);
+            std::process::exit(2);
+        }
+        // samples are told apart by their target, so both would count as one
+        if args.targets[..i].contains(target) {
+            eprintln!("'{}' is given more than once", target);
+            std::process::exit(2);
         }
-        println!(
-            "Failed to get response from url ('{}'), trying again in {} seconds.",
-            URL, TRY_AGAIN_SECS
-        );
-        delays += 1;
-        tokio::time::sleep(Duration::from_secs(TRY_AGAIN_SECS)).await;
     }
-}
\ No newline at end of file
+    let exporter = args.export.as_ref().map(|path| {
+        let format = args.format.unwrap_or_else(|| Format::from_path(path));
+        Exporter::open(path, format).unwrap_or_else(|e| {
+            eprintln!("Could not open {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(args.timeout))
+        .build()
+        .expect("the client has no unusual settings");
+    let intervals = Intervals {
+        up: Duration::from_secs(args.interval),
+        down: Duration::from_secs(args.retry_interval),
+    };
+    let (sender, samples) = tokio::sync::mpsc::channel(64);
+    monitor::spawn(client, &args.targets, intervals, sender);
+
+    let mut recorder = Recorder {
+        stats: args.targets.iter().map(|t| TargetStats::new(t)).collect(),
+        exporter,
+    };
+    if args.plain || !io::stdout().is_terminal() {
+        plain::run(&mut recorder, samples).await;
+    } else if let Err(e) = dashboard::run(&mut recorder, samples).await {
+        eprintln!("Terminal error: {}", e);
+        std::process::exit(1);
+    }
+    print!("{}", stats::report(&recorder.stats, Local::now()));
+}
@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use reqwest::Client;
+use tokio::sync::mpsc::Sender;
+
+/// One timed request to a target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub target: String,
+    /// When the request was sent.
+    pub time: DateTime<Local>,
+    /// Until the whole body was read, or until the request failed.
+    pub latency: Duration,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Status(u16),
+    /// No response at all: DNS, connection, timeout...
+    Error(String),
+}
+
+impl Sample {
+    /// Only 2xx responses count as up.
+    pub fn is_up(&self) -> bool {
+        matches!(self.outcome, Outcome::Status(status) if (200..300).contains(&status))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Intervals {
+    /// Between requests while the target is up.
+    pub up: Duration,
+    /// Between requests while it is down.
+    pub down: Duration,
+}
+
+pub async fn probe(client: &Client, target: &str) -> Sample {
+    let time = Local::now();
+    let start = Instant::now();
+    let outcome = match client.get(target).send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            match response.bytes().await {
+                Ok(_) => Outcome::Status(status),
+                Err(e) => Outcome::Error(format!("Body cut off: {}", e.without_url())),
+            }
+        }
+        Err(e) => Outcome::Error(describe(e)),
+    };
+    Sample {
+        target: target.to_string(),
+        time,
+        latency: start.elapsed(),
+        outcome,
+    }
+}
+
+fn describe(e: reqwest::Error) -> String {
+    if e.is_timeout() {
+        "Timed out".to_string()
+    } else if e.is_connect() {
+        format!("Could not connect: {}", e.without_url())
+    } else {
+        e.without_url().to_string()
+    }
+}
+
+/// Probes each target on its own task until the receiver is dropped.
+pub fn spawn(client: Client, targets: &[String], intervals: Intervals, samples: Sender<Sample>) {
+    for target in targets {
+        let (client, target, samples) = (client.clone(), target.clone(), samples.clone());
+        tokio::spawn(async move {
+            loop {
+                let sample = probe(&client, &target).await;
+                let wait = match sample.is_up() {
+                    true => intervals.up,
+                    false => intervals.down,
+                };
+                if samples.send(sample).await.is_err() {
+                    return;
+                }
+                tokio::time::sleep(wait).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use reqwest::Client;
+
+    use super::{probe, Outcome};
+
+    #[tokio::test]
+    async fn statuses_and_errors() {
+        let app = Router::new()
+            .route("/ok", get(|| async { "fine" }))
+            .route("/down", get(|| async { StatusCode::SERVICE_UNAVAILABLE }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "late"
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = Client::builder()
+            .timeout(Duration::from_millis(300))
+            .build()
+            .unwrap();
+
+        let ok = probe(&client, &format!("http://{}/ok", address)).await;
+        assert_eq!(ok.outcome, Outcome::Status(200));
+        assert!(ok.is_up());
+        let down = probe(&client, &format!("http://{}/down", address)).await;
+        assert_eq!(down.outcome, Outcome::Status(503));
+        assert!(!down.is_up());
+        let slow = probe(&client, &format!("http://{}/slow", address)).await;
+        assert_eq!(slow.outcome, Outcome::Error("Timed out".to_string()));
+        assert!(slow.latency >= Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,100 @@
+use tokio::sync::mpsc::Receiver;
+
+use crate::monitor::{Outcome, Sample};
+use crate::stats::{format_duration, TargetStats};
+use crate::Recorder;
+
+/// Prints a line per sample and per outage until Ctrl-C, for logs and pipes.
+pub async fn run(recorder: &mut Recorder, mut samples: Receiver<Sample>) {
+    loop {
+        let sample = tokio::select! {
+            sample = samples.recv() => match sample {
+                Some(sample) => sample,
+                None => return,
+            },
+            _ = tokio::signal::ctrl_c() => return,
+        };
+        if let Err(e) = recorder.record(&sample) {
+            eprintln!("Could not export the sample: {}", e);
+        }
+        if let Some(target) = recorder.stats.iter().find(|s| s.target == sample.target) {
+            for line in lines(&sample, target) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+/// The lines for a sample already recorded in `target`.
+fn lines(sample: &Sample, target: &TargetStats) -> Vec<String> {
+    let time = sample.time.format("%Y-%m-%d %H:%M:%S");
+    let latency = sample.latency.as_millis();
+    let mut lines = vec![match &sample.outcome {
+        Outcome::Status(status) => format!("{} {} {} {}ms", time, sample.target, status, latency),
+        Outcome::Error(e) => format!(
+            "{} {} no response after {}ms: {}",
+            time, sample.target, latency, e
+        ),
+    }];
+    match target.outages.last() {
+        Some(outage) if outage.end.is_none() && outage.start == sample.time => {
+            lines.push(format!("{} {} is DOWN", time, sample.target))
+        }
+        Some(outage) if outage.end == Some(sample.time) => lines.push(format!(
+            "{} {} is UP again after {}",
+            time,
+            sample.target,
+            format_duration(outage.duration(sample.time))
+        )),
+        _ => {}
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{Local, TimeZone};
+
+    use super::lines;
+    use crate::monitor::{Outcome, Sample};
+    use crate::stats::TargetStats;
+
+    #[test]
+    fn outages_get_a_line_of_their_own() {
+        let mut target = TargetStats::new("https://example.com/");
+        let mut printed = vec![];
+        for (secs, outcome) in [
+            (0, Outcome::Status(200)),
+            (5, Outcome::Error("Timed out".to_string())),
+            (10, Outcome::Status(503)),
+            (75, Outcome::Status(204)),
+        ] {
+            let sample = Sample {
+                target: target.target.clone(),
+                time: Local.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+                latency: Duration::from_millis(120),
+                outcome,
+            };
+            target.record(&sample);
+            let time = sample.time.format("%Y-%m-%d %H:%M:%S").to_string();
+            printed.extend(
+                lines(&sample, &target)
+                    .iter()
+                    .map(|l| l.replace(&time, "T")),
+            );
+        }
+        assert_eq!(
+            printed,
+            [
+                "T https://example.com/ 200 120ms",
+                "T https://example.com/ no response after 120ms: Timed out",
+                "T https://example.com/ is DOWN",
+                "T https://example.com/ 503 120ms",
+                "T https://example.com/ 204 120ms",
+                "T https://example.com/ is UP again after 1m 10s",
+            ]
+        );
+    }
+}
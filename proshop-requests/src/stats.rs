@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::monitor::{Outcome, Sample};
+
+/// Samples kept per target for the latency graph.
+const RECENT: usize = 500;
+
+/// A run of failed requests, from the first failure to the first success after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outage {
+    pub start: DateTime<Local>,
+    /// `None` while it lasts.
+    pub end: Option<DateTime<Local>>,
+    pub failures: u64,
+    /// What the first failed request got.
+    pub cause: String,
+}
+
+impl Outage {
+    pub fn duration(&self, now: DateTime<Local>) -> Duration {
+        (self.end.unwrap_or(now) - self.start)
+            .to_std()
+            .unwrap_or_default()
+    }
+}
+
+/// Everything measured for one target so far.
+#[derive(Debug)]
+pub struct TargetStats {
+    pub target: String,
+    /// The time of the first sample.
+    pub since: Option<DateTime<Local>>,
+    pub requests: u64,
+    pub up: u64,
+    /// How many responses took each whole number of milliseconds, which stays as small as the
+    /// timeout allows however long the monitor runs.
+    latencies: BTreeMap<u64, u64>,
+    /// Requests that got a response, the sum of `latencies`.
+    responses: u64,
+    pub statuses: BTreeMap<u16, u64>,
+    /// Requests that got no response.
+    pub errors: u64,
+    pub outages: Vec<Outage>,
+    /// The latest samples, oldest first.
+    pub recent: VecDeque<Sample>,
+}
+
+impl TargetStats {
+    pub fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+            since: None,
+            requests: 0,
+            up: 0,
+            latencies: BTreeMap::new(),
+            responses: 0,
+            statuses: BTreeMap::new(),
+            errors: 0,
+            outages: vec![],
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, sample: &Sample) {
+        self.since.get_or_insert(sample.time);
+        self.requests += 1;
+        match &sample.outcome {
+            Outcome::Status(status) => {
+                *self.statuses.entry(*status).or_default() += 1;
+                let millis = sample.latency.as_millis().try_into().unwrap_or(u64::MAX);
+                *self.latencies.entry(millis).or_default() += 1;
+                self.responses += 1;
+            }
+            Outcome::Error(_) => self.errors += 1,
+        }
+        let ongoing = self
+            .outages
+            .last_mut()
+            .filter(|outage| outage.end.is_none());
+        match (sample.is_up(), ongoing) {
+            (true, Some(outage)) => outage.end = Some(sample.time),
+            (true, None) => {}
+            (false, Some(outage)) => outage.failures += 1,
+            (false, None) => self.outages.push(Outage {
+                start: sample.time,
+                end: None,
+                failures: 1,
+                cause: match &sample.outcome {
+                    Outcome::Status(status) => format!("HTTP {}", status),
+                    Outcome::Error(e) => e.clone(),
+                },
+            }),
+        }
+        if sample.is_up() {
+            self.up += 1;
+        }
+        if self.recent.len() == RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(sample.clone());
+    }
+
+    /// Nearest-rank percentile of the response latencies, `p` in 0..=100, to the millisecond.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.responses == 0 {
+            return None;
+        }
+        let rank = ((p / 100.0 * self.responses as f64).ceil() as u64).clamp(1, self.responses);
+        let mut counted = 0;
+        self.latencies.iter().find_map(|(&millis, &count)| {
+            counted += count;
+            (counted >= rank).then(|| Duration::from_millis(millis))
+        })
+    }
+
+    /// The share of requests that were up, in percent.
+    pub fn uptime(&self) -> Option<f64> {
+        (self.requests > 0).then(|| self.up as f64 * 100.0 / self.requests as f64)
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.outages
+            .last()
+            .is_some_and(|outage| outage.end.is_none())
+    }
+
+    /// Since when the target has been up or down, whichever it is now.
+    pub fn state_since(&self) -> Option<DateTime<Local>> {
+        match self.outages.last() {
+            Some(outage) => Some(outage.end.unwrap_or(outage.start)),
+            None => self.since,
+        }
+    }
+}
+
+/// Like "2h 5m", "3m 20s" or "45s", the two largest units only.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+pub fn format_latency(latency: Option<Duration>) -> String {
+    match latency {
+        Some(latency) => format!("{}ms", latency.as_millis()),
+        None => "-".to_string(),
+    }
+}
+
+/// The plain text report of all targets.
+pub fn report(stats: &[TargetStats], now: DateTime<Local>) -> String {
+    let mut out = String::new();
+    for target in stats {
+        let state = match target.is_down() {
+            true => "DOWN",
+            false => "UP",
+        };
+        let _ = write!(out, "{}  {}", target.target, state);
+        if let Some(since) = target.state_since() {
+            let lasted = (now - since).to_std().unwrap_or_default();
+            let _ = write!(
+                out,
+                " since {} ({})",
+                since.format("%Y-%m-%d %H:%M:%S"),
+                format_duration(lasted)
+            );
+        }
+        out.push('\n');
+        let _ = writeln!(
+            out,
+            "  requests {}, uptime {}",
+            target.requests,
+            target
+                .uptime()
+                .map_or("-".to_string(), |uptime| format!("{:.2}%", uptime))
+        );
+        let _ = writeln!(
+            out,
+            "  latency p50 {}  p95 {}  p99 {}",
+            format_latency(target.percentile(50.0)),
+            format_latency(target.percentile(95.0)),
+            format_latency(target.percentile(99.0))
+        );
+        let mut counts: Vec<String> = target
+            .statuses
+            .iter()
+            .map(|(status, count)| format!("{}: {}", status, count))
+            .collect();
+        if target.errors > 0 {
+            counts.push(format!("no response: {}", target.errors));
+        }
+        let _ = writeln!(out, "  responses {}", counts.join(", "));
+        if !target.outages.is_empty() {
+            let _ = writeln!(out, "  outages");
+        }
+        for outage in &target.outages {
+            let end = match outage.end {
+                Some(end) => end.format("%H:%M:%S").to_string(),
+                None => "ongoing".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "    {} - {} ({}), {} failed, {}",
+                outage.start.format("%Y-%m-%d %H:%M:%S"),
+                end,
+                format_duration(outage.duration(now)),
+                outage.failures,
+                outage.cause
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Local, TimeZone};
+
+    use super::{format_duration, report, TargetStats};
+    use crate::monitor::{Outcome, Sample};
+
+    fn at(secs: i64) -> DateTime<Local> {
+        Local.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn sample(secs: i64, millis: u64, outcome: Outcome) -> Sample {
+        Sample {
+            target: "https://example.com/".to_string(),
+            time: at(secs),
+            latency: Duration::from_millis(millis),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut stats = TargetStats::new("https://example.com/");
+        assert_eq!(stats.percentile(50.0), None);
+        for millis in (1..=100).rev() {
+            stats.record(&sample(0, millis, Outcome::Status(200)));
+        }
+        // requests without a response have no latency to speak of
+        stats.record(&sample(0, 5000, Outcome::Error("Timed out".to_string())));
+        let ms = |p| stats.percentile(p).unwrap().as_millis();
+        assert_eq!((ms(50.0), ms(95.0), ms(99.0), ms(100.0)), (50, 95, 99, 100));
+        assert_eq!(ms(0.0), 1);
+
+        // a long run takes no more room than its distinct latencies
+        for i in 0..10_000 {
+            stats.record(&sample(0, 40 + i % 20, Outcome::Status(200)));
+        }
+        assert_eq!(stats.latencies.len(), 100);
+        let ms = |p| stats.percentile(p).unwrap().as_millis();
+        assert_eq!((ms(0.0), ms(50.0), ms(100.0)), (1, 50, 100));
+    }
+
+    #[test]
+    fn outages() {
+        let mut stats = TargetStats::new("https://example.com/");
+        let timeline = [
+            (0, Outcome::Status(200)),
+            (5, Outcome::Status(503)),
+            (10, Outcome::Error("Timed out".to_string())),
+            (20, Outcome::Status(200)),
+            (25, Outcome::Status(301)),
+        ];
+        for (secs, outcome) in timeline {
+            stats.record(&sample(secs, 10, outcome));
+        }
+        assert_eq!(stats.outages.len(), 2);
+        let first = &stats.outages[0];
+        assert_eq!((first.start, first.end), (at(5), Some(at(20))));
+        assert_eq!((first.failures, first.cause.as_str()), (2, "HTTP 503"));
+        assert_eq!(first.duration(at(100)), Duration::from_secs(15));
+        assert!(stats.is_down());
+        assert_eq!(stats.state_since(), Some(at(25)));
+        assert_eq!(stats.outages[1].duration(at(40)), Duration::from_secs(15));
+        assert_eq!(stats.uptime(), Some(40.0));
+
+        let report = report(&[stats], at(40));
+        assert!(
+            report.contains("https://example.com/  DOWN since"),
+            "{}",
+            report
+        );
+        assert!(report.contains("requests 5, uptime 40.00%"), "{}", report);
+        assert!(
+            report.contains("responses 200: 2, 301: 1, 503: 1, no response: 1"),
+            "{}",
+            report
+        );
+        assert!(report.contains("(15s), 2 failed, HTTP 503"), "{}", report);
+        assert!(
+            report.contains("ongoing (15s), 1 failed, HTTP 301"),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(200)), "3m 20s");
+        assert_eq!(format_duration(Duration::from_secs(7500)), "2h 5m");
+        assert_eq!(format_duration(Duration::from_secs(90000)), "1d 1h");
+    }
+}
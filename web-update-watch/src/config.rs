@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+
+use crate::notify::{Notifier, NotifierConfig};
+use crate::watch::{Normalize, Watch, DEFAULT_TIMEOUT};
+
+/// The watch list file:
+///
+/// ```toml
+/// history = "history"
+/// host_delay = 2.0
+///
+/// [[target]]
+/// name = "ledige-pladser"
+/// url = "https://ufm.dk/uddannelse/videregaende-uddannelse/sogning-optag-og-vejledning/ledige-pladser"
+/// selector = "main table"
+/// interval = 300
+/// jitter = 30
+/// collapse_whitespace = true
+/// strip = ['Opdateret \d+\. \w+ \d{4}']
+/// headers = { "User-Agent" = "web-update-watch" }
+/// notify = [{ type = "bell" }, { type = "log", path = "changes.jsonl" }]
+/// ```
+///
+/// Durations are in seconds. See [`NotifierConfig`] for the notifiers.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchList {
+    #[serde(default = "WatchList::default_history")]
+    pub history: PathBuf,
+    /// Least time between two requests to the same host, across all targets.
+    #[serde(default = "WatchList::default_host_delay")]
+    host_delay: f64,
+    /// How often the status summary is printed, never when 0.
+    #[serde(default = "WatchList::default_summary")]
+    summary: f64,
+    #[serde(rename = "target")]
+    targets: Vec<TargetConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TargetConfig {
+    /// Shown in the output, the URL by default.
+    name: Option<String>,
+    url: String,
+    selector: Option<String>,
+    #[serde(default = "TargetConfig::default_interval")]
+    interval: f64,
+    /// Up to this much is added to every interval, so targets drift apart.
+    #[serde(default)]
+    jitter: f64,
+    #[serde(default = "TargetConfig::default_timeout")]
+    timeout: f64,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    collapse_whitespace: bool,
+    #[serde(default)]
+    strip: Vec<String>,
+    /// Told about every change, besides stdout.
+    #[serde(default)]
+    notify: Vec<NotifierConfig>,
+}
+
+/// A watch and when to check it.
+#[derive(Debug)]
+pub struct Target {
+    pub name: String,
+    pub watch: Watch,
+    pub interval: Duration,
+    pub jitter: Duration,
+    pub notifiers: Vec<Box<dyn Notifier>>,
+}
+
+#[derive(Debug)]
+pub struct ConfigError(String);
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl WatchList {
+    fn default_history() -> PathBuf {
+        PathBuf::from("history")
+    }
+    fn default_host_delay() -> f64 {
+        1.0
+    }
+    fn default_summary() -> f64 {
+        60.0
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError(format!("Could not read {}: {}", path.display(), e)))?;
+        Self::parse(&text).map_err(|e| ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let list: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        seconds("host_delay", list.host_delay, true)?;
+        seconds("summary", list.summary, true)?;
+        Ok(list)
+    }
+
+    pub fn host_delay(&self) -> Duration {
+        Duration::from_secs_f64(self.host_delay)
+    }
+
+    pub fn summary(&self) -> Option<Duration> {
+        (self.summary > 0.0).then(|| Duration::from_secs_f64(self.summary))
+    }
+
+    pub fn into_targets(self) -> Result<Vec<Target>, ConfigError> {
+        let mut targets: Vec<Target> = vec![];
+        for config in self.targets {
+            let name = config.name.clone().unwrap_or_else(|| config.url.clone());
+            let target = config
+                .into_target(name.clone())
+                .map_err(|e| ConfigError(format!("Target `{}`: {}", name, e)))?;
+            if targets.iter().any(|other| other.name == target.name) {
+                return Err(ConfigError(format!("Target `{}` is listed twice", name)));
+            }
+            // they would share, and overwrite, one history
+            let key = target.watch.key();
+            if let Some(other) = targets.iter().find(|other| other.watch.key() == key) {
+                return Err(ConfigError(format!(
+                    "Targets `{}` and `{}` watch the same page and selector",
+                    other.name, name
+                )));
+            }
+            targets.push(target);
+        }
+        if targets.is_empty() {
+            return Err(ConfigError("The watch list has no targets".to_string()));
+        }
+        Ok(targets)
+    }
+}
+
+impl TargetConfig {
+    fn default_interval() -> f64 {
+        1.25
+    }
+    fn default_timeout() -> f64 {
+        DEFAULT_TIMEOUT.as_secs_f64()
+    }
+
+    fn into_target(self, name: String) -> Result<Target, String> {
+        let strip = self
+            .strip
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?;
+        let headers = header_map(&self.headers)?;
+        reqwest::Url::parse(&self.url).map_err(|e| format!("Invalid url: {}", e))?;
+        let mut watch = Watch::new(
+            self.url,
+            self.selector.as_deref(),
+            Normalize {
+                collapse_whitespace: self.collapse_whitespace,
+                strip,
+            },
+        )?;
+        watch.headers = headers;
+        watch.timeout = seconds("timeout", self.timeout, false)?;
+        Ok(Target {
+            name,
+            watch,
+            interval: seconds("interval", self.interval, false)?,
+            jitter: seconds("jitter", self.jitter, true)?,
+            notifiers: self
+                .notify
+                .into_iter()
+                .map(NotifierConfig::build)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+pub fn header_map(headers: &BTreeMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        map.insert(
+            HeaderName::try_from(name.as_str())
+                .map_err(|e| format!("Invalid header `{}`: {}", name, e))?,
+            HeaderValue::try_from(value.as_str())
+                .map_err(|e| format!("Invalid value of header `{}`: {}", name, e))?,
+        );
+    }
+    Ok(map)
+}
+
+fn seconds(field: &str, value: f64, zero_allowed: bool) -> Result<Duration, String> {
+    match Duration::try_from_secs_f64(value) {
+        Ok(duration) if zero_allowed || !duration.is_zero() => Ok(duration),
+        _ => Err(format!("`{}` must be a positive number of seconds", field)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::WatchList;
+
+    #[test]
+    fn loads_targets() {
+        let list = WatchList::parse(
+            r##"
+            host_delay = 0.5
+
+            [[target]]
+            url = "https://example.com/"
+
+            [[target]]
+            name = "places"
+            url = "https://example.com/places"
+            selector = "#places li"
+            interval = 60
+            jitter = 5.5
+            timeout = 10
+            headers = { "User-Agent" = "web-update-watch", Accept = "text/html" }
+            collapse_whitespace = true
+            strip = ['\d{2}:\d{2}']
+            notify = [{ type = "bell" }, { type = "log", path = "changes.jsonl" }]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(list.host_delay(), Duration::from_millis(500));
+        assert_eq!(list.summary(), Some(Duration::from_secs(60)));
+        assert_eq!(list.history.to_str(), Some("history"));
+
+        let targets = list.into_targets().unwrap();
+        assert_eq!(targets[0].name, "https://example.com/");
+        assert_eq!(targets[0].interval, Duration::from_millis(1250));
+        assert_eq!(targets[0].jitter, Duration::ZERO);
+        assert_eq!(targets[0].watch.timeout, Duration::from_secs(30));
+
+        let places = &targets[1];
+        assert_eq!(places.name, "places");
+        assert_eq!(places.watch.key(), "https://example.com/places #places li");
+        assert_eq!(places.interval, Duration::from_secs(60));
+        assert_eq!(places.jitter, Duration::from_millis(5500));
+        assert_eq!(places.watch.timeout, Duration::from_secs(10));
+        assert_eq!(places.watch.headers["user-agent"], "web-update-watch");
+        assert_eq!(places.watch.headers.len(), 2);
+        assert!(places.watch.normalize.collapse_whitespace);
+        assert_eq!(places.watch.normalize.strip.len(), 1);
+        assert_eq!(places.notifiers.len(), 2);
+        assert!(targets[0].notifiers.is_empty());
+    }
+
+    #[test]
+    fn example_list() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/watch.toml");
+        let targets = WatchList::load(path.as_ref())
+            .unwrap()
+            .into_targets()
+            .unwrap();
+        assert_eq!(targets[0].name, "ledige-pladser");
+        assert_eq!(targets[0].notifiers.len(), 2);
+    }
+
+    #[test]
+    fn invalid_lists() {
+        let targets = |toml: &str| WatchList::parse(toml).map(WatchList::into_targets);
+        for invalid in [
+            "",
+            "[[target]]\nurl = \"https://example.com\"\nintervall = 5",
+            "host_delay = -1\n[[target]]\nurl = \"https://example.com\"",
+        ] {
+            assert!(targets(invalid).is_err(), "{}", invalid);
+        }
+        for invalid in [
+            "target = []",
+            "[[target]]\nurl = \"example.com\"",
+            "[[target]]\nurl = \"https://example.com\"\ninterval = 0",
+            "[[target]]\nurl = \"https://example.com\"\nselector = \"ul >\"",
+            "[[target]]\nurl = \"https://example.com\"\nstrip = [\"(\"]",
+            "[[target]]\nurl = \"https://example.com\"\nheaders = { \"Bad Name\" = \"x\" }",
+            "[[target]]\nurl = \"https://a.example\"\n[[target]]\nurl = \"https://a.example\"",
+            concat!(
+                "[[target]]\nname = \"a\"\nurl = \"https://a.example\"\nselector = \"p\"\n",
+                "[[target]]\nname = \"b\"\nurl = \"https://a.example\"\nselector = \"p\""
+            ),
+            "[[target]]\nurl = \"https://example.com\"\nnotify = [{ type = \"webhook\", url = \"x\" }]",
+        ] {
+            assert!(matches!(targets(invalid), Ok(Err(_))), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use similar::{ChangeTag, TextDiff};
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A line diff from `old` to `new`, with `-` and `+` marking changed lines and `…` standing in
+/// for skipped unchanged ones. Empty if nothing changed.
+pub fn diff(old: &str, new: &str) -> String {
+    let lines = lines(
+        &old.lines().collect::<Vec<_>>(),
+        &new.lines().collect::<Vec<_>>(),
+    );
+    let mut shown = vec![false; lines.len()];
+    for (i, _) in lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+    {
+        let end = (i + CONTEXT + 1).min(lines.len());
+        shown[i.saturating_sub(CONTEXT)..end].fill(true);
+    }
+
+    let mut out = String::new();
+    let mut skipped = false;
+    for (line, shown) in lines.iter().zip(&shown) {
+        if !shown {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            out.push_str("  …\n");
+            skipped = false;
+        }
+        let (mark, text) = match line {
+            Line::Same(text) => (' ', text),
+            Line::Removed(text) => ('-', text),
+            Line::Added(text) => ('+', text),
+        };
+        out.push(mark);
+        out.push(' ');
+        out.push_str(text);
+        out.push('\n');
+    }
+    if skipped && shown.contains(&true) {
+        out.push_str("  …\n");
+    }
+    out
+}
+
+/// A Myers diff in linear space, removals before additions.
+fn lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    TextDiff::from_slices(old, new)
+        .iter_all_changes()
+        .map(|change| match change.tag() {
+            ChangeTag::Equal => Line::Same(change.value()),
+            ChangeTag::Delete => Line::Removed(change.value()),
+            ChangeTag::Insert => Line::Added(change.value()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+
+    #[test]
+    fn unchanged_is_empty() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+        assert_eq!(diff("", ""), "");
+    }
+
+    #[test]
+    fn changed_lines_with_context() {
+        let old: String = (1..=12).map(|i| format!("{}\n", i)).collect();
+        let new = old.replace("5\n", "five\n") + "13\n";
+        assert_eq!(
+            diff(&old, &new),
+            "  …\n  3\n  4\n- 5\n+ five\n  6\n  7\n  …\n  11\n  12\n+ 13\n"
+        );
+    }
+
+    #[test]
+    fn long_pages() {
+        let old: String = (0..20_000).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 10000\n", "");
+        assert_eq!(
+            diff(&old, &new),
+            "  …\n  line 9998\n  line 9999\n- line 10000\n  line 10001\n  line 10002\n  …\n"
+        );
+    }
+
+    #[test]
+    fn from_and_to_nothing() {
+        assert_eq!(diff("", "new\n"), "+ new\n");
+        assert_eq!(diff("old\nlines", ""), "- old\n- lines\n");
+    }
+}
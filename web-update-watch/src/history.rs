@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Every distinct snapshot of one watch, one file per change named by its Unix time in
+/// milliseconds.
+#[derive(Debug)]
+pub struct History {
+    dir: PathBuf,
+}
+impl History {
+    /// The history of `key` below `root`, created when missing.
+    pub fn open(root: &Path, key: &str) -> io::Result<Self> {
+        let dir = root.join(dir_name(key));
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Snapshot files, oldest first.
+    pub fn snapshots(&self) -> io::Result<Vec<PathBuf>> {
+        let mut snapshots: Vec<(u128, PathBuf)> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let time = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_suffix(".txt")?
+                    .parse()
+                    .ok()?;
+                Some((time, path))
+            })
+            .collect();
+        snapshots.sort();
+        Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+    }
+
+    pub fn latest_time(&self) -> io::Result<Option<SystemTime>> {
+        Ok(self.snapshots()?.last().and_then(|path| {
+            let millis = path.file_stem()?.to_str()?.parse().ok()?;
+            Some(UNIX_EPOCH + Duration::from_millis(millis))
+        }))
+    }
+
+    pub fn latest(&self) -> io::Result<Option<String>> {
+        match self.snapshots()?.last() {
+            Some(path) => fs::read_to_string(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, content: &str) -> io::Result<PathBuf> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // never overwrite an earlier snapshot taken within the same millisecond
+        let newest = self
+            .snapshots()?
+            .last()
+            .and_then(|path| path.file_stem()?.to_str()?.parse::<u128>().ok());
+        let time = newest.map_or(now, |newest| now.max(newest + 1));
+        let path = self.dir.join(format!("{}.txt", time));
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+/// The slug of `key`, which is readable but shared by similar keys, followed by a hash of all of
+/// `key`, which tells them apart.
+fn dir_name(key: &str) -> String {
+    format!("{}-{:016x}", slug(key), fnv1a(key))
+}
+
+/// FNV-1a, which unlike the standard library's hasher stays the same between Rust versions, so
+/// directories are found again after an upgrade.
+fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A directory name for `key`, keeping ASCII letters and digits.
+fn slug(key: &str) -> String {
+    let key = key
+        .strip_prefix("https://")
+        .or_else(|| key.strip_prefix("http://"))
+        .unwrap_or(key);
+    let mut slug = String::with_capacity(key.len());
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' {
+            slug.push(c);
+        } else if !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_matches('_').chars().take(120).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{dir_name, slug, History};
+
+    #[test]
+    fn slugs() {
+        assert_eq!(
+            slug("https://ufm.dk/uddannelse/ledige-pladser"),
+            "ufm.dk_uddannelse_ledige_pladser"
+        );
+        assert_eq!(
+            slug("http://127.0.0.1:8080/?q=1 #main > p"),
+            "127.0.0.1_8080_q_1_main_p"
+        );
+        assert_eq!(
+            dir_name("https://example.com/"),
+            "example.com-0c8b41cfdcb3c914"
+        );
+        // keys that share a slug still get a directory each
+        assert_eq!(slug("a/b"), slug("a-b"));
+        assert_ne!(dir_name("a/b"), dir_name("a-b"));
+        let long = format!("https://example.com/{}", "a".repeat(200));
+        assert_eq!(slug(&long), slug(&format!("{}b", long)));
+        assert_ne!(dir_name(&long), dir_name(&format!("{}b", long)));
+    }
+
+    #[test]
+    fn keeps_every_snapshot() {
+        let root = tempfile::tempdir().unwrap();
+        let history = History::open(root.path(), "https://example.com/").unwrap();
+        assert_eq!(history.latest().unwrap(), None);
+        assert_eq!(history.latest_time().unwrap(), None);
+        for content in ["first", "second", "third"] {
+            history.save(content).unwrap();
+        }
+        assert_eq!(history.snapshots().unwrap().len(), 3);
+        assert_eq!(history.latest().unwrap().as_deref(), Some("third"));
+        let (saved, now) = (history.latest_time().unwrap().unwrap(), SystemTime::now());
+        assert!(saved.max(now).duration_since(saved.min(now)).unwrap() < Duration::from_secs(60));
+
+        let reopened = History::open(root.path(), "https://example.com/").unwrap();
+        assert_eq!(reopened.latest().unwrap().as_deref(), Some("third"));
+        let other = History::open(root.path(), "https://example.com/other").unwrap();
+        assert_eq!(other.latest().unwrap(), None);
+    }
+}
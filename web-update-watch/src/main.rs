@@ -1,67 +1,163 @@
-use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-#[tokio::main]
-async fn main() {
-    let url = "https://ufm.dk/uddannelse/videregaende-uddannelse/sogning-optag-og-vejledning/ledige-pladser";
-
-    #[allow(unused)]
-    {
-        let mut prev_was_change = true;
-        let mut prev = ();
-    }
+use clap::Parser;
+use regex::Regex;
+use tokio::task::JoinSet;
+use url::Url;
 
-    loop {
-        let response = reqwest::get(url).await;
-        match response {
-            Ok(response) => {
-                let url = response.url();
-                let mut buffer = String::new();
-                let src = url
-                    .domain()
-                    .or_else(|| {
-                        url.host().map(|h| {
-                            buffer = h.to_string();
-                            buffer.as_ref()
-                        })
-                    })
-                    .unwrap_or_else(|| url.as_ref());
+mod config;
+mod diff;
+mod history;
+mod notify;
+mod schedule;
+mod status;
+mod watch;
+use config::{Target, WatchList};
+use schedule::{HostLimiter, Scheduled};
+use status::Status;
+use watch::{Normalize, Watch, Watcher};
 
-                let buffer;
-                let src_2 = match url.domain() {
-                    None => match url.host() {
-                        None => url.as_ref(),
-                        Some(host) => {
-                            buffer = host.to_string();
-                            buffer.as_ref()
-                        }
-                    },
-                    Some(domain) => domain,
-                };
+/// Checks web pages over and over, printing a diff whenever their text changes.
+/// Every version is kept in the history directory, so a restart picks up where it left off.
+/// Ctrl-C prints how every target has fared and quits.
+#[derive(Debug, Parser)]
+struct Args {
+    #[arg(
+        default_value = "https://ufm.dk/uddannelse/videregaende-uddannelse/sogning-optag-og-vejledning/ledige-pladser"
+    )]
+    url: Url,
+    /// CSS selector of the elements to compare, the whole page by default.
+    #[arg(short, long)]
+    selector: Option<String>,
+    /// Ignore changes in whitespace and empty lines.
+    #[arg(short = 'w', long)]
+    collapse_whitespace: bool,
+    /// Ignore text matching this regex, like a timestamp. Can be given several times.
+    #[arg(long, value_name = "REGEX")]
+    strip: Vec<Regex>,
+    /// Defaults to `history`, or the one in the watch list.
+    #[arg(long)]
+    history: Option<PathBuf>,
+    /// Milliseconds between checks.
+    #[arg(long, default_value_t = 1250)]
+    interval: u64,
+    /// Watch every target in this TOML file instead of a single URL.
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["url", "selector", "collapse_whitespace", "strip", "interval"]
+    )]
+    list: Option<PathBuf>,
+}
 
-                println!("Got response: {} @ {}{}", response.status(), src, src_2);
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let (targets, history, host_delay, summary_every) = match load(&args) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let history = args.history.clone().unwrap_or(history);
 
-                let again_sleep = tokio::time::sleep(Duration::from_millis(1250));
-                tokio::select! {
-                    () = again_sleep => {
-                        print!("Going again... ");
-                    }
-                }
-            }
+    let limiter = Arc::new(HostLimiter::new(host_delay));
+    let status = Arc::new(Mutex::new(Vec::with_capacity(targets.len())));
+    let client = reqwest::Client::new();
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.into_iter().enumerate() {
+        let watcher = match Watcher::open(target.watch, &history) {
+            Ok(watcher) => watcher,
             Err(e) => {
-                eprintln!("Failed HTTP GET request:\n   {}", e);
-
-                let retry_delay = Duration::from_millis(1250);
-                let retry_sleep = tokio::time::sleep(retry_delay);
-                let continue_signal = tokio::signal::ctrl_c();
-                tokio::select! {
-                    () = retry_sleep => {
-                        print!("Waited the full {} ms, trying again... ", retry_delay.as_millis());
-                    }
-                    Ok(()) = continue_signal => {
-                        print!("Skipped wait (ctrl-c), trying again... ");
-                    }
-                }
+                eprintln!("Could not read the history in {}: {}", history.display(), e);
+                std::process::exit(1);
+            }
+        };
+        status.lock().unwrap().push((
+            target.name.clone(),
+            Status {
+                last_change: watcher.last_change(),
+                ..Status::default()
+            },
+        ));
+        tasks.spawn(schedule::run(
+            Scheduled {
+                name: target.name,
+                watcher,
+                interval: target.interval,
+                jitter: target.jitter,
+                notifiers: target.notifiers,
+                limiter: limiter.clone(),
+                status: status.clone(),
+                index,
+            },
+            client.clone(),
+        ));
+    }
+    if let Some(every) = summary_every {
+        let status = status.clone();
+        tasks.spawn(async move {
+            let mut ticks = tokio::time::interval(every);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                print!(
+                    "{}",
+                    status::summary(&status.lock().unwrap(), SystemTime::now())
+                );
             }
+        });
+    }
+
+    tokio::select! {
+        Ok(()) = tokio::signal::ctrl_c() => {
+            println!();
+            print!("{}", status::summary(&status.lock().unwrap(), SystemTime::now()));
+        }
+        Some(Err(e)) = tasks.join_next() => {
+            eprintln!("A watch stopped: {}", e);
+            std::process::exit(1);
         }
     }
-}
\ No newline at end of file
+}
+
+/// The targets with the history directory, the per-host delay and how often to summarize.
+fn load(args: &Args) -> Result<(Vec<Target>, PathBuf, Duration, Option<Duration>), String> {
+    let Some(path) = &args.list else {
+        let target = single_target(args)?;
+        return Ok((vec![target], PathBuf::from("history"), Duration::ZERO, None));
+    };
+    let list = WatchList::load(path).map_err(|e| e.to_string())?;
+    let (history, host_delay, summary) = (list.history.clone(), list.host_delay(), list.summary());
+    let targets = list.into_targets().map_err(|e| e.to_string())?;
+    Ok((targets, history, host_delay, summary))
+}
+
+/// The target given by the command line flags.
+fn single_target(args: &Args) -> Result<Target, String> {
+    let watch = Watch::new(
+        args.url.as_str(),
+        args.selector.as_deref(),
+        Normalize {
+            collapse_whitespace: args.collapse_whitespace,
+            strip: args.strip.clone(),
+        },
+    )?;
+    let name = match args.url.domain() {
+        Some(domain) => domain.to_string(),
+        None => match args.url.host() {
+            Some(host) => host.to_string(),
+            None => args.url.to_string(),
+        },
+    };
+    Ok(Target {
+        name,
+        watch,
+        interval: Duration::from_millis(args.interval),
+        jitter: Duration::ZERO,
+        notifiers: vec![],
+    })
+}
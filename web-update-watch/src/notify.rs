@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::config::header_map;
+
+/// How long a webhook or command may take, so a hanging one doesn't hold up its target's checks.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A detected change, as handed to every notifier of its target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub target: String,
+    pub url: String,
+    /// Unix time in seconds.
+    pub time: u64,
+    pub diff: String,
+    pub snapshot: PathBuf,
+}
+impl Event {
+    pub fn new(target: &str, url: &str, diff: &str, snapshot: PathBuf) -> Self {
+        Self {
+            target: target.to_string(),
+            url: url.to_string(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            diff: diff.to_string(),
+            snapshot,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NotifyError(String);
+impl Display for NotifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Somewhere a change is reported to, besides stdout.
+#[async_trait]
+pub trait Notifier: Send + Sync + std::fmt::Debug {
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError>;
+}
+
+/// How a notifier is written in the watch list, under `notify` of a target:
+///
+/// ```toml
+/// notify = [
+///     { type = "bell" },
+///     { type = "command", program = "notify-send", args = ["Ledige pladser changed"] },
+///     { type = "webhook", url = "https://example.com/hook" },
+///     { type = "log", path = "changes.jsonl" },
+/// ]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NotifierConfig {
+    Bell {},
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    Log {
+        path: PathBuf,
+    },
+}
+impl NotifierConfig {
+    pub fn build(self) -> Result<Box<dyn Notifier>, String> {
+        Ok(match self {
+            NotifierConfig::Bell {} => Box::new(Bell),
+            NotifierConfig::Command { program, args } => Box::new(Command { program, args }),
+            NotifierConfig::Webhook { url, headers } => {
+                reqwest::Url::parse(&url).map_err(|e| format!("Invalid webhook url: {}", e))?;
+                Box::new(Webhook {
+                    client: Client::new(),
+                    url,
+                    headers: header_map(&headers)?,
+                })
+            }
+            NotifierConfig::Log { path } => Box::new(Log { path }),
+        })
+    }
+}
+
+/// Rings the terminal bell, which most terminals turn into a desktop alert when unfocused.
+#[derive(Debug)]
+pub struct Bell;
+#[async_trait]
+impl Notifier for Bell {
+    async fn notify(&self, _event: &Event) -> Result<(), NotifyError> {
+        let mut stdout = std::io::stdout();
+        stdout
+            .write_all(b"\x07")
+            .and_then(|()| stdout.flush())
+            .map_err(|e| NotifyError(format!("Could not ring the bell: {}", e)))
+    }
+}
+
+/// Runs a program with the diff on stdin and the rest of the event in `WATCH_*` variables,
+/// killing it when it takes longer than [`TIMEOUT`].
+#[derive(Debug)]
+pub struct Command {
+    program: String,
+    args: Vec<String>,
+}
+#[async_trait]
+impl Notifier for Command {
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let failed = |e: std::io::Error| NotifyError(format!("`{}` failed: {}", self.program, e));
+        let mut child = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .env("WATCH_TARGET", &event.target)
+            .env("WATCH_URL", &event.url)
+            .env("WATCH_SNAPSHOT", &event.snapshot)
+            .stdin(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(failed)?;
+        let stdin = child.stdin.take();
+        let finished = async {
+            if let Some(mut stdin) = stdin {
+                // a hook may not read its stdin at all
+                let _ = stdin.write_all(event.diff.as_bytes()).await;
+            }
+            child.wait().await
+        };
+        let status = match tokio::time::timeout(TIMEOUT, finished).await {
+            Ok(status) => status.map_err(failed)?,
+            Err(_) => {
+                let _ = child.kill().await;
+                return Err(NotifyError(format!(
+                    "`{}` was killed after {} seconds",
+                    self.program,
+                    TIMEOUT.as_secs()
+                )));
+            }
+        };
+        match status.success() {
+            true => Ok(()),
+            false => Err(NotifyError(format!(
+                "`{}` exited with {}",
+                self.program, status
+            ))),
+        }
+    }
+}
+
+/// POSTs the event as JSON.
+#[derive(Debug)]
+pub struct Webhook {
+    client: Client,
+    url: String,
+    headers: HeaderMap,
+}
+#[async_trait]
+impl Notifier for Webhook {
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let request = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .timeout(TIMEOUT)
+            .json(event);
+        let failed = |e: reqwest::Error| NotifyError(format!("Webhook failed: {}", e));
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(failed)
+    }
+}
+
+/// Appends the event as one JSON line.
+#[derive(Debug)]
+pub struct Log {
+    path: PathBuf,
+}
+#[async_trait]
+impl Notifier for Log {
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let mut line = serde_json::to_string(event).expect("events serialize");
+        line.push('\n');
+        let failed = |e: std::io::Error| {
+            NotifyError(format!("Could not log to {}: {}", self.path.display(), e))
+        };
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await.map_err(failed)?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(failed)?;
+        file.write_all(line.as_bytes()).await.map_err(failed)?;
+        // tokio writes in the background, so the line may not be in the file until flushed
+        file.flush().await.map_err(failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::{Json, Router};
+
+    use super::{Event, NotifierConfig};
+
+    fn event() -> Event {
+        Event {
+            time: 1_700_000_000,
+            ..Event::new(
+                "ledige-pladser",
+                "https://ufm.dk/ledige-pladser",
+                "  Datalogi\n+ Fysik\n",
+                PathBuf::from("history/ufm.dk/1.txt"),
+            )
+        }
+    }
+
+    fn notifier(toml: &str) -> Box<dyn super::Notifier> {
+        toml::from_str::<NotifierConfig>(toml)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_json() {
+        type Received = Arc<Mutex<Vec<(Option<String>, Event)>>>;
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(received): State<Received>,
+                     headers: HeaderMap,
+                     Json(event): Json<Event>| async move {
+                        let token = headers
+                            .get("x-token")
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        received.lock().unwrap().push((token, event));
+                    },
+                ),
+            )
+            .fallback(|| async { StatusCode::NOT_FOUND })
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let hook = notifier(&format!(
+            "type = \"webhook\"\nurl = \"http://{}/hook\"\nheaders = {{ X-Token = \"secret\" }}",
+            address
+        ));
+        hook.notify(&event()).await.unwrap();
+        assert_eq!(
+            *received.lock().unwrap(),
+            [(Some("secret".to_string()), event())]
+        );
+
+        let missing = notifier(&format!(
+            "type = \"webhook\"\nurl = \"http://{}/nope\"",
+            address
+        ));
+        assert!(missing.notify(&event()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn log_appends_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs/changes.jsonl");
+        let log = notifier(&format!("type = \"log\"\npath = {:?}", path));
+        log.notify(&event()).await.unwrap();
+        log.notify(&event()).await.unwrap();
+        let lines: Vec<Event> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines, [event(), event()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_gets_the_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let hook = notifier(&format!(
+            "type = \"command\"\nprogram = \"sh\"\nargs = [\"-c\", 'echo \"$WATCH_TARGET $WATCH_URL\" > {0:?}; cat >> {0:?}']",
+            out
+        ));
+        hook.notify(&event()).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "ledige-pladser https://ufm.dk/ledige-pladser\n  Datalogi\n+ Fysik\n"
+        );
+
+        let failing = notifier("type = \"command\"\nprogram = \"sh\"\nargs = [\"-c\", \"exit 3\"]");
+        assert!(failing.notify(&event()).await.is_err());
+        let missing = notifier("type = \"command\"\nprogram = \"/nonexistent/hook\"");
+        assert!(missing.notify(&event()).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(start_paused = true)]
+    async fn hanging_command_is_killed() {
+        let hanging = notifier("type = \"command\"\nprogram = \"sleep\"\nargs = [\"600\"]");
+        let started = std::time::Instant::now();
+        let error = hanging.notify(&event()).await.unwrap_err();
+        assert_eq!(error.to_string(), "`sleep` was killed after 30 seconds");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn invalid_notifiers() {
+        for invalid in [
+            "type = \"email\"",
+            "type = \"log\"",
+            "type = \"bell\"\nloud = true",
+        ] {
+            assert!(
+                toml::from_str::<NotifierConfig>(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+        for invalid in [
+            "type = \"webhook\"\nurl = \"nowhere\"",
+            "type = \"webhook\"\nurl = \"https://example.com\"\nheaders = { \"a b\" = \"c\" }",
+        ] {
+            let config = toml::from_str::<NotifierConfig>(invalid).unwrap();
+            assert!(config.build().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use reqwest::Client;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::time::Instant;
+
+use crate::notify::{Event, Notifier};
+use crate::status::Status;
+use crate::watch::{Change, Watcher};
+
+/// Spaces out requests to the same host, whichever target makes them.
+#[derive(Debug)]
+pub struct HostLimiter {
+    delay: Duration,
+    /// When each host may next be asked.
+    next: Mutex<HashMap<String, Instant>>,
+}
+impl HostLimiter {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes the next free slot for `host` and waits for it.
+    pub async fn wait(&self, host: &str) {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = next.get(host).map_or(now, |&free| free.max(now));
+            next.insert(host.to_string(), slot + self.delay);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// A watcher with its schedule and shared state.
+pub struct Scheduled {
+    pub name: String,
+    pub watcher: Watcher,
+    pub interval: Duration,
+    pub jitter: Duration,
+    pub notifiers: Vec<Box<dyn Notifier>>,
+    pub limiter: Arc<HostLimiter>,
+    pub status: Arc<Mutex<Vec<(String, Status)>>>,
+    /// This target's entry in `status`.
+    pub index: usize,
+}
+
+/// Checks the target forever, printing every outcome and passing changes on to the notifiers.
+pub async fn run(mut scheduled: Scheduled, client: Client) {
+    let host = reqwest::Url::parse(&scheduled.watcher.watch.url)
+        .ok()
+        .and_then(|url| {
+            Some(format!(
+                "{}:{}",
+                url.host_str()?,
+                url.port_or_known_default()?
+            ))
+        })
+        .unwrap_or_default();
+    let name = &scheduled.name;
+    let notify = spawn_notifiers(name.clone(), std::mem::take(&mut scheduled.notifiers));
+    loop {
+        scheduled.limiter.wait(&host).await;
+        let result = scheduled.watcher.check(&client).await;
+        scheduled.status.lock().unwrap()[scheduled.index]
+            .1
+            .record(&result, SystemTime::now());
+        match result {
+            Ok(Change::First) => println!("[{}] Saved the first snapshot", name),
+            Ok(Change::Unchanged) => println!("[{}] No change", name),
+            Ok(Change::Changed { diff, snapshot }) => {
+                println!("[{}] Changed (saved as {}):", name, snapshot.display());
+                print!("{}", diff);
+                let event = Event::new(name, &scheduled.watcher.watch.url, &diff, snapshot);
+                let _ = notify.send(event);
+            }
+            Err(e) => eprintln!("[{}] {}", name, e),
+        }
+        tokio::time::sleep(next_delay(scheduled.interval, scheduled.jitter)).await;
+    }
+}
+
+/// Hands each event to the notifiers on a task of their own, one event after another, so a slow
+/// webhook or hanging command does not push back the target's next check.
+fn spawn_notifiers(name: String, notifiers: Vec<Box<dyn Notifier>>) -> UnboundedSender<Event> {
+    let (sender, mut events) = mpsc::unbounded_channel::<Event>();
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            for notifier in &notifiers {
+                if let Err(e) = notifier.notify(&event).await {
+                    eprintln!("[{}] {}", name, e);
+                }
+            }
+        }
+    });
+    sender
+}
+
+/// `interval` plus a random part of `jitter`.
+fn next_delay(interval: Duration, jitter: Duration) -> Duration {
+    interval + jitter.mul_f64(fastrand::f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::time::Instant;
+
+    use super::{next_delay, spawn_notifiers, HostLimiter};
+    use crate::notify::{Event, Notifier, NotifyError};
+
+    /// Takes its time over every event, then keeps the diff.
+    #[derive(Debug, Default)]
+    struct Slow(Arc<Mutex<Vec<String>>>);
+    #[async_trait]
+    impl Notifier for Slow {
+        async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            self.0.lock().unwrap().push(event.diff.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spaces_out_requests_per_host() {
+        let limiter = HostLimiter::new(Duration::from_secs(2));
+        let start = Instant::now();
+        limiter.wait("a.example:443").await;
+        limiter.wait("b.example:443").await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.wait("a.example:443").await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+
+        // two waiting at once get consecutive slots
+        let (first, second) = tokio::join!(
+            async {
+                limiter.wait("a.example:443").await;
+                start.elapsed()
+            },
+            async {
+                limiter.wait("a.example:443").await;
+                start.elapsed()
+            },
+        );
+        assert_eq!(
+            (first, second),
+            (Duration::from_secs(4), Duration::from_secs(6))
+        );
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let now = Instant::now();
+        limiter.wait("a.example:443").await;
+        assert_eq!(now.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn jitter() {
+        let interval = Duration::from_secs(10);
+        assert_eq!(next_delay(interval, Duration::ZERO), interval);
+        for _ in 0..100 {
+            let delay = next_delay(interval, Duration::from_secs(5));
+            assert!(interval <= delay && delay <= Duration::from_secs(15));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn notifiers_do_not_hold_up_checks() {
+        let notified = Arc::new(Mutex::new(vec![]));
+        let notify = spawn_notifiers(
+            "places".to_string(),
+            vec![
+                Box::new(Slow(notified.clone())),
+                Box::new(Slow(notified.clone())),
+            ],
+        );
+        let start = Instant::now();
+        for diff in ["+ a\n", "+ b\n"] {
+            let event = Event::new("places", "https://example.com/", diff, PathBuf::new());
+            notify.send(event).unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // still one at a time, in order
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert_eq!(*notified.lock().unwrap(), ["+ a\n", "+ a\n"]);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(notified.lock().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::{Duration, SystemTime};
+
+use crate::watch::{Change, CheckError};
+
+/// How a target has fared so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    pub last_success: Option<SystemTime>,
+    /// Taken from the history at startup, so it survives restarts.
+    pub last_change: Option<SystemTime>,
+    /// Failed checks since the last successful one.
+    pub failures: u32,
+}
+impl Status {
+    pub fn record(&mut self, result: &Result<Change, CheckError>, now: SystemTime) {
+        match result {
+            Ok(change) => {
+                self.last_success = Some(now);
+                self.failures = 0;
+                if *change != Change::Unchanged {
+                    self.last_change = Some(now);
+                }
+            }
+            Err(_) => self.failures += 1,
+        }
+    }
+}
+
+/// One line per target, aligned in columns.
+pub fn summary(statuses: &[(String, Status)], now: SystemTime) -> String {
+    let rows: Vec<[String; 4]> = statuses
+        .iter()
+        .map(|(name, status)| {
+            [
+                name.clone(),
+                ago(status.last_success, now),
+                ago(status.last_change, now),
+                status.failures.to_string(),
+            ]
+        })
+        .collect();
+    let header = ["Target", "Last success", "Last change", "Failures"].map(str::to_string);
+    let mut widths = header.clone().map(|title| title.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+fn ago(time: Option<SystemTime>, now: SystemTime) -> String {
+    let Some(time) = time else {
+        return "never".to_string();
+    };
+    let seconds = now.duration_since(time).unwrap_or(Duration::ZERO).as_secs();
+    match seconds {
+        0..60 => format!("{}s ago", seconds),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use reqwest::StatusCode;
+
+    use super::{summary, Status};
+    use crate::watch::{Change, CheckError};
+
+    #[test]
+    fn records_and_summarizes() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut places = Status::default();
+        places.record(&Ok(Change::First), start);
+        places.record(&Ok(Change::Unchanged), start + Duration::from_secs(90));
+        places.record(
+            &Err(CheckError::Status(StatusCode::SERVICE_UNAVAILABLE)),
+            start + Duration::from_secs(100),
+        );
+        places.record(
+            &Err(CheckError::NoMatch("ul".to_string())),
+            start + Duration::from_secs(110),
+        );
+        assert_eq!(places.failures, 2);
+        assert_eq!(places.last_change, Some(start));
+
+        let mut recovered = places.clone();
+        recovered.record(&Ok(Change::Unchanged), start + Duration::from_secs(120));
+        assert_eq!(recovered.failures, 0);
+
+        let now = start + Duration::from_secs(2 * 3600);
+        assert_eq!(
+            summary(
+                &[
+                    ("ledige-pladser".to_string(), places),
+                    ("new".to_string(), Status::default()),
+                ],
+                now
+            ),
+            "Target          Last success  Last change  Failures\n\
+             ledige-pladser  1h ago        2h ago       2\n\
+             new             never         never        0\n"
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
+use scraper::{ElementRef, Html, Selector};
+
+use crate::diff::diff;
+use crate::history::History;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Elements whose text is code rather than content, and may change on every load (nonces, inline
+/// state), so it would report a change on every check.
+const HIDDEN: [&str; 3] = ["script", "style", "noscript"];
+
+/// A page, or the part of it picked by a CSS selector, whose text is compared between checks.
+#[derive(Debug)]
+pub struct Watch {
+    pub url: String,
+    selector: Option<(String, Selector)>,
+    pub normalize: Normalize,
+    /// Sent with every request, besides the client's own.
+    pub headers: HeaderMap,
+    pub timeout: Duration,
+}
+
+/// Makes the text stable against changes that do not matter, like a "last updated" clock.
+#[derive(Debug, Default)]
+pub struct Normalize {
+    /// Trims lines, collapses runs of whitespace and drops empty lines.
+    pub collapse_whitespace: bool,
+    /// Matches are removed before comparing, in order.
+    pub strip: Vec<Regex>,
+}
+
+#[derive(Debug)]
+pub enum CheckError {
+    Request(reqwest::Error),
+    Status(StatusCode),
+    /// The selector matched no element, so the page layout likely changed.
+    NoMatch(String),
+    History(io::Error),
+}
+
+/// The outcome of one check that got the page.
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// Nothing was stored before.
+    First,
+    Unchanged,
+    Changed {
+        diff: String,
+        snapshot: PathBuf,
+    },
+}
+
+/// A watch with its history, remembering the latest snapshot between checks.
+#[derive(Debug)]
+pub struct Watcher {
+    pub watch: Watch,
+    history: History,
+    previous: Option<String>,
+}
+
+impl Watch {
+    pub fn new(
+        url: impl Into<String>,
+        selector: Option<&str>,
+        normalize: Normalize,
+    ) -> Result<Self, String> {
+        let selector = match selector {
+            Some(source) => Some((
+                source.to_string(),
+                Selector::parse(source)
+                    .map_err(|e| format!("Invalid selector `{}`: {}", source, e))?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            url: url.into(),
+            selector,
+            normalize,
+            headers: HeaderMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Tells watches apart in the history, as the same page may be watched with several selectors.
+    pub fn key(&self) -> String {
+        match &self.selector {
+            Some((source, _)) => format!("{} {}", self.url, source),
+            None => self.url.clone(),
+        }
+    }
+
+    /// The normalized text of the selected elements, one element per line, or of the whole
+    /// document without a selector.
+    pub fn extract(&self, body: &str) -> Result<String, CheckError> {
+        let html = Html::parse_document(body);
+        let text = match &self.selector {
+            Some((source, selector)) => {
+                let elements: Vec<String> = html.select(selector).map(visible_text).collect();
+                if elements.is_empty() {
+                    return Err(CheckError::NoMatch(source.clone()));
+                }
+                elements.join("\n")
+            }
+            None => visible_text(html.root_element()),
+        };
+        Ok(self.normalize.apply(&text))
+    }
+
+    pub async fn fetch(&self, client: &Client) -> Result<String, CheckError> {
+        let response = client
+            .get(&self.url)
+            .headers(self.headers.clone())
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(CheckError::Request)?;
+        if !response.status().is_success() {
+            return Err(CheckError::Status(response.status()));
+        }
+        let body = response.text().await.map_err(CheckError::Request)?;
+        self.extract(&body)
+    }
+}
+
+/// The text inside `element`, leaving out that of [`HIDDEN`] elements.
+fn visible_text(element: ElementRef) -> String {
+    element
+        .descendants()
+        .filter(|node| {
+            node.ancestors()
+                .filter_map(|ancestor| ancestor.value().as_element())
+                .all(|ancestor| !HIDDEN.contains(&ancestor.name()))
+        })
+        .filter_map(|node| node.value().as_text())
+        .map(|text| &**text)
+        .collect()
+}
+
+impl Normalize {
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for pattern in &self.strip {
+            text = pattern.replace_all(&text, "").into_owned();
+        }
+        if self.collapse_whitespace {
+            text = text
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        text
+    }
+}
+
+impl Display for CheckError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckError::Request(e) => write!(f, "Failed HTTP GET request: {}", e),
+            CheckError::Status(status) => write!(f, "Got response: {}", status),
+            CheckError::NoMatch(selector) => write!(f, "Nothing matched `{}`", selector),
+            CheckError::History(e) => write!(f, "Could not store the snapshot: {}", e),
+        }
+    }
+}
+
+impl Watcher {
+    /// Continues from the latest snapshot in the history below `root`.
+    pub fn open(watch: Watch, root: &Path) -> io::Result<Self> {
+        let history = History::open(root, &watch.key())?;
+        let previous = history.latest()?;
+        Ok(Self {
+            watch,
+            history,
+            previous,
+        })
+    }
+
+    /// Fetches the page, storing and diffing it against the previous snapshot when it changed.
+    pub async fn check(&mut self, client: &Client) -> Result<Change, CheckError> {
+        let content = self.watch.fetch(client).await?;
+        if self.previous.as_ref() == Some(&content) {
+            return Ok(Change::Unchanged);
+        }
+        let snapshot = self.history.save(&content).map_err(CheckError::History)?;
+        let change = match &self.previous {
+            Some(previous) => Change::Changed {
+                diff: diff(previous, &content),
+                snapshot,
+            },
+            None => Change::First,
+        };
+        self.previous = Some(content);
+        Ok(change)
+    }
+
+    /// When the latest snapshot was stored, before or during this run.
+    pub fn last_change(&self) -> Option<SystemTime> {
+        self.history.latest_time().ok().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::response::Html;
+    use axum::routing::get;
+    use axum::Router;
+    use regex::Regex;
+
+    use super::{Change, CheckError, Normalize, Watch, Watcher};
+
+    fn page(items: &[&str], updated: &str) -> String {
+        let items: String = items
+            .iter()
+            .map(|item| format!("    <li>{}</li>\n", item))
+            .collect();
+        // the script changes with every load, like a nonce would
+        format!(
+            "<html><head><title>Ledige pladser</title>\n\
+             <style>li {{ color: red }}</style></head><body>\n\
+             <script>window.nonce = \"{}\";</script>\n\
+             <nav>Forside<noscript>Slå JavaScript til</noscript></nav>\n\
+             <ul id=\"places\">\n{}</ul>\n\
+             <p class=\"updated\">Opdateret {}</p>\n\
+             </body></html>",
+            updated, items, updated
+        )
+    }
+
+    /// Serves the shared page at `/`, and 404 everywhere else.
+    async fn serve(body: Arc<Mutex<String>>) -> String {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|State(body): State<Arc<Mutex<String>>>| async move {
+                    Html(body.lock().unwrap().clone())
+                }),
+            )
+            .fallback(|| async { StatusCode::NOT_FOUND })
+            .with_state(body);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn selects_and_normalizes() {
+        let body = page(&["Datalogi", "Matematik"], "12:00:01");
+        let normalize = || Normalize {
+            collapse_whitespace: true,
+            strip: vec![Regex::new(r"\d{2}:\d{2}:\d{2}").unwrap()],
+        };
+        let list = Watch::new("", Some("#places li"), normalize()).unwrap();
+        assert_eq!(list.extract(&body).unwrap(), "Datalogi\nMatematik");
+        let updated = Watch::new("", Some("p.updated"), normalize()).unwrap();
+        assert_eq!(updated.extract(&body).unwrap(), "Opdateret");
+
+        let nav = Watch::new("", Some("nav"), Normalize::default()).unwrap();
+        assert_eq!(nav.extract(&body).unwrap(), "Forside");
+        let raw = Watch::new("", Some("ul"), Normalize::default()).unwrap();
+        assert_eq!(
+            raw.extract(&body).unwrap(),
+            "\n    Datalogi\n    Matematik\n"
+        );
+        let whole = Watch::new("", None, normalize()).unwrap();
+        assert_eq!(
+            whole.extract(&body).unwrap(),
+            "Ledige pladser\nForside\nDatalogi\nMatematik\nOpdateret"
+        );
+
+        assert!(matches!(
+            Watch::new("", Some("table"), normalize()).unwrap().extract(&body),
+            Err(CheckError::NoMatch(selector)) if selector == "table"
+        ));
+        assert!(Watch::new("", Some("ul >"), normalize()).is_err());
+    }
+
+    #[tokio::test]
+    async fn reports_changes_against_history() {
+        let body = Arc::new(Mutex::new(page(&["Datalogi", "Matematik"], "12:00:01")));
+        let url = serve(body.clone()).await;
+        let history = tempfile::tempdir().unwrap();
+        let client = reqwest::Client::new();
+        let watch = || {
+            Watch::new(
+                format!("{}/", url),
+                Some("body"),
+                Normalize {
+                    collapse_whitespace: true,
+                    strip: vec![Regex::new(r"Opdateret .*").unwrap()],
+                },
+            )
+            .unwrap()
+        };
+
+        let mut watcher = Watcher::open(watch(), history.path()).unwrap();
+        assert_eq!(watcher.check(&client).await.unwrap(), Change::First);
+        *body.lock().unwrap() = page(&["Datalogi", "Matematik"], "12:00:02");
+        assert_eq!(watcher.check(&client).await.unwrap(), Change::Unchanged);
+
+        *body.lock().unwrap() = page(&["Datalogi", "Fysik", "Matematik"], "12:00:03");
+        let Change::Changed { diff, snapshot } = watcher.check(&client).await.unwrap() else {
+            panic!("the added item is a change");
+        };
+        assert_eq!(diff, "  Forside\n  Datalogi\n+ Fysik\n  Matematik\n");
+        assert_eq!(
+            std::fs::read_to_string(snapshot).unwrap(),
+            "Forside\nDatalogi\nFysik\nMatematik"
+        );
+
+        // a restart continues from the stored history
+        let mut restarted = Watcher::open(watch(), history.path()).unwrap();
+        assert_eq!(restarted.check(&client).await.unwrap(), Change::Unchanged);
+        assert_eq!(restarted.history.snapshots().unwrap().len(), 2);
+
+        let mut missing = Watcher::open(
+            Watch::new(format!("{}/gone", url), None, Normalize::default()).unwrap(),
+            history.path(),
+        )
+        .unwrap();
+        assert!(matches!(
+            missing.check(&client).await,
+            Err(CheckError::Status(status)) if status == StatusCode::NOT_FOUND
+        ));
+    }
+}